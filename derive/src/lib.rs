@@ -0,0 +1,88 @@
+//! Derive macros for `clickhouse-readonly`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{ext::IdentExt, parse_macro_input, Data, DeriveInput, Error, Fields, LitStr};
+
+/// Derives `FromRow`, mapping struct fields to columns with the same name.
+///
+/// A column name can be overridden with `#[clickhouse(rename = "column")]`.
+#[proc_macro_derive(FromRow, attributes(clickhouse))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_from_row(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_from_row(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(data) => match data.fields {
+            Fields::Named(fields) => fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    input.ident,
+                    "FromRow can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                input.ident,
+                "FromRow can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut getters = Vec::with_capacity(fields.len());
+    for field in fields {
+        let ident = field.ident.expect("named field");
+        let ty = field.ty;
+        // `r#type` reads the column `type`.
+        let column = column_name(&field.attrs)?.unwrap_or_else(|| ident.unraw().to_string());
+
+        getters.push(quote! {
+            #ident: row.get::<#ty, _>(#column)?
+        });
+    }
+
+    let name = input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::clickhouse_readonly::FromRow for #name #ty_generics #where_clause {
+            fn from_row<'a>(
+                row: &'a ::clickhouse_readonly::Row<'a, ::clickhouse_readonly::Simple>,
+            ) -> ::clickhouse_readonly::ClickhouseResult<Self> {
+                Ok(Self {
+                    #(#getters,)*
+                })
+            }
+        }
+    })
+}
+
+/// Returns the column name from `#[clickhouse(rename = "...")]`, if any.
+fn column_name(attrs: &[syn::Attribute]) -> syn::Result<Option<String>> {
+    let mut rename = None;
+
+    for attr in attrs {
+        if !attr.path().is_ident("clickhouse") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let value: LitStr = meta.value()?.parse()?;
+                rename = Some(value.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported clickhouse attribute"))
+            }
+        })?;
+    }
+
+    Ok(rename)
+}
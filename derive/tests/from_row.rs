@@ -0,0 +1,65 @@
+use clickhouse_readonly::{Block, FromRow};
+
+#[derive(Debug, PartialEq, FromRow)]
+struct User {
+    id: u64,
+    #[clickhouse(rename = "user_name")]
+    name: String,
+    email: Option<String>,
+}
+
+#[test]
+fn test_from_row() {
+    let block = Block::new()
+        .column("id", vec![1_u64, 2])
+        .column("user_name", vec!["alice", "bob"])
+        .column("email", vec![Some("alice@example.com".to_string()), None]);
+
+    let users: Vec<User> = block
+        .rows()
+        .map(|row| User::from_row(&row))
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(
+        users,
+        vec![
+            User {
+                id: 1,
+                name: "alice".to_string(),
+                email: Some("alice@example.com".to_string()),
+            },
+            User {
+                id: 2,
+                name: "bob".to_string(),
+                email: None,
+            },
+        ]
+    );
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+struct Event {
+    r#type: String,
+}
+
+#[test]
+fn test_raw_identifier() {
+    let block = Block::new().column("type", vec!["click"]);
+    let row = block.rows().next().unwrap();
+
+    assert_eq!(
+        Event::from_row(&row).unwrap(),
+        Event {
+            r#type: "click".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_missing_column() {
+    let block = Block::new().column("id", vec![1_u64]);
+    let row = block.rows().next().unwrap();
+
+    assert!(User::from_row(&row).is_err());
+}
@@ -14,7 +14,7 @@ async fn main() -> ClickhouseResult<()> {
         "password".to_string(),
         true,
     )
-    .build();
+    .build()?;
 
     let pool = Pool::new(config);
     let mut handle = pool.get_handle().await?;
@@ -0,0 +1,286 @@
+//! Conversion of blocks into Arrow record batches, enabled by the `arrow` feature.
+
+use std::{slice, sync::Arc};
+
+use arrow_array::{
+    types::{
+        ArrowPrimitiveType, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
+        UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+    },
+    ArrayRef, BooleanArray, FixedSizeBinaryArray, ListArray, NullArray, PrimitiveArray,
+    RecordBatch, StringArray,
+};
+use arrow_buffer::{NullBuffer, OffsetBuffer};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use either::Either;
+
+use crate::{
+    block::Block,
+    column::{iter::Iterable, Column, Simple},
+    error::{Error, FromSqlError, Result},
+    types::{FromSql, SqlType},
+    value::ValueRef,
+};
+
+impl Block<Simple> {
+    /// Converts the block into an Arrow record batch, copying the data.
+    ///
+    /// `String` becomes `Utf8` and fails on invalid UTF-8, `FixedString(n)` becomes
+    /// `FixedSizeBinary(n)`, `Int256` and `UInt256` become `FixedSizeBinary(32)` in
+    /// little-endian order, and `Nothing` becomes `Null`. `LowCardinality` columns are
+    /// materialized. `Map` and `Tuple` are not supported.
+    pub fn to_record_batch(&self) -> Result<RecordBatch> {
+        let mut fields = Vec::with_capacity(self.column_count());
+        let mut arrays = Vec::with_capacity(self.column_count());
+        for column in self.columns() {
+            fields.push(field(column.name(), &column.sql_type())?);
+            arrays.push(column_array(column)?);
+        }
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays).map_err(arrow_error)
+    }
+}
+
+fn arrow_error(err: ArrowError) -> Error {
+    Error::Other(format!("Arrow error: {}", err).into())
+}
+
+/// Returns the type without `LowCardinality` and `Nullable`, and whether it is nullable.
+fn base_type(sql_type: &SqlType) -> (&SqlType, bool) {
+    match sql_type {
+        SqlType::LowCardinality(inner) => base_type(inner),
+        SqlType::Nullable(inner) => (base_type(inner).0, true),
+        SqlType::Nothing => (sql_type, true),
+        _ => (sql_type, false),
+    }
+}
+
+fn field(name: &str, sql_type: &SqlType) -> Result<Field> {
+    let (sql_type, nullable) = base_type(sql_type);
+    Ok(Field::new(name, data_type(sql_type)?, nullable))
+}
+
+fn data_type(sql_type: &SqlType) -> Result<DataType> {
+    Ok(match sql_type {
+        SqlType::Bool => DataType::Boolean,
+        SqlType::UInt8 => DataType::UInt8,
+        SqlType::UInt16 => DataType::UInt16,
+        SqlType::UInt32 => DataType::UInt32,
+        SqlType::UInt64 => DataType::UInt64,
+        SqlType::Int8 => DataType::Int8,
+        SqlType::Int16 => DataType::Int16,
+        SqlType::Int32 => DataType::Int32,
+        SqlType::Int64 => DataType::Int64,
+        SqlType::Float32 => DataType::Float32,
+        SqlType::Float64 => DataType::Float64,
+        SqlType::Int256 | SqlType::UInt256 => DataType::FixedSizeBinary(32),
+        SqlType::String => DataType::Utf8,
+        SqlType::FixedString(len) => DataType::FixedSizeBinary(*len as i32),
+        SqlType::Nothing => DataType::Null,
+        SqlType::Array(inner) => DataType::List(Arc::new(field("item", inner)?)),
+        SqlType::Nullable(_) | SqlType::LowCardinality(_) => data_type(base_type(sql_type).0)?,
+        SqlType::Map(_, _) | SqlType::Tuple(_) => {
+            return Err(Error::FromSql(FromSqlError::UnsupportedColumnType(
+                format!("{} in Arrow", sql_type).into(),
+            )))
+        }
+    })
+}
+
+fn column_array(column: &Column<Simple>) -> Result<ArrayRef> {
+    // Columns without nulls are copied at once.
+    Ok(match column.sql_type() {
+        SqlType::UInt8 => slice_array::<UInt8Type>(column)?,
+        SqlType::UInt16 => slice_array::<UInt16Type>(column)?,
+        SqlType::UInt32 => slice_array::<UInt32Type>(column)?,
+        SqlType::UInt64 => slice_array::<UInt64Type>(column)?,
+        SqlType::Int8 => slice_array::<Int8Type>(column)?,
+        SqlType::Int16 => slice_array::<Int16Type>(column)?,
+        SqlType::Int32 => slice_array::<Int32Type>(column)?,
+        SqlType::Int64 => slice_array::<Int64Type>(column)?,
+        SqlType::Float32 => slice_array::<Float32Type>(column)?,
+        SqlType::Float64 => slice_array::<Float64Type>(column)?,
+        sql_type => {
+            let values = (0..column.len()).map(|row| non_null(column.at(row)));
+            values_array(base_type(&sql_type).0, values.collect())?
+        }
+    })
+}
+
+fn slice_array<T>(column: &Column<Simple>) -> Result<ArrayRef>
+where
+    T: ArrowPrimitiveType,
+    for<'a> T::Native: Iterable<'a, Simple, Iter = slice::Iter<'a, T::Native>>,
+{
+    let values = column.iter::<T::Native>()?.as_slice().to_vec();
+    Ok(Arc::new(PrimitiveArray::<T>::from_iter_values(values)))
+}
+
+fn non_null(value: ValueRef) -> Option<ValueRef> {
+    match value {
+        ValueRef::Nullable(Either::Left(_)) => None,
+        ValueRef::Nullable(Either::Right(value)) => Some(*value),
+        value => Some(value),
+    }
+}
+
+/// Builds an array of `sql_type`, which is neither `Nullable` nor `LowCardinality`,
+/// `None` standing for NULL.
+fn values_array(sql_type: &SqlType, values: Vec<Option<ValueRef>>) -> Result<ArrayRef> {
+    Ok(match sql_type {
+        SqlType::Bool => Arc::new(BooleanArray::from(convert::<bool>(values)?)),
+        SqlType::UInt8 => primitive_array::<UInt8Type>(values)?,
+        SqlType::UInt16 => primitive_array::<UInt16Type>(values)?,
+        SqlType::UInt32 => primitive_array::<UInt32Type>(values)?,
+        SqlType::UInt64 => primitive_array::<UInt64Type>(values)?,
+        SqlType::Int8 => primitive_array::<Int8Type>(values)?,
+        SqlType::Int16 => primitive_array::<Int16Type>(values)?,
+        SqlType::Int32 => primitive_array::<Int32Type>(values)?,
+        SqlType::Int64 => primitive_array::<Int64Type>(values)?,
+        SqlType::Float32 => primitive_array::<Float32Type>(values)?,
+        SqlType::Float64 => primitive_array::<Float64Type>(values)?,
+        SqlType::String => Arc::new(StringArray::from(convert::<&str>(values)?)),
+        SqlType::FixedString(len) => {
+            let values = convert::<&[u8]>(values)?;
+            let array = FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                values.into_iter(),
+                *len as i32,
+            );
+            Arc::new(array.map_err(arrow_error)?)
+        }
+        SqlType::Int256 | SqlType::UInt256 => {
+            let values = values.into_iter().map(|value| match value {
+                Some(ValueRef::Int256(v)) => Some(v.to_le_bytes()),
+                Some(ValueRef::UInt256(v)) => Some(v.to_le_bytes()),
+                _ => None,
+            });
+            let array = FixedSizeBinaryArray::try_from_sparse_iter_with_size(values, 32);
+            Arc::new(array.map_err(arrow_error)?)
+        }
+        SqlType::Nothing => Arc::new(NullArray::new(values.len())),
+        SqlType::Array(inner) => list_array(inner, values)?,
+        _ => return Err(data_type(sql_type).unwrap_err()),
+    })
+}
+
+fn convert<'a, T: FromSql<'a>>(values: Vec<Option<ValueRef<'a>>>) -> Result<Vec<Option<T>>> {
+    values
+        .into_iter()
+        .map(|value| value.map(T::from_sql).transpose())
+        .collect()
+}
+
+fn primitive_array<T>(values: Vec<Option<ValueRef>>) -> Result<ArrayRef>
+where
+    T: ArrowPrimitiveType,
+    for<'a> T::Native: FromSql<'a>,
+{
+    let values = convert::<T::Native>(values)?;
+    Ok(Arc::new(values.into_iter().collect::<PrimitiveArray<T>>()))
+}
+
+fn list_array(inner: &SqlType, values: Vec<Option<ValueRef>>) -> Result<ArrayRef> {
+    let mut lengths = Vec::with_capacity(values.len());
+    let mut items = Vec::new();
+    for value in &values {
+        match value {
+            Some(ValueRef::Array(_, vs)) => {
+                lengths.push(vs.len());
+                items.extend(vs.iter().cloned().map(non_null));
+            }
+            _ => lengths.push(0),
+        }
+    }
+
+    let nulls = values
+        .iter()
+        .any(Option::is_none)
+        .then(|| NullBuffer::from_iter(values.iter().map(Option::is_some)));
+    let items = values_array(base_type(inner).0, items)?;
+
+    let array = ListArray::try_new(
+        Arc::new(field("item", inner)?),
+        OffsetBuffer::from_lengths(lengths),
+        items,
+        nulls,
+    );
+    Ok(Arc::new(array.map_err(arrow_error)?))
+}
+
+#[cfg(test)]
+mod test {
+    use arrow_array::{cast::AsArray, Array};
+
+    use super::*;
+    use crate::{
+        column::{self, ArcColumnWrapper, ColumnData},
+        value::Value,
+    };
+
+    #[test]
+    fn test_to_record_batch() {
+        let item_type = SqlType::Nullable(SqlType::UInt32.into());
+        let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(
+            SqlType::Array(item_type.clone().into()),
+            2,
+        )
+        .unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            for row in [vec![Some(1_u32), None], vec![]] {
+                let values = row.into_iter().map(Value::from).collect();
+                data.push(Value::Array(item_type.clone().into(), Arc::new(values)));
+            }
+        }
+
+        let block = Block::new()
+            .column("id", vec![1_u64, 2])
+            .column("name", vec![Some("foo"), None])
+            .column("vals", column::new_column::<Simple>("vals", data));
+        let batch = block.to_record_batch().unwrap();
+
+        assert_eq!(batch.num_rows(), 2);
+        let schema = batch.schema();
+        assert_eq!(schema.field(0).data_type(), &DataType::UInt64);
+        assert!(!schema.field(0).is_nullable());
+        assert_eq!(schema.field(1).data_type(), &DataType::Utf8);
+        assert!(schema.field(1).is_nullable());
+
+        let ids = batch.column(0).as_primitive::<UInt64Type>();
+        assert_eq!(ids.values(), &[1, 2]);
+
+        let names = batch.column(1).as_string::<i32>();
+        assert_eq!(names.value(0), "foo");
+        assert!(names.is_null(1));
+
+        let vals = batch.column(2).as_list::<i32>();
+        let first = vals.value(0);
+        let first = first.as_primitive::<UInt32Type>();
+        assert_eq!(first.value(0), 1);
+        assert!(first.is_null(1));
+        assert_eq!(vals.value(1).len(), 0);
+    }
+
+    #[test]
+    fn test_to_record_batch_int256() {
+        let value = ethnum::U256::from(258_u32);
+        let block = Block::new().column("n", vec![value]);
+        let batch = block.to_record_batch().unwrap();
+
+        let array = batch.column(0).as_fixed_size_binary();
+        assert_eq!(array.value(0), value.to_le_bytes());
+    }
+
+    #[test]
+    fn test_to_record_batch_unsupported() {
+        let sql_type = SqlType::Map(SqlType::String.into(), SqlType::UInt8.into());
+        let data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, 0).unwrap();
+        let block = Block::new().column("m", column::new_column::<Simple>("m", data));
+
+        let err = block.to_record_batch().unwrap_err();
+        assert!(matches!(
+            err,
+            Error::FromSql(FromSqlError::UnsupportedColumnType(_))
+        ));
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    cmp,
+    io::{self, Read},
+};
+
+use crate::{
+    binary::ReadEx,
+    error::{DriverError, Error, Result},
+    types::Compression,
+};
+
+const CHECKSUM_SIZE: usize = 16;
+/// Method byte followed by compressed and uncompressed sizes.
+const HEADER_SIZE: usize = 9;
+const MAX_COMPRESSED_SIZE: usize = 0x4000_0000;
+/// The decompressed size from the header is allocated up front, so it is bounded too.
+const MAX_DECOMPRESSED_SIZE: usize = 0x4000_0000;
+
+const METHOD_NONE: u8 = 0x02;
+const METHOD_LZ4: u8 = 0x82;
+const METHOD_ZSTD: u8 = 0x90;
+
+const ZSTD_LEVEL: i32 = 1;
+
+/// Reader over a sequence of compressed frames, decompressing them on demand.
+pub(crate) struct CompressedReader<'a, R> {
+    reader: &'a mut R,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<'a, R: Read> CompressedReader<'a, R> {
+    pub(crate) fn new(reader: &'a mut R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<'a, R: Read> Read for CompressedReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Skip empty frames, otherwise `Ok(0)` would be taken for the end of data.
+        while self.position == self.buffer.len() {
+            self.buffer = decompress_frame(&mut *self.reader)?;
+            self.position = 0;
+        }
+
+        let n = cmp::min(buf.len(), self.buffer.len() - self.position);
+        buf[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+fn decompress_frame<R: ReadEx>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut checksum = [0_u8; CHECKSUM_SIZE];
+    reader.read_bytes(&mut checksum)?;
+
+    let mut header = [0_u8; HEADER_SIZE];
+    reader.read_bytes(&mut header)?;
+
+    let method = header[0];
+    let compressed_size = u32::from_le_bytes(header[1..5].try_into().unwrap()) as usize;
+    let original_size = u32::from_le_bytes(header[5..9].try_into().unwrap()) as usize;
+
+    if !(HEADER_SIZE..=MAX_COMPRESSED_SIZE).contains(&compressed_size) {
+        return Err(Error::Driver(DriverError::BadCompressedPacketHeader));
+    }
+    if original_size > MAX_DECOMPRESSED_SIZE {
+        return Err(Error::Driver(DriverError::MalformedData(format!(
+            "decompressed size {} of a frame exceeds {}.",
+            original_size, MAX_DECOMPRESSED_SIZE
+        ))));
+    }
+
+    let mut frame = vec![0_u8; compressed_size];
+    frame[..HEADER_SIZE].copy_from_slice(&header);
+    reader.read_bytes(&mut frame[HEADER_SIZE..])?;
+
+    if u128::from_le_bytes(checksum) != city_hash_128(&frame) {
+        return Err(Error::Driver(DriverError::BadHash));
+    }
+
+    let data = &frame[HEADER_SIZE..];
+    let decompressed = match method {
+        _ if original_size == 0 => Vec::new(),
+        METHOD_NONE => data.to_vec(),
+        METHOD_LZ4 => lz4_flex::block::decompress(data, original_size)
+            .map_err(|err| DriverError::Decompression(err.to_string()))?,
+        METHOD_ZSTD => zstd::bulk::decompress(data, original_size)
+            .map_err(|err| DriverError::Decompression(err.to_string()))?,
+        method => {
+            return Err(Error::Driver(DriverError::UnknownCompressionMethod {
+                method,
+            }))
+        }
+    };
+
+    if decompressed.len() != original_size {
+        return Err(Error::Driver(DriverError::BadCompressedPacketHeader));
+    }
+
+    Ok(decompressed)
+}
+
+/// Packs `data` into a single frame compressed with the given method.
+pub(crate) fn compress_buffer(data: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    let (method, compressed) = match compression {
+        Compression::None => (METHOD_NONE, data.to_vec()),
+        Compression::Lz4 => (METHOD_LZ4, lz4_flex::block::compress(data)),
+        Compression::Zstd => (METHOD_ZSTD, zstd::bulk::compress(data, ZSTD_LEVEL)?),
+    };
+
+    let mut frame = Vec::with_capacity(HEADER_SIZE + compressed.len());
+    frame.push(method);
+    frame.extend_from_slice(&((HEADER_SIZE + compressed.len()) as u32).to_le_bytes());
+    frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&compressed);
+
+    let mut result = Vec::with_capacity(CHECKSUM_SIZE + frame.len());
+    result.extend_from_slice(&city_hash_128(&frame).to_le_bytes());
+    result.extend_from_slice(&frame);
+    Ok(result)
+}
+
+/// CityHash128 with the halves laid out the way Clickhouse writes them.
+fn city_hash_128(data: &[u8]) -> u128 {
+    cityhash_rs::cityhash_102_128(data).rotate_left(64)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_compress_and_read() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 7) as u8).collect();
+
+        for compression in [Compression::Lz4, Compression::Zstd] {
+            let mut buffer = compress_buffer(&data[..5_000], compression).unwrap();
+            buffer.extend(compress_buffer(&[], compression).unwrap());
+            buffer.extend(compress_buffer(&data[5_000..], compression).unwrap());
+
+            let mut cursor = Cursor::new(buffer);
+            let mut reader = CompressedReader::new(&mut cursor);
+            let mut actual = vec![0_u8; data.len()];
+            reader.read_bytes(&mut actual).unwrap();
+
+            assert_eq!(actual, data);
+        }
+    }
+
+    #[test]
+    fn test_corrupt_zstd_frame() {
+        let mut buffer = compress_buffer(b"some block data", Compression::Zstd).unwrap();
+        let last = buffer.len() - 1;
+        buffer[CHECKSUM_SIZE + HEADER_SIZE..last].fill(0);
+        let frame = buffer[CHECKSUM_SIZE..].to_vec();
+        buffer[..CHECKSUM_SIZE].copy_from_slice(&city_hash_128(&frame).to_le_bytes());
+
+        let mut cursor = Cursor::new(buffer);
+        let mut reader = CompressedReader::new(&mut cursor);
+        let mut actual = [0_u8; 4];
+
+        match reader.read_bytes(&mut actual) {
+            Err(Error::Driver(DriverError::Decompression(_))) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decompressed_size_too_large() {
+        let mut buffer = compress_buffer(b"some block data", Compression::Lz4).unwrap();
+        let size = CHECKSUM_SIZE + 5;
+        buffer[size..size + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut cursor = Cursor::new(buffer);
+        let mut reader = CompressedReader::new(&mut cursor);
+        let mut actual = [0_u8; 4];
+
+        match reader.read_bytes(&mut actual) {
+            Err(Error::Driver(DriverError::MalformedData(_))) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bad_checksum() {
+        let mut buffer = compress_buffer(b"some block data", Compression::Lz4).unwrap();
+        buffer[0] ^= 0xff;
+
+        let mut cursor = Cursor::new(buffer);
+        let mut reader = CompressedReader::new(&mut cursor);
+        let mut actual = [0_u8; 4];
+
+        match reader.read_bytes(&mut actual) {
+            Err(Error::Driver(DriverError::BadHash)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
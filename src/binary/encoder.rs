@@ -49,4 +49,42 @@ impl Encoder {
     pub fn get_buffer_ref(&self) -> &[u8] {
         self.buffer.as_ref()
     }
+
+    /// Empties the buffer, keeping its capacity for the next message.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+/// Writes after the bytes of `buffer`, call `clear` to reuse only its allocation.
+impl From<Vec<u8>> for Encoder {
+    fn from(buffer: Vec<u8>) -> Self {
+        Encoder { buffer }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_clear_keeps_capacity() {
+        let mut encoder = Encoder::new();
+        encoder.string("SELECT 1");
+        encoder.write(42_u64);
+        let capacity = encoder.buffer.capacity();
+
+        encoder.clear();
+        assert!(encoder.get_buffer_ref().is_empty());
+        assert_eq!(encoder.buffer.capacity(), capacity);
+
+        encoder.uvarint(300);
+        assert_eq!(encoder.get_buffer_ref(), [0xac, 0x02]);
+        assert_eq!(encoder.buffer.capacity(), capacity);
+
+        let mut reused = Encoder::from(encoder.get_buffer());
+        reused.clear();
+        reused.write(1_u8);
+        assert_eq!(reused.get_buffer(), [1]);
+    }
 }
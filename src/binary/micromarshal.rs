@@ -1,4 +1,4 @@
-use ethnum::I256;
+use ethnum::{I256, U256};
 
 // Marshal //
 
@@ -19,7 +19,7 @@ macro_rules! impl_marshal {
 }
 
 impl_marshal! {
-    u16, u32, u64, i16, i32, i64, I256
+    u16, u32, u64, i16, i32, i64, I256, U256
 }
 
 impl Marshal for u8 {
@@ -91,7 +91,7 @@ impl Unmarshal<i8> for i8 {
 }
 
 impl_unmarshal! {
-    u16, u32, u64, i16, i32, i64, I256
+    u16, u32, u64, i16, i32, i64, I256, U256
 }
 
 impl Unmarshal<f32> for f32 {
@@ -121,6 +121,8 @@ mod test {
     use rand::distributions::{Distribution, Standard};
     use rand::random;
 
+    use ethnum::U256;
+
     use super::{Marshal, Unmarshal};
     use crate::types::StatBuffer;
 
@@ -194,4 +196,19 @@ mod test {
     fn test_bool() {
         test_some::<bool>()
     }
+
+    #[test]
+    fn test_u256() {
+        for v in [
+            U256::ZERO,
+            U256::ONE,
+            U256::from_words(random(), random()),
+            U256::MAX,
+        ] {
+            let mut buffer = U256::buffer();
+            v.marshal(buffer.as_mut());
+            assert_eq!(buffer, v.to_le_bytes());
+            assert_eq!(U256::unmarshal(buffer.as_ref()), v);
+        }
+    }
 }
@@ -1,9 +1,11 @@
+pub(crate) use self::compressed::{compress_buffer, CompressedReader};
 pub use self::encoder::Encoder;
 pub use self::micromarshal::{Marshal, Unmarshal};
 pub(crate) use self::parser::Parser;
 pub(crate) use self::read_ex::ReadEx;
 pub use self::uvarint::put_uvarint;
 
+mod compressed;
 mod encoder;
 mod parser;
 mod read_ex;
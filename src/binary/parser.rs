@@ -4,17 +4,23 @@ use chrono_tz::Tz;
 use log::{trace, warn};
 
 use crate::{
-    binary::ReadEx,
-    block::Block,
+    binary::{CompressedReader, ReadEx},
+    block::{Block, BlockLimit},
     error::{DriverError, Error, Result, ServerError},
     protocol,
-    types::{Packet, ProfileInfo, Progress, ServerInfo},
+    types::{Compression, Packet, ProfileInfo, Progress, ServerInfo},
 };
 
 /// The internal clickhouse response parser.
 pub(crate) struct Parser<T> {
     reader: T,
     tz: Option<Tz>,
+    /// Protocol revision of the connection, 0 before `Hello`.
+    revision: u64,
+    compression: Compression,
+    /// Position of the reader in the whole server stream, reported in errors.
+    offset: u64,
+    block_limit: Option<BlockLimit>,
 }
 
 /// The parser can be used to parse clickhouse responses into values.  Generally
@@ -26,8 +32,29 @@ impl<T: Read> Parser<T> {
     /// than one value can be behind the reader in which case the parser can
     /// be invoked multiple times.  In other words: the stream does not have
     /// to be terminated.
-    pub(crate) fn new(reader: T, tz: Option<Tz>) -> Parser<T> {
-        Self { reader, tz }
+    ///
+    /// `offset` is the number of bytes of the server stream consumed before the reader.
+    pub(crate) fn new(
+        reader: T,
+        tz: Option<Tz>,
+        revision: u64,
+        compression: Compression,
+        offset: u64,
+    ) -> Parser<T> {
+        Self {
+            reader,
+            tz,
+            revision,
+            compression,
+            offset,
+            block_limit: None,
+        }
+    }
+
+    /// Fails data packets with blocks that would exceed `limit`.
+    pub(crate) fn with_block_limit(mut self, limit: Option<BlockLimit>) -> Self {
+        self.block_limit = limit;
+        self
     }
 
     /// Parses a single value out of the stream. If there are multiple
@@ -42,46 +69,76 @@ impl<T: Read> Parser<T> {
             protocol::SERVER_PROFILE_INFO => Ok(self.parse_profile_info()?),
             protocol::SERVER_EXCEPTION => Ok(self.parse_exception()?),
             protocol::SERVER_DATA | protocol::SERVER_TOTALS | protocol::SERVER_EXTREMES => {
-                Ok(self.parse_block()?)
+                Ok(self.parse_block(packet)?)
             }
             protocol::SERVER_END_OF_STREAM => Ok(Packet::Eof(())),
-            packet => Err(Error::Driver(DriverError::UnknownPacket { packet })),
+            packet => Err(Error::Driver(DriverError::UnknownPacket {
+                packet,
+                offset: self.offset,
+            })),
         }
     }
 
-    fn parse_block(&mut self) -> Result<Packet<()>> {
+    fn parse_block(&mut self, packet: u64) -> Result<Packet<()>> {
         match self.tz {
-            None => Err(Error::Driver(DriverError::UnexpectedPacket)),
+            // Data before `Hello`, the server timezone is not known yet.
+            None => Err(Error::Driver(DriverError::UnexpectedPacketAt {
+                packet,
+                offset: self.offset,
+            })),
             Some(tz) => {
                 self.reader.skip_string()?;
-                let block = Block::load(&mut self.reader, tz)?;
-                Ok(Packet::Block(block))
+                let block = if self.compression.is_enabled() {
+                    let mut reader = CompressedReader::new(&mut self.reader);
+                    Block::load_with_limit(&mut reader, tz, self.block_limit)?
+                } else {
+                    Block::load_with_limit(&mut self.reader, tz, self.block_limit)?
+                };
+                Ok(match packet {
+                    protocol::SERVER_TOTALS => Packet::Totals(block),
+                    protocol::SERVER_EXTREMES => Packet::Extremes(block),
+                    _ => Packet::Block(block),
+                })
             }
         }
     }
 
     fn parse_server_info(&mut self) -> Result<Packet<()>> {
-        let server_info = ServerInfo {
+        let mut server_info = ServerInfo {
             name: self.reader.read_string()?,
             major_version: self.reader.read_uvarint()?,
             minor_version: self.reader.read_uvarint()?,
             revision: self.reader.read_uvarint()?,
-            timezone: match self.reader.read_string()?.parse() {
-                Ok(tz) => tz,
-                Err(err) => return Err(io::Error::new(io::ErrorKind::Other, err).into()),
-            },
+            ..ServerInfo::default()
+        };
+        server_info.timezone = match self.reader.read_string()?.parse() {
+            Ok(tz) => tz,
+            Err(err) => return Err(io::Error::new(io::ErrorKind::Other, err).into()),
         };
 
+        let revision = server_info.protocol_revision();
+        if revision >= protocol::DBMS_MIN_REVISION_WITH_SERVER_DISPLAY_NAME {
+            server_info.display_name = self.reader.read_string()?;
+        }
+        if revision >= protocol::DBMS_MIN_REVISION_WITH_VERSION_PATCH {
+            server_info.patch_version = self.reader.read_uvarint()?;
+        }
+
         trace!("[hello]        <- {:?}", &server_info);
         Ok(Packet::Hello((), server_info))
     }
 
     fn parse_progress(&mut self) -> Result<Packet<()>> {
-        let progress = Progress {
+        let mut progress = Progress {
             rows: self.reader.read_uvarint()?,
             bytes: self.reader.read_uvarint()?,
             total_rows: self.reader.read_uvarint()?,
+            ..Progress::default()
         };
+        if self.revision >= protocol::DBMS_MIN_REVISION_WITH_CLIENT_WRITE_INFO {
+            progress.written_rows = self.reader.read_uvarint()?;
+            progress.written_bytes = self.reader.read_uvarint()?;
+        }
 
         trace!(
             "[process] <- Progress: rows={}, bytes={}, total rows={}",
@@ -108,19 +165,220 @@ impl<T: Read> Parser<T> {
     }
 
     fn parse_exception(&mut self) -> Result<Packet<()>> {
-        let exception = ServerError {
-            code: self.reader.read_scalar()?,
-            name: self.reader.read_string()?,
-            message: self.reader.read_string()?,
-            stack_trace: self.reader.read_string()?,
-        };
+        let exception = self.parse_server_error()?;
 
         warn!("server exception: {:?}", exception);
         Ok(Packet::Exception(exception))
     }
 
+    fn parse_server_error(&mut self) -> Result<ServerError> {
+        let code = self.reader.read_scalar()?;
+        let name = self.reader.read_string()?;
+        let message = self.reader.read_string()?;
+        let stack_trace = self.reader.read_string()?;
+        let has_nested: u8 = self.reader.read_scalar()?;
+
+        let nested = match has_nested {
+            0 => None,
+            _ => Some(Box::new(self.parse_server_error()?)),
+        };
+
+        Ok(ServerError {
+            code,
+            name,
+            message,
+            stack_trace,
+            nested,
+        })
+    }
+
     fn parse_pong(&self) -> Packet<()> {
         trace!("[process]      <- pong");
         Packet::Pong(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::binary::Encoder;
+
+    fn parse(bytes: &[u8], tz: Option<Tz>, offset: u64) -> Result<Packet<()>> {
+        let mut parser = Parser::new(Cursor::new(bytes), tz, 0, Compression::None, offset);
+        parser.parse_packet()
+    }
+
+    #[test]
+    fn test_unknown_packet() {
+        match parse(&[0x7f], None, 42) {
+            Err(Error::Driver(DriverError::UnknownPacket { packet, offset })) => {
+                assert_eq!(packet, 0x7f);
+                assert_eq!(offset, 42);
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_totals_and_extremes() {
+        let block = Block::new().column("x", vec![1_u32, 2]);
+        for code in [
+            protocol::SERVER_DATA,
+            protocol::SERVER_TOTALS,
+            protocol::SERVER_EXTREMES,
+        ] {
+            let mut bytes = vec![code as u8, 0];
+            bytes.extend(block.to_bytes());
+
+            let packet = parse(&bytes, Some(Tz::Zulu), 0).unwrap();
+            assert_eq!(packet.code(), code);
+            match packet {
+                Packet::Block(b) | Packet::Totals(b) | Packet::Extremes(b) => assert_eq!(b, block),
+                packet => panic!("unexpected packet {:?}", packet),
+            }
+        }
+    }
+
+    fn write_exception(encoder: &mut Encoder, code: i32, message: &str, has_nested: bool) {
+        encoder.write(code);
+        encoder.string("DB::Exception");
+        encoder.string(message);
+        encoder.string("");
+        encoder.write(u8::from(has_nested));
+    }
+
+    #[test]
+    fn test_nested_exception() {
+        let mut encoder = Encoder::new();
+        encoder.uvarint(protocol::SERVER_EXCEPTION);
+        write_exception(&mut encoder, 241, "Memory limit exceeded", true);
+        write_exception(&mut encoder, 159, "Timeout exceeded", false);
+        encoder.uvarint(protocol::SERVER_END_OF_STREAM);
+
+        let mut reader = Cursor::new(encoder.get_buffer());
+        let mut parser = Parser::new(&mut reader, None, 0, Compression::None, 0);
+
+        match parser.parse_packet().unwrap() {
+            Packet::Exception(e) => {
+                assert_eq!(e.code(), 241);
+                assert_eq!(e.message(), "Memory limit exceeded");
+                assert!(!e.is_retriable());
+
+                let nested = e.nested().unwrap();
+                assert_eq!(nested.code(), 159);
+                assert!(nested.is_retriable());
+                assert!(nested.nested().is_none());
+            }
+            packet => panic!("unexpected packet {:?}", packet),
+        }
+        assert!(matches!(parser.parse_packet().unwrap(), Packet::Eof(())));
+    }
+
+    #[test]
+    fn test_data_before_hello() {
+        match parse(&[protocol::SERVER_TOTALS as u8], None, 7) {
+            Err(Error::Driver(DriverError::UnexpectedPacketAt { packet, offset })) => {
+                assert_eq!(packet, protocol::SERVER_TOTALS);
+                assert_eq!(offset, 7);
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_unknown_packet_message() {
+        let err = parse(&[0x7f], None, 42).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Driver error: `Unknown packet 0x7f at byte 42 of the stream.`"
+        );
+    }
+
+    #[test]
+    fn test_server_info_revisions() {
+        for (revision, display_name, patch_version) in [
+            (54213, "", 0),
+            (54372, "clickhouse-1", 0),
+            (54465, "clickhouse-1", 3),
+        ] {
+            let mut encoder = Encoder::new();
+            encoder.uvarint(protocol::SERVER_HELLO);
+            encoder.string("ClickHouse");
+            encoder.uvarint(23);
+            encoder.uvarint(8);
+            encoder.uvarint(revision);
+            encoder.string("Europe/Berlin");
+            if revision >= protocol::DBMS_MIN_REVISION_WITH_SERVER_DISPLAY_NAME {
+                encoder.string(display_name);
+            }
+            if revision >= protocol::DBMS_MIN_REVISION_WITH_VERSION_PATCH {
+                encoder.uvarint(patch_version);
+            }
+            encoder.uvarint(protocol::SERVER_END_OF_STREAM);
+
+            let mut reader = Cursor::new(encoder.get_buffer());
+            let mut parser = Parser::new(&mut reader, None, 0, Compression::None, 0);
+            match parser.parse_packet().unwrap() {
+                Packet::Hello(_, info) => {
+                    assert_eq!(info.revision, revision);
+                    assert_eq!(info.display_name, display_name);
+                    assert_eq!(info.patch_version, patch_version);
+                    assert_eq!(info.timezone, Tz::Europe__Berlin);
+                }
+                packet => panic!("unexpected packet {:?}", packet),
+            }
+            assert!(matches!(parser.parse_packet().unwrap(), Packet::Eof(())));
+        }
+    }
+
+    #[test]
+    fn test_progress_written() {
+        let mut encoder = Encoder::new();
+        encoder.uvarint(protocol::SERVER_PROGRESS);
+        for value in [10_u64, 100, 1000, 2, 20] {
+            encoder.uvarint(value);
+        }
+
+        let mut reader = Cursor::new(encoder.get_buffer());
+        let revision = protocol::DBMS_MIN_REVISION_WITH_CLIENT_WRITE_INFO;
+        let mut parser = Parser::new(&mut reader, None, revision, Compression::None, 0);
+        match parser.parse_packet().unwrap() {
+            Packet::Progress(progress) => {
+                assert_eq!(progress.total_rows, 1000);
+                assert_eq!(progress.written_rows, 2);
+                assert_eq!(progress.written_bytes, 20);
+            }
+            packet => panic!("unexpected packet {:?}", packet),
+        }
+    }
+
+    #[test]
+    fn test_block_limit() {
+        let block = Block::new()
+            .column("id", vec![1_u64, 2, 3])
+            .column("name", vec!["a", "b", "c"]);
+        let mut bytes = vec![protocol::SERVER_DATA as u8, 0];
+        bytes.extend(block.to_bytes());
+
+        // 3 * 8 bytes of ids and 3 * 10 bytes of names.
+        for (max_bytes, fits) in [(54, true), (53, false), (23, false)] {
+            let limit = BlockLimit {
+                max_bytes,
+                string_bytes: 10,
+            };
+            let mut parser =
+                Parser::new(Cursor::new(&bytes), Some(Tz::Zulu), 0, Compression::None, 0)
+                    .with_block_limit(Some(limit));
+            match parser.parse_packet() {
+                Ok(Packet::Block(b)) if fits => assert_eq!(b, block),
+                Err(Error::Driver(DriverError::BlockTooLarge { estimate, limit })) if !fits => {
+                    assert!(estimate > max_bytes);
+                    assert_eq!(limit, max_bytes);
+                }
+                res => panic!("unexpected result {:?}", res),
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::binary::{Encoder, ReadEx};
-use crate::error::{DriverError, Error, Result};
+use crate::error::{Error, Result};
 
 const BLOCK_INFO_OVERFLOWS: u64 = 1;
 const BLOCK_INFO_BUCKET_NUM: u64 = 2;
@@ -30,7 +30,11 @@ impl BlockInfo {
                 BLOCK_INFO_OVERFLOWS => block_info.is_overflows = reader.read_scalar()?,
                 BLOCK_INFO_BUCKET_NUM => block_info.bucket_num = reader.read_scalar()?,
                 END_FIELD => break,
-                packet => return Err(Error::Driver(DriverError::UnknownPacket { packet })),
+                field => {
+                    return Err(Error::Other(
+                        format!("Unknown block info field {}.", field).into(),
+                    ))
+                }
             }
         }
 
@@ -1,13 +1,18 @@
-use std::{cmp, default::Default, fmt, io::Read, marker::PhantomData};
+use std::{cmp, default::Default, fmt, io::Read, marker::PhantomData, ptr, slice, sync::Arc};
 
-use ethnum::I256;
+use either::Either;
+use ethnum::{I256, U256};
 
 use crate::{
-    binary::{Encoder, ReadEx},
-    column::{self, ArcColumnWrapper, Column, ColumnFrom, ColumnType, Simple},
-    error::{Error, FromSqlError, Result},
+    binary::{compress_buffer, Encoder, ReadEx},
+    column::{
+        self, ArcColumnWrapper, Column, ColumnData, ColumnFrom, ColumnType, ColumnWrapper, Simple,
+        StringColumnData,
+    },
+    error::{DriverError, Error, FromSqlError, Result},
     protocol,
-    types::{FromSql, SqlType},
+    types::{Compression, FromSql, SqlType},
+    value::ValueRef,
     Complex,
 };
 
@@ -16,7 +21,7 @@ pub(crate) use self::row::BlockRef;
 pub use self::{
     block_info::BlockInfo,
     builder::{RCons, RNil, RowBuilder},
-    row::{Row, Rows},
+    row::{FromRow, FromRows, Row, Rows},
 };
 
 mod block_info;
@@ -24,20 +29,66 @@ mod builder;
 mod chunk_iterator;
 mod row;
 
+/// Upper bound of the memory taken by a loaded block, see
+/// `PoolConfigBuilder::with_max_block_bytes`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct BlockLimit {
+    pub(crate) max_bytes: usize,
+    /// Bytes assumed per `String` value.
+    pub(crate) string_bytes: usize,
+}
+
+impl BlockLimit {
+    /// Estimated size of a column with `rows` values of `type_name`.
+    ///
+    /// Fixed-width types are exact. Arrays and maps are assumed to hold one element per row.
+    /// Unknown types count as empty, loading them fails anyway.
+    fn estimate(&self, type_name: &str, rows: usize) -> usize {
+        match SqlType::from_type_name(type_name) {
+            Ok(sql_type) => self.value_bytes(&sql_type).saturating_mul(rows),
+            Err(_) => 0,
+        }
+    }
+
+    fn value_bytes(&self, sql_type: &SqlType) -> usize {
+        match sql_type {
+            SqlType::String => self.string_bytes,
+            SqlType::Nullable(inner) => 1 + self.value_bytes(inner),
+            SqlType::Array(inner) => 8 + self.value_bytes(inner),
+            SqlType::Map(key, value) => 8 + self.value_bytes(key) + self.value_bytes(value),
+            SqlType::Tuple(types) => types.iter().map(|t| self.value_bytes(t)).sum(),
+            SqlType::LowCardinality(inner) => self.value_bytes(inner),
+            sql_type => sql_type.fixed_width_bytes().unwrap_or_default(),
+        }
+    }
+}
+
 const INSERT_BLOCK_SIZE: usize = 1_048_576;
 const DEFAULT_CAPACITY: usize = 100;
+/// Rows printed by the `Debug` implementation, the rest are summarized.
+const DEBUG_MAX_ROWS: usize = 100;
 
 pub trait ColumnIdx {
     fn get_index<K: ColumnType>(&self, columns: &[Column<K>]) -> Result<usize>;
 }
 
-pub trait Sliceable {
+mod private {
+    pub trait Sealed {}
+}
+
+/// Types whose column data `Block::column_slice` can borrow.
+///
+/// The slice is built from the raw column buffer, so the trait is sealed: only the
+/// numeric types below, whose layout matches `slice_type`, implement it.
+pub trait Sliceable: private::Sealed {
     fn slice_type() -> SqlType;
 }
 
 macro_rules! sliceable {
     ( $($t:ty: $k:ident),* ) => {
         $(
+            impl private::Sealed for $t {}
+
             impl Sliceable for $t {
                 fn slice_type() -> SqlType {
                     SqlType::$k
@@ -57,7 +108,11 @@ sliceable! {
     i16: Int16,
     i32: Int32,
     i64: Int64,
-    I256: Int256
+    I256: Int256,
+    U256: UInt256,
+
+    f32: Float32,
+    f64: Float64
 }
 
 /// Represents Clickhouse Block
@@ -95,6 +150,162 @@ impl Block<Simple> {
     }
 }
 
+impl Block<Simple> {
+    /// Merges blocks with the same columns into a single block, copying the data.
+    ///
+    /// Unlike `concat`, the result owns its columns, so it can be extended and written.
+    pub fn merge(blocks: &[Self]) -> Result<Self> {
+        let first = match blocks.first() {
+            None => return Ok(Block::new()),
+            Some(first) => first,
+        };
+
+        for block in &blocks[1..] {
+            if block.column_count() != first.column_count() {
+                return Err(Error::Other(
+                    format!(
+                        "Unable to merge blocks with {} and {} columns.",
+                        first.column_count(),
+                        block.column_count()
+                    )
+                    .into(),
+                ));
+            }
+
+            for (expected, actual) in first.columns.iter().zip(&block.columns) {
+                if expected.name() != actual.name() || expected.sql_type() != actual.sql_type() {
+                    return Err(Error::Other(
+                        format!(
+                            "Unable to merge column `{}` {} with column `{}` {}.",
+                            expected.name(),
+                            expected.sql_type(),
+                            actual.name(),
+                            actual.sql_type()
+                        )
+                        .into(),
+                    ));
+                }
+            }
+        }
+
+        let row_count = blocks.iter().map(|block| block.row_count()).sum();
+        let mut columns = Vec::with_capacity(first.column_count());
+        for (i, column) in first.columns.iter().enumerate() {
+            let mut data = match column.sql_type() {
+                // The size of the strings is known, so they are copied into a single chunk.
+                SqlType::String => {
+                    let bytes = blocks
+                        .iter()
+                        .map(|block| &block.columns[i])
+                        .flat_map(|source| (0..source.len()).map(move |row| source.at(row)))
+                        .map(|value| match value {
+                            ValueRef::String(bytes) => bytes.len(),
+                            _ => 0,
+                        })
+                        .sum();
+                    ArcColumnWrapper::wrap(StringColumnData::with_byte_capacity(row_count, bytes))
+                }
+                sql_type => <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, row_count)?,
+            };
+            {
+                let data = Arc::get_mut(&mut data).unwrap();
+                for block in blocks {
+                    let source = &block.columns[i];
+                    for row in 0..source.len() {
+                        data.push(source.at(row).into());
+                    }
+                }
+            }
+            columns.push(column::new_column(column.name(), data));
+        }
+
+        Ok(Block {
+            info: first.info,
+            columns,
+            capacity: blocks.iter().map(|b| b.capacity).sum(),
+        })
+    }
+}
+
+impl Block<Simple> {
+    /// Returns a copy of the block with rows sorted by the column `col`.
+    /// NULL values go last in both directions.
+    pub fn sorted_by<I>(&self, col: I, desc: bool) -> Result<Self>
+    where
+        I: ColumnIdx + Copy,
+    {
+        let column = self.get_column(col)?;
+
+        let mut permutation: Vec<usize> = (0..self.row_count()).collect();
+        permutation.sort_by(|&a, &b| {
+            let (a, b) = (column.at(a), column.at(b));
+            match (is_null(&a), is_null(&b)) {
+                (false, false) if desc => b.cmp(&a),
+                _ => a.cmp(&b),
+            }
+        });
+
+        let mut columns = Vec::with_capacity(self.column_count());
+        for column in &self.columns {
+            let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(
+                column.sql_type(),
+                permutation.len(),
+            )?;
+            {
+                let data = Arc::get_mut(&mut data).unwrap();
+                for &row in &permutation {
+                    data.push(column.at(row).into());
+                }
+            }
+            columns.push(column::new_column(column.name(), data));
+        }
+
+        Ok(Block {
+            info: self.info,
+            columns,
+            capacity: self.capacity,
+        })
+    }
+
+    /// Returns an iterator converting every row into `T`.
+    ///
+    /// The iteration stops after the first row that fails to convert, yielding its error.
+    pub fn iter_rows<T: FromRow>(&self) -> FromRows<'_, T> {
+        FromRows {
+            rows: self.rows(),
+            failed: false,
+            kind: PhantomData,
+        }
+    }
+
+    /// Returns the data of a numeric column as a slice, without copying.
+    ///
+    /// Fails if the column type is not exactly `T`, e.g. for nullable columns.
+    pub fn column_slice<T, I>(&self, col: I) -> Result<&[T]>
+    where
+        T: Sliceable,
+        I: ColumnIdx + Copy,
+    {
+        let column = self.get_column(col)?;
+        let sql_type = column.sql_type();
+
+        if sql_type != T::slice_type() {
+            return Err(Error::FromSql(FromSqlError::InvalidType {
+                src: sql_type.to_string(),
+                dst: T::slice_type().to_string(),
+            }));
+        }
+
+        unsafe {
+            let mut ptr: *const u8 = ptr::null();
+            let mut size: usize = 0;
+            column.get_internal(&[&mut ptr, &mut size as *mut usize as *mut *const u8], 0, 0)?;
+            assert_ne!(ptr, ptr::null());
+            Ok(slice::from_raw_parts(ptr as *const T, size))
+        }
+    }
+}
+
 impl<L: ColumnType, R: ColumnType> PartialEq<Block<R>> for Block<L> {
     fn eq(&self, other: &Block<R>) -> bool {
         if self.columns.len() != other.columns.len() {
@@ -129,7 +340,10 @@ impl<K: ColumnType> AsRef<Block<K>> for Block<K> {
 
 impl ColumnIdx for usize {
     #[inline(always)]
-    fn get_index<K: ColumnType>(&self, _: &[Column<K>]) -> Result<usize> {
+    fn get_index<K: ColumnType>(&self, columns: &[Column<K>]) -> Result<usize> {
+        if *self >= columns.len() {
+            return Err(Error::FromSql(FromSqlError::OutOfRange));
+        }
         Ok(*self)
     }
 }
@@ -168,14 +382,39 @@ impl Block {
         }
     }
 
+    #[cfg(test)]
     pub(crate) fn load<R>(reader: &mut R, tz: chrono_tz::Tz) -> Result<Self>
     where
         R: Read + ReadEx,
     {
-        Self::raw_load(reader, tz)
+        Self::raw_load(reader, tz, None)
+    }
+
+    /// Same as `load`, failing before the data of a column is read if the block
+    /// would exceed `limit`.
+    pub(crate) fn load_with_limit<R>(
+        reader: &mut R,
+        tz: chrono_tz::Tz,
+        limit: Option<BlockLimit>,
+    ) -> Result<Self>
+    where
+        R: Read + ReadEx,
+    {
+        Self::raw_load(reader, tz, limit)
     }
 
-    fn raw_load<R>(reader: &mut R, tz: chrono_tz::Tz) -> Result<Block<Simple>>
+    /// Decodes a block written by `to_bytes`, for testing the column encoders.
+    #[cfg(test)]
+    pub(crate) fn from_bytes(bytes: &[u8], tz: chrono_tz::Tz) -> Result<Self> {
+        let mut reader = std::io::Cursor::new(bytes);
+        Self::load(&mut reader, tz)
+    }
+
+    fn raw_load<R>(
+        reader: &mut R,
+        tz: chrono_tz::Tz,
+        limit: Option<BlockLimit>,
+    ) -> Result<Block<Simple>>
     where
         R: ReadEx,
     {
@@ -183,10 +422,24 @@ impl Block {
         block.info = BlockInfo::read(reader)?;
 
         let num_columns = reader.read_uvarint()?;
-        let num_rows = reader.read_uvarint()?;
+        let num_rows = reader.read_uvarint()? as usize;
 
+        let mut estimate = 0_usize;
         for _ in 0..num_columns {
-            let column = Column::read(reader, num_rows as usize, tz)?;
+            let name = reader.read_string()?;
+            let type_name = reader.read_string()?;
+
+            if let Some(limit) = limit {
+                estimate = estimate.saturating_add(limit.estimate(&type_name, num_rows));
+                if estimate > limit.max_bytes {
+                    return Err(Error::Driver(DriverError::BlockTooLarge {
+                        estimate,
+                        limit: limit.max_bytes,
+                    }));
+                }
+            }
+
+            let column = Column::read(reader, name, &type_name, num_rows, tz)?;
             block.append_column(column);
         }
 
@@ -225,15 +478,38 @@ impl<K: ColumnType> Block<K> {
     }
 
     /// Get the value of a particular cell of the block.
+    ///
+    /// A row or column index past the end fails with `FromSqlError::OutOfRange`.
     pub fn get<'a, T, I>(&'a self, row: usize, col: I) -> Result<T>
     where
         T: FromSql<'a>,
         I: ColumnIdx + Copy,
     {
         let column_index = col.get_index(self.columns())?;
+        if row >= self.row_count() {
+            return Err(Error::FromSql(FromSqlError::OutOfRange));
+        }
         T::from_sql(self.columns[column_index].at(row))
     }
 
+    /// Get the value of a particular cell of the block, or `None` if there is no such column.
+    pub fn get_opt<'a, T, I>(&'a self, row: usize, col: I) -> Result<Option<T>>
+    where
+        T: FromSql<'a>,
+        I: ColumnIdx + Copy,
+    {
+        let column = match col.get_index(self.columns()) {
+            Ok(column_index) => self.columns.get(column_index),
+            Err(_) => None,
+        };
+
+        match column {
+            None => Ok(None),
+            Some(_) if row >= self.row_count() => Err(Error::FromSql(FromSqlError::OutOfRange)),
+            Some(column) => T::from_sql(column.at(row)).map(Some),
+        }
+    }
+
     /// Add new column into this block
     pub fn add_column<S>(self, name: &str, values: S) -> Self
     where
@@ -282,6 +558,141 @@ impl<K: ColumnType> Block<K> {
         let column = &self.columns[column_index];
         Ok(column)
     }
+
+    /// Same as `get_column` with a name, but matches the name ignoring ASCII case.
+    ///
+    /// Fails if several columns match, e.g. `id` and `ID`.
+    pub fn get_column_ci(&self, name: &str) -> Result<&Column<K>> {
+        let mut matches = self
+            .columns
+            .iter()
+            .filter(|column| column.name().eq_ignore_ascii_case(name));
+
+        match (matches.next(), matches.next()) {
+            (None, _) => Err(Error::FromSql(FromSqlError::OutOfRange)),
+            (Some(column), None) => Ok(column),
+            (Some(_), Some(_)) => Err(Error::Other(
+                format!("Column name {} is ambiguous ignoring case.", name).into(),
+            )),
+        }
+    }
+
+    /// Returns columns of the `Nested` structure `prefix`, i.e. all the `prefix.*` array columns.
+    ///
+    /// Fails if there are no such columns or if their arrays differ in length in some row.
+    pub fn nested(&self, prefix: &str) -> Result<Vec<&Column<K>>> {
+        let columns: Vec<&Column<K>> = self
+            .columns
+            .iter()
+            .filter(|column| {
+                column
+                    .name()
+                    .strip_prefix(prefix)
+                    .is_some_and(|name| name.starts_with('.'))
+            })
+            .collect();
+
+        if columns.is_empty() {
+            return Err(Error::FromSql(FromSqlError::OutOfRange));
+        }
+
+        for column in &columns {
+            if !matches!(column.sql_type(), SqlType::Array(_)) {
+                return Err(Error::FromSql(FromSqlError::InvalidType {
+                    src: column.sql_type().to_string(),
+                    dst: "Array".into(),
+                }));
+            }
+        }
+
+        for row in 0..self.row_count() {
+            let expected = array_len(columns[0], row);
+            for column in &columns[1..] {
+                let actual = array_len(column, row);
+                if actual != expected {
+                    return Err(Error::Other(
+                        format!(
+                            "Nested column `{}` has {} elements in row {}, expected {}.",
+                            column.name(),
+                            actual,
+                            row,
+                            expected
+                        )
+                        .into(),
+                    ));
+                }
+            }
+        }
+
+        Ok(columns)
+    }
+
+    /// Returns a block with only the columns `names`, in this order.
+    /// The column data is shared with this block, not copied.
+    ///
+    /// The result is of the same kind as this block, so its columns can be read the same way.
+    pub fn project(&self, names: &[&str]) -> Result<Self> {
+        let mut columns = Vec::with_capacity(names.len());
+        for name in names {
+            match self.columns.iter().find(|column| column.name() == *name) {
+                None => {
+                    return Err(Error::Other(
+                        format!("Column `{}` is missing.", name).into(),
+                    ))
+                }
+                Some(column) => columns.push(column.clone()),
+            }
+        }
+
+        Ok(Block {
+            info: self.info,
+            columns,
+            capacity: self.capacity,
+        })
+    }
+
+    /// Checks that the block has the columns of `schema` with exactly these types,
+    /// so that schema drift is reported before reading the data.
+    ///
+    /// Fails with the first missing column or type mismatch.
+    pub fn validate_schema(&self, schema: &[(&str, SqlType)]) -> Result<()> {
+        self.check_schema(schema, false)
+    }
+
+    /// Like `validate_schema`, but a `Nullable(T)` column matches `T` and vice versa.
+    pub fn validate_schema_nullable(&self, schema: &[(&str, SqlType)]) -> Result<()> {
+        self.check_schema(schema, true)
+    }
+
+    fn check_schema(&self, schema: &[(&str, SqlType)], nullable: bool) -> Result<()> {
+        for (name, expected) in schema {
+            let column = match self.columns.iter().find(|column| column.name() == *name) {
+                None => {
+                    return Err(Error::Other(
+                        format!("Column `{}` {} is missing.", name, expected).into(),
+                    ))
+                }
+                Some(column) => column,
+            };
+
+            let actual = column.sql_type();
+            let matches = if nullable {
+                actual.unwrap_nullable() == expected.unwrap_nullable()
+            } else {
+                actual == *expected
+            };
+            if !matches {
+                return Err(Error::Other(
+                    format!(
+                        "Column `{}` has type {}, expected {}.",
+                        name, actual, expected
+                    )
+                    .into(),
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<K: ColumnType> Block<K> {
@@ -295,12 +706,27 @@ impl<K: ColumnType> Block<K> {
         }
     }
 
-    pub(crate) fn send_data(&self, encoder: &mut Encoder) {
+    /// Encodes the block the way it is sent to the server, without a live connection.
+    #[cfg(test)]
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        self.write(&mut encoder);
+        encoder.get_buffer()
+    }
+
+    pub(crate) fn send_data(&self, encoder: &mut Encoder, compression: Compression) -> Result<()> {
         encoder.uvarint(protocol::CLIENT_DATA);
         encoder.string(""); // temporary table
         for chunk in self.chunks(INSERT_BLOCK_SIZE) {
-            chunk.write(encoder);
+            if compression.is_enabled() {
+                let mut tmp = Encoder::new();
+                chunk.write(&mut tmp);
+                encoder.write_bytes(&compress_buffer(tmp.get_buffer_ref(), compression)?);
+            } else {
+                chunk.write(encoder);
+            }
         }
+        Ok(())
     }
 
     pub(crate) fn chunks(&self, n: usize) -> ChunkIterator<K> {
@@ -310,43 +736,114 @@ impl<K: ColumnType> Block<K> {
 
 impl<K: ColumnType> fmt::Debug for Block<K> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let titles: Vec<&str> = self.columns.iter().map(|column| column.name()).collect();
+        write_table(self, f, DEBUG_MAX_ROWS, None)
+    }
+}
 
-        let cells: Vec<_> = self.columns.iter().map(|col| text_cells(col)).collect();
+/// `Display` wrapper of a block which prints a limited number of rows
+/// and truncates long cells, see `Block::display_limited`.
+pub struct BlockDisplay<'a, K: ColumnType> {
+    block: &'a Block<K>,
+    max_rows: usize,
+    max_col_width: usize,
+}
 
-        let titles_len: Vec<_> = titles
-            .iter()
-            .map(|t| t.chars().count())
-            .zip(cells.iter().map(|w| column_width(w)))
-            .map(|(a, b)| cmp::max(a, b))
-            .collect();
+impl<K: ColumnType> Block<K> {
+    /// Returns a wrapper which displays at most `max_rows` rows and cuts cells
+    /// longer than `max_col_width` characters.
+    pub fn display_limited(&self, max_rows: usize, max_col_width: usize) -> BlockDisplay<'_, K> {
+        BlockDisplay {
+            block: self,
+            max_rows,
+            max_col_width,
+        }
+    }
+}
 
-        print_line(f, &titles_len, "\n\u{250c}", '┬', "\u{2510}\n")?;
+impl<'a, K: ColumnType> fmt::Display for BlockDisplay<'a, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_table(self.block, f, self.max_rows, Some(self.max_col_width))
+    }
+}
 
-        for (i, title) in titles.iter().enumerate() {
-            write!(f, "\u{2502}{:>width$} ", title, width = titles_len[i] + 1)?;
-        }
-        write!(f, "\u{2502}")?;
+fn write_table<K: ColumnType>(
+    block: &Block<K>,
+    f: &mut fmt::Formatter,
+    max_rows: usize,
+    max_col_width: Option<usize>,
+) -> fmt::Result {
+    let row_count = cmp::min(block.row_count(), max_rows);
 
-        if self.row_count() > 0 {
-            print_line(f, &titles_len, "\n\u{251c}", '┼', "\u{2524}\n")?;
-        }
+    let titles: Vec<&str> = block.columns.iter().map(|column| column.name()).collect();
 
-        for j in 0..self.row_count() {
-            for (i, col) in cells.iter().enumerate() {
-                write!(f, "\u{2502}{:>width$} ", col[j], width = titles_len[i] + 1)?;
+    let cells: Vec<Vec<String>> = block
+        .columns
+        .iter()
+        .map(|col| {
+            let cells = text_cells(col, row_count);
+            match max_col_width {
+                None => cells,
+                Some(width) => cells.into_iter().map(|c| truncate(c, width)).collect(),
             }
+        })
+        .collect();
+
+    let titles_len: Vec<_> = titles
+        .iter()
+        .map(|t| t.chars().count())
+        .zip(cells.iter().map(|w| column_width(w)))
+        .map(|(a, b)| cmp::max(a, b))
+        .collect();
+
+    print_line(f, &titles_len, "\n\u{250c}", '┬', "\u{2510}\n")?;
+
+    for (i, title) in titles.iter().enumerate() {
+        write!(f, "\u{2502}{:>width$} ", title, width = titles_len[i] + 1)?;
+    }
+    write!(f, "\u{2502}")?;
+
+    if row_count > 0 {
+        print_line(f, &titles_len, "\n\u{251c}", '┼', "\u{2524}\n")?;
+    }
 
-            let new_line = (j + 1) != self.row_count();
-            write!(f, "\u{2502}{}", if new_line { "\n" } else { "" })?;
+    for j in 0..row_count {
+        for (i, col) in cells.iter().enumerate() {
+            write!(f, "\u{2502}{:>width$} ", col[j], width = titles_len[i] + 1)?;
         }
 
-        print_line(f, &titles_len, "\n\u{2514}", '┴', "\u{2518}")
+        let new_line = (j + 1) != row_count;
+        write!(f, "\u{2502}{}", if new_line { "\n" } else { "" })?;
     }
+
+    print_line(f, &titles_len, "\n\u{2514}", '┴', "\u{2518}")?;
+
+    if block.row_count() > row_count {
+        write!(
+            f,
+            "\n\u{2026} ({} more rows)",
+            block.row_count() - row_count
+        )?;
+    }
+    Ok(())
+}
+
+/// Cuts `cell` to `width` characters, the last one being an ellipsis.
+fn truncate(cell: String, width: usize) -> String {
+    if cell.chars().count() <= width {
+        return cell;
+    }
+
+    let mut result: String = cell.chars().take(width.saturating_sub(1)).collect();
+    result.push('\u{2026}');
+    result
 }
 
 fn column_width(column: &[String]) -> usize {
-    column.iter().map(|cell| cell.len()).max().unwrap_or(0)
+    column
+        .iter()
+        .map(|cell| cell.chars().count())
+        .max()
+        .unwrap_or(0)
 }
 
 fn print_line(
@@ -367,6 +864,385 @@ fn print_line(
     write!(f, "{}", right)
 }
 
-fn text_cells<K: ColumnType>(data: &Column<K>) -> Vec<String> {
-    (0..data.len()).map(|i| format!("{}", data.at(i))).collect()
+fn is_null(value: &ValueRef) -> bool {
+    matches!(value, ValueRef::Nullable(Either::Left(_)))
+}
+
+fn array_len<K: ColumnType>(column: &Column<K>, row: usize) -> usize {
+    match column.at(row) {
+        ValueRef::Array(_, values) => values.len(),
+        _ => 0,
+    }
+}
+
+fn text_cells<K: ColumnType>(data: &Column<K>, rows: usize) -> Vec<String> {
+    (0..rows).map(|i| format!("{}", data.at(i))).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::value::Value;
+
+    #[test]
+    fn test_get_opt() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec!["foo", "bar"]);
+
+        assert_eq!(block.get_opt::<u32, _>(1, "id").unwrap(), Some(2));
+        assert_eq!(block.get_opt::<u32, _>(0, "missing").unwrap(), None);
+        assert_eq!(block.get_opt::<u32, _>(0, 5).unwrap(), None);
+        assert!(block.get_opt::<u32, _>(0, "name").is_err());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let block = Block::new()
+            .column("u8", vec![1_u8, 2])
+            .column("u16", vec![1_u16, 2])
+            .column("u32", vec![1_u32, 2])
+            .column("u64", vec![1_u64, u64::MAX])
+            .column("i8", vec![-1_i8, 2])
+            .column("i16", vec![-1_i16, 2])
+            .column("i32", vec![-1_i32, 2])
+            .column("i64", vec![i64::MIN, 2])
+            .column("f32", vec![1.5_f32, -0.0])
+            .column("f64", vec![1.5_f64, f64::MAX])
+            .column("s", vec!["foo", ""])
+            .column("n", vec![Some("foo"), None])
+            .column("a", vec![vec![1_u32, 2], vec![]])
+            .column("as", vec![vec!["foo"], vec!["bar", "baz"]]);
+
+        let bytes = block.to_bytes();
+        let rblock = Block::from_bytes(&bytes, chrono_tz::Tz::Zulu).unwrap();
+
+        assert_eq!(block, rblock);
+        assert_eq!(rblock.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_from_bytes_truncated() {
+        let block = Block::new().column("s", vec!["foo", "bar"]);
+        let bytes = block.to_bytes();
+
+        assert!(Block::from_bytes(&bytes[..bytes.len() - 1], chrono_tz::Tz::Zulu).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Item {
+        id: u32,
+        name: String,
+    }
+
+    impl FromRow for Item {
+        fn from_row<'a>(row: &'a Row<'a, Simple>) -> Result<Self> {
+            Ok(Item {
+                id: row.get("id")?,
+                name: row.get("name")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_iter_rows() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec!["foo", "bar"]);
+
+        let items: Vec<Item> = block.iter_rows().collect::<Result<_>>().unwrap();
+        assert_eq!(
+            items,
+            [
+                Item {
+                    id: 1,
+                    name: "foo".into()
+                },
+                Item {
+                    id: 2,
+                    name: "bar".into()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_rows_error() {
+        let block = Block::new()
+            .column("id", vec![1_u64, 2])
+            .column("name", vec!["foo", "bar"]);
+
+        let mut rows = block.iter_rows::<Item>();
+        assert!(rows.next().unwrap().is_err());
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn test_get_column_ci() {
+        let block = Block::new()
+            .column("UserId", vec![1_u32, 2])
+            .column("name", vec!["foo", "bar"])
+            .column("NAME", vec!["x", "y"]);
+
+        assert_eq!(block.get_column_ci("userid").unwrap().name(), "UserId");
+        assert!(block.get_column("userid").is_err());
+        assert!(block.get_column_ci("missing").is_err());
+
+        let err = block.get_column_ci("Name").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Other error: `Column name Name is ambiguous ignoring case.`"
+        );
+    }
+
+    #[test]
+    fn test_nested() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2, 3])
+            .column("n.a", vec![vec![1_u32, 2], vec![], vec![3]])
+            .column("n.b", vec![vec!["x", "y"], vec![], vec!["z"]])
+            .column("nn.a", vec![vec![1_u32], vec![], vec![]]);
+
+        let nested = block.nested("n").unwrap();
+        let names: Vec<&str> = nested.iter().map(|column| column.name()).collect();
+        assert_eq!(names, ["n.a", "n.b"]);
+
+        assert!(block.nested("id").is_err());
+        assert!(block.nested("missing").is_err());
+    }
+
+    #[test]
+    fn test_nested_length_mismatch() {
+        let block = Block::new()
+            .column("n.a", vec![vec![1_u32, 2], vec![3]])
+            .column("n.b", vec![vec![1_u32], vec![2, 3]]);
+
+        assert!(block.nested("n").is_err());
+    }
+
+    #[test]
+    fn test_project() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec!["foo", "bar"])
+            .column("score", vec![0.5_f64, 1.5]);
+
+        let projected = block.project(&["score", "id"]).unwrap();
+        let names: Vec<&str> = projected.columns().iter().map(|c| c.name()).collect();
+        assert_eq!(names, ["score", "id"]);
+        assert_eq!(projected.row_count(), 2);
+        assert_eq!(
+            projected.column_slice::<f64, _>("score").unwrap(),
+            [0.5, 1.5]
+        );
+        assert_eq!(
+            projected.column_slice::<u32, _>("id").unwrap().as_ptr(),
+            block.column_slice::<u32, _>("id").unwrap().as_ptr()
+        );
+
+        let concat = Block::concat(&[block.clone(), block.clone()]);
+        let projected = concat.project(&["name"]).unwrap();
+        let names: Vec<&[u8]> = projected
+            .get_column("name")
+            .unwrap()
+            .iter::<&[u8]>()
+            .unwrap()
+            .collect();
+        assert_eq!(names, [b"foo", b"bar", b"foo", b"bar"]);
+
+        match block.project(&["id", "missing"]) {
+            Err(Error::Other(message)) => {
+                assert_eq!(message.to_string(), "Column `missing` is missing.")
+            }
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_validate_schema() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec![Some("foo"), None]);
+
+        let schema = [
+            ("name", SqlType::Nullable(SqlType::String.into())),
+            ("id", SqlType::UInt32),
+        ];
+        assert!(block.validate_schema(&schema).is_ok());
+        assert!(block.validate_schema(&schema[..1]).is_ok());
+
+        let schema = [("id", SqlType::UInt32), ("name", SqlType::String)];
+        match block.validate_schema(&schema) {
+            Err(Error::Other(message)) => assert_eq!(
+                message.to_string(),
+                "Column `name` has type Nullable(String), expected String."
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(block.validate_schema_nullable(&schema).is_ok());
+
+        let schema = [("id", SqlType::UInt64)];
+        assert!(block.validate_schema_nullable(&schema).is_err());
+
+        let schema = [("missing", SqlType::UInt32)];
+        match block.validate_schema(&schema) {
+            Err(Error::Other(message)) => {
+                assert_eq!(message.to_string(), "Column `missing` UInt32 is missing.")
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let blocks = [
+            Block::new()
+                .column("id", vec![1_u32, 2])
+                .column("name", vec![Some("foo"), None]),
+            Block::new()
+                .column("id", Vec::<u32>::new())
+                .column("name", Vec::<Option<&str>>::new()),
+            Block::new()
+                .column("id", vec![3_u32])
+                .column("name", vec![Some("bar")]),
+        ];
+
+        let mut merged = Block::merge(&blocks).unwrap();
+        assert_eq!(merged, Block::concat(&blocks));
+
+        merged
+            .push(vec![
+                ("id".to_string(), Value::from(4_u32)),
+                ("name".to_string(), Value::from(Some("baz"))),
+            ])
+            .unwrap();
+        assert_eq!(merged.row_count(), 4);
+        assert_eq!(merged.get::<u32, _>(3, "id").unwrap(), 4);
+
+        assert!(Block::merge(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merge_schema_mismatch() {
+        let block = Block::new().column("id", vec![1_u32]);
+
+        let other = Block::new().column("id", vec![1_u64]);
+        assert!(Block::merge(&[block.clone(), other]).is_err());
+
+        let other = Block::new().column("key", vec![1_u32]);
+        assert!(Block::merge(&[block.clone(), other]).is_err());
+
+        let other = Block::new()
+            .column("id", vec![1_u32])
+            .column("name", vec!["foo"]);
+        assert!(Block::merge(&[block, other]).is_err());
+    }
+
+    #[test]
+    fn test_display_limited() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2, 3])
+            .column("name", vec!["short", "привет, мир", "x"]);
+
+        let expected = "
+┌────┬───────┐
+│ id │  name │
+├────┼───────┤
+│  1 │ short │
+│  2 │ прив… │
+└────┴───────┘
+… (1 more rows)";
+        assert_eq!(block.display_limited(2, 5).to_string(), expected);
+    }
+
+    #[test]
+    fn test_debug_binary_strings() {
+        let mut blob = vec![0x1f_u8, 0x8b, 0x08, 0x00];
+        blob.resize(1024, 0xff);
+        let block = Block::new().column("data", vec![&blob[..], b"text"]);
+
+        let expected = "
+┌─────────────────────────────────┐
+│                            data │
+├─────────────────────────────────┤
+│ 0x1f8b0800ffffffff…(1024 bytes) │
+│                            text │
+└─────────────────────────────────┘";
+        assert_eq!(format!("{:?}", block), expected);
+    }
+
+    #[test]
+    fn test_debug_nested_nulls() {
+        let item_type = SqlType::Nullable(SqlType::UInt32.into());
+        let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(
+            SqlType::Array(item_type.clone().into()),
+            2,
+        )
+        .unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            for row in [vec![Some(1_u32), None, Some(3)], vec![None]] {
+                let values = row.into_iter().map(Value::from).collect();
+                data.push(Value::Array(item_type.clone().into(), Arc::new(values)));
+            }
+        }
+        let block = Block::new()
+            .column("vals", column::new_column::<Simple>("vals", data))
+            .column("name", vec![Some("foo"), None]);
+
+        let expected = "
+┌──────────────┬──────┐
+│         vals │ name │
+├──────────────┼──────┤
+│ [1, NULL, 3] │  foo │
+│       [NULL] │ NULL │
+└──────────────┴──────┘";
+        assert_eq!(format!("{:?}", block), expected);
+    }
+
+    #[test]
+    fn test_debug_limit() {
+        let block = Block::new().column("id", (0..150_u32).collect::<Vec<_>>());
+        let text = format!("{:?}", block);
+
+        assert_eq!(text.lines().count(), 106);
+        assert!(text.ends_with("… (50 more rows)"));
+    }
+
+    #[test]
+    fn test_sorted_by() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2, 3, 4, 5])
+            .column("score", vec![Some(20_i64), None, Some(-5), Some(20), None])
+            .column("name", vec!["b", "c", "a", "e", "d"]);
+
+        let sorted = block.sorted_by("score", false).unwrap();
+        let ids: Vec<u32> = sorted.column_slice("id").unwrap().to_vec();
+        assert_eq!(ids, [3, 1, 4, 2, 5]);
+
+        let sorted = block.sorted_by("score", true).unwrap();
+        let ids: Vec<u32> = sorted.column_slice("id").unwrap().to_vec();
+        assert_eq!(ids, [1, 4, 3, 2, 5]);
+
+        let sorted = block.sorted_by("name", true).unwrap();
+        assert_eq!(sorted.get::<String, _>(0, "name").unwrap(), "e");
+        assert_eq!(sorted.get::<Option<i64>, _>(0, "score").unwrap(), Some(20));
+
+        assert!(block.sorted_by("missing", false).is_err());
+    }
+
+    #[test]
+    fn test_column_slice() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2, 3])
+            .column("value", vec![0.5_f64, 1.5, 2.5]);
+
+        assert_eq!(block.column_slice::<u32, _>("id").unwrap(), &[1, 2, 3]);
+        assert_eq!(block.column_slice::<f64, _>(1).unwrap(), &[0.5, 1.5, 2.5]);
+        assert!(block.column_slice::<u64, _>("id").is_err());
+        assert!(block.column_slice::<u32, _>("missing").is_err());
+
+        let block = Block::new().column("id", vec![Some(1_u32), None]);
+        assert!(block.column_slice::<u32, _>("id").is_err());
+    }
 }
@@ -1,8 +1,9 @@
 use std::{marker, sync::Arc};
 
 use crate::{
-    column::{Column, ColumnType},
+    column::{Column, ColumnType, Simple},
     types::{FromSql, SqlType},
+    value::ValueRef,
 };
 
 use crate::{
@@ -46,6 +47,26 @@ impl<'a, K: ColumnType> Row<'a, K> {
     pub fn sql_type<I: ColumnIdx + Copy>(&self, col: I) -> Result<SqlType> {
         Ok(self.block_ref.get_column(col)?.sql_type())
     }
+
+    /// Return the columns of the block the row belongs to.
+    pub fn columns(&self) -> &[Column<K>] {
+        self.block_ref.columns()
+    }
+
+    /// Return every cell of the row in column order.
+    pub fn values(&self) -> Vec<ValueRef<'_>> {
+        self.columns()
+            .iter()
+            .map(|column| column.at(self.row))
+            .collect()
+    }
+}
+
+/// Conversion of a row into a user type.
+///
+/// It can be derived with `#[derive(FromRow)]` when the `derive` feature is enabled.
+pub trait FromRow: Sized {
+    fn from_row<'a>(row: &'a Row<'a, Simple>) -> Result<Self>;
 }
 
 pub(crate) enum BlockRef<'a, K: ColumnType> {
@@ -88,18 +109,18 @@ impl<'a, K: ColumnType> BlockRef<'a, K> {
         }
     }
 
-    fn get_column<I: ColumnIdx + Copy>(&self, col: I) -> Result<&Column<K>> {
+    fn columns(&self) -> &[Column<K>] {
         match self {
-            BlockRef::Borrowed(block) => {
-                let column_index = col.get_index(block.columns())?;
-                Ok(&block.columns[column_index])
-            }
-            BlockRef::Owned(block) => {
-                let column_index = col.get_index(block.columns())?;
-                Ok(&block.columns[column_index])
-            }
+            BlockRef::Borrowed(block) => block.columns(),
+            BlockRef::Owned(block) => block.columns(),
         }
     }
+
+    fn get_column<I: ColumnIdx + Copy>(&self, col: I) -> Result<&Column<K>> {
+        let columns = self.columns();
+        let column_index = col.get_index(columns)?;
+        Ok(&columns[column_index])
+    }
 }
 
 /// Immutable rows iterator
@@ -125,3 +146,84 @@ impl<'a, K: ColumnType> Iterator for Rows<'a, K> {
         result
     }
 }
+
+/// Iterator converting rows into `T` with `FromRow`, see `Block::iter_rows`.
+///
+/// It stops after the first row that fails to convert.
+pub struct FromRows<'a, T> {
+    pub(crate) rows: Rows<'a, Simple>,
+    pub(crate) failed: bool,
+    pub(crate) kind: marker::PhantomData<T>,
+}
+
+impl<'a, T: FromRow> Iterator for FromRows<'a, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let row = self.rows.next()?;
+        let result = T::from_row(&row);
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::{Error, FromSqlError};
+
+    fn is_out_of_range<T>(result: Result<T>) -> bool {
+        matches!(result, Err(Error::FromSql(FromSqlError::OutOfRange)))
+    }
+
+    #[test]
+    fn test_zero_column_block() {
+        let block = Block::<Simple>::new();
+        assert_eq!(block.row_count(), 0);
+        assert_eq!(block.rows().count(), 0);
+
+        assert!(is_out_of_range(block.get::<u32, _>(0, 0)));
+        assert!(is_out_of_range(block.get::<u32, _>(0, "id")));
+        assert!(is_out_of_range(block.get_column(0)));
+    }
+
+    #[test]
+    fn test_zero_row_block() {
+        let block = Block::new().column("id", Vec::<u32>::new());
+        assert_eq!(block.rows().count(), 0);
+
+        assert!(is_out_of_range(block.get::<u32, _>(0, "id")));
+        assert!(is_out_of_range(block.get::<u32, _>(0, 0)));
+        assert!(is_out_of_range(block.get_opt::<u32, _>(0, "id")));
+        assert!(is_out_of_range(block.get::<u32, _>(0, 1)));
+    }
+
+    #[test]
+    fn test_column_index_out_of_range() {
+        let block = Block::new().column("id", vec![1_u32]);
+        let row = block.rows().next().unwrap();
+
+        assert_eq!(row.get::<u32, _>(0).unwrap(), 1);
+        assert!(is_out_of_range(row.get::<u32, _>(1)));
+        assert!(is_out_of_range(row.name(1)));
+        assert!(is_out_of_range(row.sql_type(1)));
+    }
+
+    #[test]
+    fn test_values() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec!["foo", "bar"]);
+
+        let row = block.rows().nth(1).unwrap();
+        let names: Vec<&str> = row.columns().iter().map(|column| column.name()).collect();
+        assert_eq!(names, ["id", "name"]);
+        assert_eq!(
+            row.values(),
+            [ValueRef::UInt32(2), ValueRef::String(b"bar")]
+        );
+    }
+}
@@ -1,23 +1,24 @@
-use std::fmt;
+use std::{fmt, time::Instant};
 
 use crate::{
     block::Block,
-    error::{Error, Result},
+    error::{ConnectionError, DriverError, Error, Result},
     pool::{Pool, PoolBinding, PoolConfig},
     query::{block_stream::BlockStream, *},
-    stream::ConnectingStream,
+    stream::{self, ConnectingStream},
     transport::ClickhouseTransport,
-    types::{Cmd, Packet, ServerInfo},
+    types::{Cmd, Packet, ServerInfo, SqlType},
 };
 use futures_core::{future::BoxFuture, stream::BoxStream};
 use futures_util::{FutureExt, StreamExt};
 use log::{info, warn};
+use url::Url;
 
 /// Retry guard max attempts
-const MAX_RETRY_ATTEMTS: usize = 3;
+pub(crate) const MAX_RETRY_ATTEMTS: usize = 3;
 
 pub(crate) const PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
-/// Retry guard timeout between attempts
+/// Retry guard timeout after the first failed attempt
 pub(crate) const RETRY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
 pub struct Client {
@@ -41,11 +42,28 @@ impl Default for Context {
     }
 }
 
+/// A column of a table, read with `ClientHandle::describe_table`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnMeta {
+    pub name: String,
+    pub sql_type: DescribedType,
+}
+
+/// Type of a described column.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DescribedType {
+    Parsed(SqlType),
+    /// The type name as the server sent it, for types the driver does not support.
+    Raw(String),
+}
+
 /// Clickhouse client handle.
 pub struct ClientHandle {
     pub(crate) inner: Option<ClickhouseTransport>,
     pub(crate) context: Context,
     pub(crate) pool: PoolBinding,
+    /// Time when the handle was created or returned to the pool
+    pub(crate) last_used: Instant,
 }
 
 impl fmt::Debug for ClientHandle {
@@ -67,42 +85,67 @@ impl Client {
             }
         };
 
+        let hosts = match &pool {
+            None => config.hosts(),
+            Some(p) => p.get_addrs(),
+        };
+
+        let mut errors = Vec::with_capacity(hosts.len());
+        for addr in hosts {
+            match with_timeout(Self::open_host(&addr, &config, pool.clone()), timeout).await {
+                Ok(handle) => return Ok(handle),
+                Err(err) => {
+                    warn!("failed to connect to {}: {}", addr, err);
+                    errors.push((addr, err));
+                }
+            }
+        }
+
+        match errors.len() {
+            1 => Err(errors.pop().unwrap().1),
+            _ => Err(Error::Connection(ConnectionError::AllHostsFailed(errors))),
+        }
+    }
+
+    async fn open_host(
+        addr: &Url,
+        config: &PoolConfig,
+        pool: Option<Pool>,
+    ) -> Result<ClientHandle> {
         let context = Context {
             config: config.clone(),
             ..Default::default()
         };
 
-        with_timeout(
-            async move {
-                let addr = match &pool {
-                    None => &config.addr,
-                    Some(p) => p.get_addr(),
-                };
-
-                info!("try to connect to {}", addr);
-                if addr.port() == Some(8123) {
-                    warn!("You should use port 9000 instead of 8123 because clickhouse-rs work through the binary interface.");
-                }
-                let mut stream = ConnectingStream::new(addr, &config).await?;
-                stream.set_nodelay(true)?;
-
-                let transport = ClickhouseTransport::new(stream, pool.clone());
-
-                let mut handle = ClientHandle {
-                    inner: Some(transport),
-                    pool: match pool {
-                        None => PoolBinding::None,
-                        Some(p) => PoolBinding::Detached(p),
-                    },
-                    context
-                };
+        info!("try to connect to {}", addr);
+        let addresses = match (&pool, &config.proxy) {
+            // The proxy resolves the host itself.
+            (_, Some(_)) => Ok(Vec::new()),
+            (None, None) => stream::resolve(addr).await,
+            (Some(p), None) => p.resolve(addr).await,
+        };
+        let mut stream = ConnectingStream::new(addr, addresses, config).await?;
+        stream.set_nodelay(true)?;
+
+        let transport = ClickhouseTransport::new(
+            stream,
+            pool.clone(),
+            config.compression,
+            config.block_limit(),
+        );
 
-                handle.hello().await?;
-                Ok(handle)
+        let mut handle = ClientHandle {
+            inner: Some(transport),
+            pool: match pool {
+                None => PoolBinding::None,
+                Some(p) => PoolBinding::Detached(p),
             },
-            timeout,
-        )
-        .await
+            context,
+            last_used: Instant::now(),
+        };
+
+        handle.hello().await?;
+        Ok(handle)
     }
 }
 
@@ -140,6 +183,69 @@ impl ClientHandle {
         Ok(())
     }
 
+    /// Checks that the connection is alive by sending a ping, without reconnecting.
+    ///
+    /// On failure the transport is dropped, so the handle should be dropped as well.
+    pub async fn check(&mut self) -> Result<()> {
+        self.ping().await
+    }
+
+    /// Returns `true` if the server answers a ping, see `check`.
+    pub async fn is_healthy(&mut self) -> bool {
+        self.check().await.is_ok()
+    }
+
+    /// Returns the connection to the pool after draining the packets left by an abandoned
+    /// query, so it is ready for the next one.
+    ///
+    /// Dropping the handle returns it without draining; the packets are then read
+    /// when the connection is handed out again.
+    pub async fn release(mut self) -> Result<()> {
+        if let Some(transport) = self.inner.take() {
+            let transport = with_timeout(transport.clear(), PING_TIMEOUT).await?;
+            self.inner = Some(transport);
+        }
+        Ok(())
+    }
+
+    /// Drains the packets left by a failed query and checks the connection with a ping,
+    /// so it goes back to the pool instead of being dropped.
+    ///
+    /// Returns `false` if the connection is already closed. An error means it broke
+    /// while draining; in both cases the handle should be dropped.
+    pub async fn drain_after_error(&mut self) -> Result<bool> {
+        let transport = match self.inner.take() {
+            Some(transport) if !transport.is_closed() => transport,
+            _ => return Ok(false),
+        };
+
+        let transport = with_timeout(transport.drain(), PING_TIMEOUT).await?;
+        self.inner = Some(transport);
+
+        if !self.pool.is_attached() && self.pool.is_some() {
+            self.pool.attach();
+        }
+        Ok(true)
+    }
+
+    /// Timezone of the server, received in the handshake.
+    pub fn server_timezone(&self) -> chrono_tz::Tz {
+        self.context.server_info.timezone
+    }
+
+    /// Version of the server as `(major, minor, patch)`.
+    ///
+    /// Servers older than revision 54401 do not send the patch, it is 0 for them.
+    pub fn server_version(&self) -> (u64, u64, u64) {
+        let info = &self.context.server_info;
+        (info.major_version, info.minor_version, info.patch_version)
+    }
+
+    /// Protocol revision of the server.
+    pub fn server_revision(&self) -> u64 {
+        self.context.server_info.revision
+    }
+
     async fn ping(&mut self) -> Result<()> {
         with_timeout(
             async move {
@@ -147,7 +253,10 @@ impl ClientHandle {
 
                 let mut h = None;
 
-                let transport = self.inner.take().unwrap().clear().await?;
+                let transport = match self.inner.take() {
+                    Some(transport) => transport.clear().await?,
+                    None => return Err(Error::Other("Connection is closed".into())),
+                };
                 let mut stream = transport.call(Cmd::Ping);
 
                 while let Some(packet) = stream.next().await {
@@ -162,8 +271,13 @@ impl ClientHandle {
                     }
                 }
 
-                self.inner = h;
-                Ok(())
+                match h {
+                    Some(transport) => {
+                        self.inner = Some(transport);
+                        Ok(())
+                    }
+                    None => Err(Error::Driver(DriverError::UnexpectedPacket)),
+                }
             },
             PING_TIMEOUT,
         )
@@ -182,6 +296,31 @@ impl ClientHandle {
         }
     }
 
+    /// Reads the columns of `table` with `DESCRIBE TABLE`. The name is put into the
+    /// query as is, so it may be qualified like `db.table`.
+    ///
+    /// Types the driver can't parse are kept as `DescribedType::Raw`.
+    pub async fn describe_table(&mut self, table: &str) -> Result<Vec<ColumnMeta>> {
+        let block = self
+            .query(format!("DESCRIBE TABLE {}", table))
+            .fetch_block()
+            .await?;
+
+        (0..block.row_count())
+            .map(|row| {
+                let type_name: String = block.get(row, "type")?;
+                let sql_type = match SqlType::from_type_name(&type_name) {
+                    Ok(sql_type) => DescribedType::Parsed(sql_type),
+                    Err(_) => DescribedType::Raw(type_name),
+                };
+                Ok(ColumnMeta {
+                    name: block.get(row, "name")?,
+                    sql_type,
+                })
+            })
+            .collect()
+    }
+
     pub(crate) fn wrap_stream<'a, F>(&'a mut self, f: F) -> BoxStream<'a, Result<Block>>
     where
         F: (FnOnce(&'a mut Self) -> BlockStream<'a>) + Send + 'static,
@@ -198,6 +337,9 @@ impl ClientHandle {
     }
 
     /// Check connection and try to reconnect if necessary.
+    ///
+    /// It runs before a query is sent, so reconnecting never repeats a query. A connection
+    /// lost while a query runs fails the stream with `DriverError::QueryInterrupted`.
     async fn check_connection(&mut self) -> Result<()> {
         self.pool.detach();
 
@@ -213,6 +355,20 @@ impl ClientHandle {
         Ok(())
     }
 
+    /// A handle without a connection, standing in for this one while its connection is
+    /// used elsewhere. It reconnects on the next query.
+    pub(crate) fn placeholder(&self) -> ClientHandle {
+        ClientHandle {
+            inner: None,
+            context: self.context.clone(),
+            pool: match Option::<Pool>::from(self.pool.clone()) {
+                None => PoolBinding::None,
+                Some(pool) => PoolBinding::Attached(pool),
+            },
+            last_used: Instant::now(),
+        }
+    }
+
     /// Switch Transport AtomicUsize status on takeing/returning connection
     pub(crate) fn set_inside(&self, value: bool) {
         if let Some(ref inner) = self.inner {
@@ -228,6 +384,7 @@ pub(crate) async fn retry(
     source: &PoolConfig,
     pool: Option<Pool>,
 ) -> Result<()> {
+    let max_attempts = handle.context.config.retry_attempts;
     let mut attempt = 0;
     let mut skip_check = false;
 
@@ -238,29 +395,44 @@ pub(crate) async fn retry(
             match handle.ping().await {
                 Ok(()) => return Ok(()),
                 Err(err) => {
-                    if attempt >= MAX_RETRY_ATTEMTS {
+                    if attempt >= max_attempts {
                         return Err(err);
                     }
                 }
             }
         }
 
-        match reconnect(handle, source, pool.clone()).await {
-            Ok(()) => continue,
-            Err(err) => {
-                skip_check = true;
-                if attempt >= MAX_RETRY_ATTEMTS {
-                    return Err(err);
-                }
-
-                tokio::time::sleep(RETRY_TIMEOUT).await;
+        if let Err(err) = reconnect(handle, source, pool.clone()).await {
+            skip_check = true;
+            if attempt >= max_attempts {
+                return Err(err);
             }
+
+            tokio::time::sleep(retry_backoff(source, attempt)).await;
         }
 
         attempt += 1;
     }
 }
 
+/// Delay before the next reconnect: `base * 2^attempt`, plus optional jitter.
+fn retry_backoff(config: &PoolConfig, attempt: usize) -> std::time::Duration {
+    let factor = 1_u32.checked_shl(attempt as u32).unwrap_or(u32::MAX);
+    let backoff = config.retry_backoff.saturating_mul(factor);
+
+    if !config.retry_jitter {
+        return backoff;
+    }
+
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    backoff.saturating_add((backoff / 2).mul_f64(f64::from(seed % 1000) / 1000.0))
+}
+
+/// New connections rotate across the configured hosts, so reconnecting
+/// moves on to the next host when the current one is unreachable.
 async fn reconnect(conn: &mut ClientHandle, source: &PoolConfig, pool: Option<Pool>) -> Result<()> {
     warn!("[reconnect]");
     let mut new_conn = match pool {
@@ -277,3 +449,164 @@ where
 {
     tokio::time::timeout(timeout, future).await?
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::mock_server;
+
+    fn retry_config(jitter: bool) -> PoolConfig {
+        let url = Url::parse("tcp://localhost:9000").unwrap();
+        mock_server::builder(url)
+            .with_retry(5, Duration::from_millis(100))
+            .with_retry_jitter(jitter)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_is_healthy() {
+        let url = mock_server::stalled_server().await;
+        let mut client = Pool::new(mock_server::config(url))
+            .get_handle()
+            .await
+            .unwrap();
+        client.check().await.unwrap();
+        assert!(client.is_healthy().await);
+
+        // The server closes the connection after answering the query.
+        let block = Block::new().column("n", vec![1_u32]);
+        let url = mock_server::closing_server(block, 0).await;
+        let mut client = Pool::new(mock_server::config(url))
+            .get_handle()
+            .await
+            .unwrap();
+        assert!(client.query("SELECT n").fetch_all().await.is_err());
+        assert!(!client.is_healthy().await);
+        assert!(client.check().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_info() {
+        let url = mock_server::stalled_server().await;
+        let config = mock_server::config(url);
+        let client = Pool::new(config).get_handle().await.unwrap();
+
+        assert_eq!(client.server_timezone(), chrono_tz::Europe::Berlin);
+        assert_eq!(
+            client.server_version(),
+            (
+                mock_server::MAJOR_VERSION,
+                mock_server::MINOR_VERSION,
+                mock_server::PATCH_VERSION
+            )
+        );
+        assert_eq!(client.server_revision(), mock_server::REVISION);
+    }
+
+    #[tokio::test]
+    async fn test_release() {
+        let blocks = vec![
+            Block::new().column("n", Vec::<u32>::new()),
+            Block::new().column("n", vec![1_u32]),
+            Block::new().column("n", vec![2_u32]),
+        ];
+        let url = mock_server::query_server(blocks).await;
+        let config = mock_server::config(url);
+        let pool = Pool::new(config);
+
+        let mut client = pool.get_handle().await.unwrap();
+        {
+            let mut stream = client.query("SELECT n").stream_blocks();
+            stream.next().await.unwrap().unwrap();
+        }
+        assert!(client.inner.as_ref().unwrap().inconsistent);
+
+        client.release().await.unwrap();
+        assert_eq!(pool.metrics().idle, 1);
+
+        let mut client = pool.get_handle().await.unwrap();
+        assert!(!client.inner.as_ref().unwrap().inconsistent);
+        let block = client.query("SELECT n").fetch_block().await.unwrap();
+        assert_eq!(block.row_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_describe_table() {
+        let block = Block::new()
+            .column("name", vec!["id", "tags", "kind"])
+            .column(
+                "type",
+                vec!["UInt64", "Array(LowCardinality(String))", "Enum8('a' = 1)"],
+            )
+            .column("default_type", vec!["", "", ""]);
+        let url = mock_server::query_server(vec![block]).await;
+        let config = mock_server::config(url);
+        let mut client = Pool::new(config).get_handle().await.unwrap();
+
+        let columns = client.describe_table("db.events").await.unwrap();
+        assert_eq!(
+            columns,
+            [
+                ColumnMeta {
+                    name: "id".into(),
+                    sql_type: DescribedType::Parsed(SqlType::UInt64),
+                },
+                ColumnMeta {
+                    name: "tags".into(),
+                    sql_type: DescribedType::Parsed(SqlType::Array(
+                        SqlType::LowCardinality(SqlType::String.into()).into()
+                    )),
+                },
+                ColumnMeta {
+                    name: "kind".into(),
+                    sql_type: DescribedType::Raw("Enum8('a' = 1)".into()),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drain_after_error() {
+        let url = mock_server::exception_server(60, "Table default.missing doesn't exist").await;
+        let config = mock_server::config(url);
+        let pool = Pool::new(config);
+
+        let mut client = pool.get_handle().await.unwrap();
+        match client.query("SELECT * FROM missing").fetch_all().await {
+            Err(Error::Server(e)) => assert_eq!(e.code(), 60),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        assert!(client.inner.as_ref().unwrap().inconsistent);
+
+        assert!(client.drain_after_error().await.unwrap());
+        assert!(!client.inner.as_ref().unwrap().inconsistent);
+
+        drop(client);
+        assert_eq!(pool.metrics().idle, 1);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let config = retry_config(false);
+        assert_eq!(retry_backoff(&config, 0), Duration::from_millis(100));
+        assert_eq!(retry_backoff(&config, 1), Duration::from_millis(200));
+        assert_eq!(retry_backoff(&config, 3), Duration::from_millis(800));
+        assert_eq!(
+            retry_backoff(&config, 100),
+            Duration::from_millis(100) * u32::MAX
+        );
+    }
+
+    #[test]
+    fn test_retry_backoff_jitter() {
+        let config = retry_config(true);
+        for attempt in 0..4 {
+            let base = Duration::from_millis(100) * (1 << attempt);
+            let backoff = retry_backoff(&config, attempt as usize);
+            assert!(backoff >= base && backoff <= base + base / 2);
+        }
+    }
+}
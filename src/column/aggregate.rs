@@ -0,0 +1,145 @@
+use std::any;
+
+use crate::{
+    column::{iter::Iterable, Column, ColumnType},
+    error::{Error, Result},
+};
+
+/// Numbers which `Column::sum` adds up.
+pub trait Summable: Copy + Default {
+    /// `None` when the sum doesn't fit in the type.
+    fn checked_add(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! summable_int {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl Summable for $t {
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+            }
+        )*
+    };
+}
+
+summable_int! { u8, u16, u32, u64, i8, i16, i32, i64 }
+
+impl Summable for f32 {
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+}
+
+impl Summable for f64 {
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+}
+
+impl<K: ColumnType> Column<K> {
+    /// The smallest value of a numeric column, `None` if it is empty.
+    /// `NaN` values are skipped.
+    pub fn min<'a, T>(&'a self) -> Result<Option<T>>
+    where
+        T: Iterable<'a, K> + Copy + PartialOrd + 'a,
+        T::Iter: Iterator<Item = &'a T>,
+    {
+        self.fold_ordered(|value, min| value < min)
+    }
+
+    /// The largest value of a numeric column, `None` if it is empty.
+    /// `NaN` values are skipped.
+    pub fn max<'a, T>(&'a self) -> Result<Option<T>>
+    where
+        T: Iterable<'a, K> + Copy + PartialOrd + 'a,
+        T::Iter: Iterator<Item = &'a T>,
+    {
+        self.fold_ordered(|value, max| value > max)
+    }
+
+    /// The sum of a numeric column, `None` if it is empty.
+    ///
+    /// Integer sums are checked: a sum which overflows `T` is an error. As `T` must be
+    /// the type of the column, sums of narrow integer columns overflow easily.
+    pub fn sum<'a, T>(&'a self) -> Result<Option<T>>
+    where
+        T: Iterable<'a, K> + Summable + 'a,
+        T::Iter: Iterator<Item = &'a T>,
+    {
+        let mut sum = None;
+        for &value in self.iter::<T>()? {
+            let total = sum.unwrap_or_default();
+            match Summable::checked_add(total, value) {
+                Some(total) => sum = Some(total),
+                None => {
+                    return Err(Error::Other(
+                        format!(
+                            "Sum of column {} overflows {}.",
+                            self.name(),
+                            any::type_name::<T>()
+                        )
+                        .into(),
+                    ))
+                }
+            }
+        }
+        Ok(sum)
+    }
+
+    fn fold_ordered<'a, T>(&'a self, better: impl Fn(T, T) -> bool) -> Result<Option<T>>
+    where
+        T: Iterable<'a, K> + Copy + PartialOrd + 'a,
+        T::Iter: Iterator<Item = &'a T>,
+    {
+        let mut result = None;
+        for &value in self.iter::<T>()? {
+            // Only NaN is not comparable to itself.
+            if value.partial_cmp(&value).is_none() {
+                continue;
+            }
+            result = match result {
+                Some(current) if !better(value, current) => Some(current),
+                _ => Some(value),
+            };
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::block::Block;
+
+    #[test]
+    fn test_min_max_sum() {
+        let block = Block::new().column("n", vec![3_i32, -7, 12, 0]);
+        let n = block.get_column("n").unwrap();
+        assert_eq!(n.min::<i32>().unwrap(), Some(-7));
+        assert_eq!(n.max::<i32>().unwrap(), Some(12));
+        assert_eq!(n.sum::<i32>().unwrap(), Some(8));
+        assert!(n.sum::<i64>().is_err());
+
+        let block = Block::new().column("f", vec![f64::NAN, 1.5, -2.0]);
+        let f = block.get_column("f").unwrap();
+        assert_eq!(f.min::<f64>().unwrap(), Some(-2.0));
+        assert_eq!(f.max::<f64>().unwrap(), Some(1.5));
+
+        let block = Block::new().column("e", Vec::<u64>::new());
+        let e = block.get_column("e").unwrap();
+        assert_eq!(e.min::<u64>().unwrap(), None);
+        assert_eq!(e.sum::<u64>().unwrap(), None);
+    }
+
+    #[test]
+    fn test_sum_overflow() {
+        let block = Block::new().column("n", vec![200_u8, 100]);
+        let n = block.get_column("n").unwrap();
+
+        let err = n.sum::<u8>().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Other error: `Sum of column n overflows u8.`"
+        );
+    }
+}
@@ -4,12 +4,13 @@ use std::sync::Arc;
 use crate::column::{
     column_data::{ArcColumnData, BoxColumnData},
     list::List,
+    low_cardinality::check_not_nested,
     ArcColumnWrapper, ColumnData,
 };
 
 use crate::{
     binary::{Encoder, ReadEx},
-    error::Result,
+    error::{DriverError, Error, Result},
     types::SqlType,
     value::{Value, ValueRef},
 };
@@ -26,9 +27,12 @@ impl ArrayColumnData {
         rows: usize,
         tz: Tz,
     ) -> Result<Self> {
+        check_not_nested(type_name)?;
+
         let mut offsets = List::with_capacity(rows);
         offsets.resize(rows, 0_u64);
         reader.read_bytes(offsets.as_mut())?;
+        check_offsets(&offsets)?;
 
         let size = match rows {
             0 => 0,
@@ -41,6 +45,23 @@ impl ArrayColumnData {
     }
 }
 
+/// Offsets come from the server: if they decreased, the slices of the rows would be
+/// out of the inner column, which has as many items as the last offset.
+pub(crate) fn check_offsets(offsets: &List<u64>) -> Result<()> {
+    let mut prev = 0_u64;
+    for row in 0..offsets.len() {
+        let offset = offsets.at(row);
+        if offset < prev {
+            return Err(Error::Driver(DriverError::MalformedData(format!(
+                "offset {} of row {} is less than the previous offset {}.",
+                offset, row, prev
+            ))));
+        }
+        prev = offset;
+    }
+    Ok(())
+}
+
 impl ColumnData for ArrayColumnData {
     fn sql_type(&self) -> SqlType {
         let inner_type = self.inner.sql_type();
@@ -48,14 +69,20 @@ impl ColumnData for ArrayColumnData {
     }
 
     fn save(&self, encoder: &mut Encoder, start: usize, end: usize) {
-        let mut offset = 0_u64;
+        // Offsets of a slice are relative to its first item, at every nesting level.
+        let first = if start > 0 {
+            self.offsets.at(start - 1)
+        } else {
+            0_u64
+        };
+        let mut offset = first;
 
         for i in start..end {
             offset = self.offsets.at(i);
-            encoder.write(offset);
+            encoder.write(offset - first);
         }
 
-        self.inner.save(encoder, 0, offset as usize);
+        self.inner.save(encoder, first as usize, offset as usize);
     }
 
     fn len(&self) -> usize {
@@ -155,4 +182,54 @@ mod test {
 
         assert_eq!(block, rblock);
     }
+
+    #[test]
+    fn test_save_nested_slice() {
+        let inner_type = SqlType::Array(SqlType::UInt32.into());
+        let sql_type = SqlType::Array(inner_type.clone().into());
+        let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, 3).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            let rows: [&[&[u32]]; 3] = [&[&[1, 2], &[3]], &[&[], &[4, 5]], &[&[6]]];
+            for row in rows {
+                let values = row
+                    .iter()
+                    .map(|vs| {
+                        let vs = vs.iter().copied().map(Value::from).collect();
+                        Value::Array(SqlType::UInt32.into(), Arc::new(vs))
+                    })
+                    .collect();
+                data.push(Value::Array(inner_type.clone().into(), Arc::new(values)));
+            }
+        }
+
+        let mut encoder = Encoder::new();
+        data.save(&mut encoder, 1, 3);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let loaded = ArrayColumnData::load(&mut reader, "Array(UInt32)", 2, Tz::Zulu).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.at(0), data.at(1));
+        assert_eq!(loaded.at(1), data.at(2));
+    }
+
+    #[test]
+    fn test_load_decreasing_offsets() {
+        let mut encoder = Encoder::new();
+        encoder.write(2_u64);
+        encoder.write(1_u64);
+        encoder.write(7_u32);
+        encoder.write(8_u32);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        match ArrayColumnData::load(&mut reader, "UInt32", 2, Tz::Zulu) {
+            Err(Error::Driver(DriverError::MalformedData(message))) => assert_eq!(
+                message,
+                "offset 1 of row 1 is less than the previous offset 2."
+            ),
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("the offsets should be rejected"),
+        }
+    }
 }
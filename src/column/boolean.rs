@@ -0,0 +1,126 @@
+use crate::{
+    binary::{Encoder, ReadEx},
+    error::Result,
+    types::SqlType,
+    value::{Value, ValueRef},
+};
+
+use crate::column::{
+    column_data::{BoxColumnData, ColumnData},
+    VectorColumnData,
+};
+
+/// `Bool` is transferred as `UInt8`, so the data is kept in the same storage.
+pub(crate) struct BoolColumnData {
+    pub(crate) inner: VectorColumnData<u8>,
+}
+
+impl BoolColumnData {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: VectorColumnData::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn load<R: ReadEx>(reader: &mut R, size: usize) -> Result<Self> {
+        let inner = VectorColumnData::load(reader, size)?;
+        Ok(Self { inner })
+    }
+}
+
+impl ColumnData for BoolColumnData {
+    fn sql_type(&self) -> SqlType {
+        SqlType::Bool
+    }
+
+    fn save(&self, encoder: &mut Encoder, start: usize, end: usize) {
+        self.inner.save(encoder, start, end);
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn push(&mut self, value: Value) {
+        match value {
+            Value::Bool(v) => self.inner.push(Value::UInt8(u8::from(v))),
+            Value::UInt8(v) => self.inner.push(Value::UInt8(v)),
+            _ => panic!("value should be a bool"),
+        }
+    }
+
+    fn at(&self, index: usize) -> ValueRef<'_> {
+        ValueRef::Bool(self.inner.data.at(index) != 0)
+    }
+
+    fn clone_instance(&self) -> BoxColumnData {
+        Box::new(Self {
+            inner: VectorColumnData {
+                data: self.inner.data.clone(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{io::Cursor, sync::Arc};
+
+    use chrono_tz::Tz;
+    use either::Either;
+
+    use super::*;
+    use crate::{
+        block::Block,
+        column::{self, ArcColumnWrapper, Simple},
+    };
+
+    #[test]
+    fn test_write_and_read() {
+        let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(SqlType::Bool, 3).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            data.push(Value::Bool(true));
+            data.push(Value::Bool(false));
+            data.push(Value::Bool(true));
+        }
+        let block = Block::<Simple>::new().column("b", column::new_column::<Simple>("b", data));
+
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let rblock = Block::load(&mut reader, Tz::Zulu).unwrap();
+
+        assert_eq!(block, rblock);
+        assert_eq!(rblock.get_column("b").unwrap().sql_type(), SqlType::Bool);
+
+        let values: Vec<bool> = (0..3).map(|row| rblock.get(row, "b").unwrap()).collect();
+        assert_eq!(values, vec![true, false, true]);
+    }
+
+    #[test]
+    fn test_nullable_write_and_read() {
+        let sql_type = SqlType::Nullable(SqlType::Bool.into());
+        let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, 2).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            data.push(Value::Nullable(Either::Right(Box::new(Value::Bool(true)))));
+            data.push(Value::Nullable(Either::Left(SqlType::Bool.into())));
+        }
+        let block = Block::<Simple>::new().column("b", column::new_column::<Simple>("b", data));
+
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let rblock = Block::load(&mut reader, Tz::Zulu).unwrap();
+
+        assert_eq!(block, rblock);
+
+        let first: Option<bool> = rblock.get(0, "b").unwrap();
+        let second: Option<bool> = rblock.get(1, "b").unwrap();
+        assert_eq!(first, Some(true));
+        assert_eq!(second, None);
+    }
+}
@@ -1,10 +1,11 @@
 use chrono_tz::Tz;
-use ethnum::I256;
+use ethnum::{I256, U256};
 
 use crate::column::{
-    array::ArrayColumnData, fixed_string::FixedStringColumnData, list::List,
-    nullable::NullableColumnData, string::StringColumnData, ArcColumnWrapper, ColumnData,
-    ColumnWrapper, VectorColumnData,
+    array::ArrayColumnData, boolean::BoolColumnData, fixed_string::FixedStringColumnData,
+    list::List, low_cardinality::LowCardinalityColumnData, map::MapColumnData,
+    nothing::NothingColumnData, nullable::NullableColumnData, string::StringColumnData,
+    tuple::TupleColumnData, ArcColumnWrapper, ColumnData, ColumnWrapper, VectorColumnData,
 };
 
 use crate::error::{Error, FromSqlError};
@@ -34,7 +35,12 @@ impl dyn ColumnData {
         size: usize,
         tz: Tz,
     ) -> Result<W::Wrapper> {
+        if let Some(definition) = parse_geo_type(type_name) {
+            return Self::load_data::<W, T>(reader, definition, size, tz);
+        }
+
         Ok(match_str!(type_name, {
+            "Bool" | "Boolean" => W::wrap(BoolColumnData::load(reader, size)?),
             "UInt8" => W::wrap(VectorColumnData::<u8>::load(reader, size)?),
             "UInt16" => W::wrap(VectorColumnData::<u16>::load(reader, size)?),
             "UInt32" => W::wrap(VectorColumnData::<u32>::load(reader, size)?),
@@ -46,6 +52,8 @@ impl dyn ColumnData {
             "Float32" | "Float" => W::wrap(VectorColumnData::<f32>::load(reader, size)?),
             "Float64" | "Double" => W::wrap(VectorColumnData::<f64>::load(reader, size)?),
             "Int256" => W::wrap(VectorColumnData::<I256>::load(reader, size)?),
+            "UInt256" => W::wrap(VectorColumnData::<U256>::load(reader, size)?),
+            "Nothing" => W::wrap(NothingColumnData::load(reader, size)?),
             "String" | "Char" | "Varchar" | "Text" | "TinyText" | "MediumText" | "LongText" | "Blob" | "TinyBlob" | "MediumBlob" | "LongBlob" => W::wrap(StringColumnData::load(reader, size)?),
             _ => {
                 if let Some(inner_type) = parse_nullable_type(type_name) {
@@ -54,6 +62,12 @@ impl dyn ColumnData {
                     W::wrap(FixedStringColumnData::load(reader, size, str_len)?)
                 } else if let Some(inner_type) = parse_array_type(type_name) {
                     W::wrap(ArrayColumnData::load(reader, inner_type, size, tz)?)
+                } else if let Some((key_type, value_type)) = parse_map_type(type_name) {
+                    W::wrap(MapColumnData::load(reader, key_type, value_type, size, tz)?)
+                } else if let Some(inner_types) = parse_tuple_type(type_name) {
+                    W::wrap(TupleColumnData::load(reader, &inner_types, size, tz)?)
+                } else if let Some(inner_type) = parse_low_cardinality_type(type_name) {
+                    W::wrap(LowCardinalityColumnData::load(reader, inner_type, size, tz)?)
                 } else {
                     return Err(
                         Error::FromSql(
@@ -70,6 +84,7 @@ impl dyn ColumnData {
         capacity: usize,
     ) -> Result<W::Wrapper> {
         Ok(match sql_type {
+            SqlType::Bool => W::wrap(BoolColumnData::with_capacity(capacity)),
             SqlType::UInt8 => W::wrap(VectorColumnData::<u8>::with_capacity(capacity)),
             SqlType::UInt16 => W::wrap(VectorColumnData::<u16>::with_capacity(capacity)),
             SqlType::UInt32 => W::wrap(VectorColumnData::<u32>::with_capacity(capacity)),
@@ -79,12 +94,14 @@ impl dyn ColumnData {
             SqlType::Int32 => W::wrap(VectorColumnData::<i32>::with_capacity(capacity)),
             SqlType::Int64 => W::wrap(VectorColumnData::<i64>::with_capacity(capacity)),
             SqlType::Int256 => W::wrap(VectorColumnData::<I256>::with_capacity(capacity)),
+            SqlType::UInt256 => W::wrap(VectorColumnData::<U256>::with_capacity(capacity)),
             SqlType::String => W::wrap(StringColumnData::with_capacity(capacity)),
             SqlType::FixedString(len) => {
                 W::wrap(FixedStringColumnData::with_capacity(capacity, len))
             }
             SqlType::Float32 => W::wrap(VectorColumnData::<f32>::with_capacity(capacity)),
             SqlType::Float64 => W::wrap(VectorColumnData::<f64>::with_capacity(capacity)),
+            SqlType::Nothing => W::wrap(NothingColumnData::with_capacity(capacity)),
             SqlType::Nullable(inner_type) => W::wrap(NullableColumnData {
                 inner: <dyn ColumnData>::from_type::<ArcColumnWrapper>(
                     inner_type.clone(),
@@ -99,12 +116,100 @@ impl dyn ColumnData {
                 )?,
                 offsets: List::with_capacity(capacity),
             }),
+            SqlType::Map(key_type, value_type) => W::wrap(MapColumnData {
+                keys: <dyn ColumnData>::from_type::<ArcColumnWrapper>(key_type.clone(), capacity)?,
+                values: <dyn ColumnData>::from_type::<ArcColumnWrapper>(
+                    value_type.clone(),
+                    capacity,
+                )?,
+                offsets: List::with_capacity(capacity),
+            }),
+            SqlType::Tuple(inner_types) => {
+                let mut inner = Vec::with_capacity(inner_types.len());
+                for inner_type in inner_types {
+                    inner.push(<dyn ColumnData>::from_type::<ArcColumnWrapper>(
+                        inner_type, capacity,
+                    )?);
+                }
+                W::wrap(TupleColumnData { inner })
+            }
+            SqlType::LowCardinality(inner_type) => W::wrap(LowCardinalityColumnData {
+                inner: <dyn ColumnData>::from_type::<ArcColumnWrapper>(
+                    inner_type.clone(),
+                    capacity,
+                )?,
+            }),
         })
     }
 }
 
+/// Parses a type name into `SqlType`, accepting the same names and aliases as `load_data`.
+pub(crate) fn parse_sql_type(type_name: &str) -> Result<SqlType> {
+    if let Some(definition) = parse_geo_type(type_name) {
+        return parse_sql_type(definition);
+    }
+
+    Ok(match_str!(type_name, {
+        "Bool" | "Boolean" => SqlType::Bool,
+        "UInt8" => SqlType::UInt8,
+        "UInt16" => SqlType::UInt16,
+        "UInt32" => SqlType::UInt32,
+        "UInt64" => SqlType::UInt64,
+        "Int8" | "TinyInt" => SqlType::Int8,
+        "Int16" | "SmallInt" => SqlType::Int16,
+        "Int32" | "Int" | "Integer" => SqlType::Int32,
+        "Int64" | "BigInt" => SqlType::Int64,
+        "Float32" | "Float" => SqlType::Float32,
+        "Float64" | "Double" => SqlType::Float64,
+        "Int256" => SqlType::Int256,
+        "UInt256" => SqlType::UInt256,
+        "Nothing" => SqlType::Nothing,
+        "String" | "Char" | "Varchar" | "Text" | "TinyText" | "MediumText" | "LongText" | "Blob" | "TinyBlob" | "MediumBlob" | "LongBlob" => SqlType::String,
+        _ => {
+            if let Some(inner_type) = parse_nullable_type(type_name) {
+                SqlType::Nullable(parse_sql_type(inner_type)?.into())
+            } else if let Some(str_len) = parse_fixed_string(type_name) {
+                SqlType::FixedString(str_len)
+            } else if let Some(inner_type) = parse_array_type(type_name) {
+                SqlType::Array(parse_sql_type(inner_type)?.into())
+            } else if let Some((key_type, value_type)) = parse_map_type(type_name) {
+                SqlType::Map(
+                    parse_sql_type(key_type)?.into(),
+                    parse_sql_type(value_type)?.into(),
+                )
+            } else if let Some(inner_types) = parse_tuple_type(type_name) {
+                SqlType::Tuple(
+                    inner_types
+                        .into_iter()
+                        .map(parse_sql_type)
+                        .collect::<Result<_>>()?,
+                )
+            } else if let Some(inner_type) = parse_low_cardinality_type(type_name) {
+                SqlType::LowCardinality(parse_sql_type(inner_type)?.into())
+            } else {
+                return Err(
+                    Error::FromSql(
+                        FromSqlError::UnsupportedColumnType(type_name.to_string().into())
+                    )
+                );
+            }
+        }
+    }))
+}
+
+/// Geo types are aliases of composite types, they are read as those.
+fn parse_geo_type(source: &str) -> Option<&'static str> {
+    match source {
+        "Point" => Some("Tuple(Float64, Float64)"),
+        "Ring" => Some("Array(Point)"),
+        "Polygon" => Some("Array(Ring)"),
+        "MultiPolygon" => Some("Array(Polygon)"),
+        _ => None,
+    }
+}
+
 fn parse_fixed_string(source: &str) -> Option<usize> {
-    if !source.starts_with("FixedString") {
+    if !source.starts_with("FixedString(") || !source.ends_with(')') {
         return None;
     }
 
@@ -116,7 +221,7 @@ fn parse_fixed_string(source: &str) -> Option<usize> {
 }
 
 fn parse_nullable_type(source: &str) -> Option<&str> {
-    if !source.starts_with("Nullable") {
+    if !source.starts_with("Nullable(") || !source.ends_with(')') {
         return None;
     }
 
@@ -130,7 +235,7 @@ fn parse_nullable_type(source: &str) -> Option<&str> {
 }
 
 fn parse_array_type(source: &str) -> Option<&str> {
-    if !source.starts_with("Array") {
+    if !source.starts_with("Array(") || !source.ends_with(')') {
         return None;
     }
 
@@ -138,13 +243,109 @@ fn parse_array_type(source: &str) -> Option<&str> {
     Some(inner_type)
 }
 
+fn parse_map_type(source: &str) -> Option<(&str, &str)> {
+    if !source.starts_with("Map(") || !source.ends_with(')') {
+        return None;
+    }
+
+    match split_type_args(&source[4..source.len() - 1])[..] {
+        [key_type, value_type] => Some((key_type, value_type)),
+        _ => None,
+    }
+}
+
+fn parse_tuple_type(source: &str) -> Option<Vec<&str>> {
+    if !source.starts_with("Tuple(") || !source.ends_with(')') {
+        return None;
+    }
+
+    let inner_types = split_type_args(&source[6..source.len() - 1]);
+    if inner_types.iter().any(|inner_type| inner_type.is_empty()) {
+        return None;
+    }
+
+    Some(inner_types)
+}
+
+fn parse_low_cardinality_type(source: &str) -> Option<&str> {
+    if !source.starts_with("LowCardinality(") || !source.ends_with(')') {
+        return None;
+    }
+
+    Some(&source[15..source.len() - 1])
+}
+
+/// Splits comma-separated type arguments, ignoring commas inside nested parentheses.
+fn split_type_args(source: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+
+    for (i, c) in source.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                result.push(source[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(source[start..].trim());
+
+    result
+}
+
 #[cfg(test)]
 mod test {
+    use std::io::Cursor;
+
     use super::*;
+    use crate::{binary::Encoder, types::FromSql};
 
     #[test]
     fn test_parse_array_type() {
         assert_eq!(parse_array_type("Array(UInt8)"), Some("UInt8"));
+        assert_eq!(parse_array_type("Array"), None);
+        assert_eq!(parse_array_type("Array(UInt8"), None);
+    }
+
+    #[test]
+    fn test_parse_map_type() {
+        assert_eq!(
+            parse_map_type("Map(String, UInt64)"),
+            Some(("String", "UInt64"))
+        );
+        assert_eq!(
+            parse_map_type("Map(String, Array(Map(UInt8, Nullable(String))))"),
+            Some(("String", "Array(Map(UInt8, Nullable(String)))"))
+        );
+        assert_eq!(parse_map_type("Map(String)"), None);
+        assert_eq!(parse_map_type("Array(UInt8)"), None);
+    }
+
+    #[test]
+    fn test_parse_tuple_type() {
+        assert_eq!(
+            parse_tuple_type("Tuple(Array(UInt8), String)"),
+            Some(vec!["Array(UInt8)", "String"])
+        );
+        assert_eq!(
+            parse_tuple_type("Tuple(UInt64, Map(String, Tuple(Int8, Int16)), String)"),
+            Some(vec!["UInt64", "Map(String, Tuple(Int8, Int16))", "String"])
+        );
+        assert_eq!(parse_tuple_type("Tuple()"), None);
+        assert_eq!(parse_tuple_type("Array(UInt8)"), None);
+    }
+
+    #[test]
+    fn test_parse_low_cardinality_type() {
+        assert_eq!(
+            parse_low_cardinality_type("LowCardinality(Nullable(String))"),
+            Some("Nullable(String)")
+        );
+        assert_eq!(parse_low_cardinality_type("String"), None);
     }
 
     #[test]
@@ -152,6 +353,95 @@ mod test {
         assert_eq!(parse_nullable_type("Nullable(Int8)"), Some("Int8"));
         assert_eq!(parse_nullable_type("Int8"), None);
         assert_eq!(parse_nullable_type("Nullable(Nullable(Int8))"), None);
+        assert_eq!(parse_nullable_type("Nullable"), None);
+        assert_eq!(parse_nullable_type("Nullable("), None);
+    }
+
+    #[test]
+    fn test_parse_sql_type() {
+        for type_name in [
+            "Bool",
+            "UInt8",
+            "Int256",
+            "UInt256",
+            "String",
+            "FixedString(16)",
+            "Float64",
+            "Nullable(Array(UInt32))",
+            "Array(Nullable(String))",
+            "Map(String, Array(Tuple(Int8, Nullable(Float32))))",
+            "Tuple(UInt64, FixedString(2))",
+            "LowCardinality(Nullable(String))",
+        ] {
+            let sql_type = parse_sql_type(type_name).unwrap();
+            assert_eq!(sql_type.to_string(), type_name);
+            assert_eq!(parse_sql_type(&sql_type.to_string()).unwrap(), sql_type);
+        }
+
+        assert_eq!(
+            parse_sql_type("Array(BigInt)").unwrap(),
+            SqlType::Array(SqlType::Int64.into())
+        );
+        assert!(parse_sql_type("Decimal(9, 2)").is_err());
+        assert!(parse_sql_type("Array(Decimal(9, 2))").is_err());
+
+        for type_name in [
+            "Array",
+            "Nullable",
+            "FixedString",
+            "Map",
+            "Tuple",
+            "LowCardinality",
+            "Array(",
+            "Nullable(",
+            "FixedString(",
+            "",
+        ] {
+            assert!(SqlType::from_type_name(type_name).is_err(), "{}", type_name);
+        }
+    }
+
+    #[test]
+    fn test_parse_geo_type() {
+        let point = SqlType::Tuple(vec![SqlType::Float64, SqlType::Float64]);
+        let ring = SqlType::Array(point.clone().into());
+        let polygon = SqlType::Array(ring.clone().into());
+
+        assert_eq!(parse_sql_type("Point").unwrap(), point);
+        assert_eq!(parse_sql_type("Ring").unwrap(), ring);
+        assert_eq!(parse_sql_type("Polygon").unwrap(), polygon);
+        assert_eq!(
+            parse_sql_type("MultiPolygon").unwrap(),
+            SqlType::Array(polygon.into())
+        );
+        assert_eq!(
+            parse_sql_type("Array(Point)").unwrap().to_string(),
+            "Array(Tuple(Float64, Float64))"
+        );
+    }
+
+    #[test]
+    fn test_load_ring() {
+        let mut encoder = Encoder::new();
+        encoder.write(2_u64); // offsets
+        for x in [1.5_f64, 3.0, 2.0, 4.5] {
+            encoder.write(x);
+        }
+
+        let mut reader = Cursor::new(encoder.get_buffer());
+        let data =
+            <dyn ColumnData>::load_data::<ArcColumnWrapper, _>(&mut reader, "Ring", 1, Tz::Zulu)
+                .unwrap();
+
+        let ring: Vec<(f64, f64)> = FromSql::from_sql(data.at(0)).unwrap();
+        assert_eq!(ring, [(1.5, 2.0), (3.0, 4.5)]);
+
+        let point: (f64, f64) = match data.at(0) {
+            crate::value::ValueRef::Array(_, points) => FromSql::from_sql(points[1].clone()),
+            value => panic!("unexpected value {:?}", value),
+        }
+        .unwrap();
+        assert_eq!(point, (3.0, 4.5));
     }
 
     #[test]
@@ -159,5 +449,7 @@ mod test {
         assert_eq!(parse_fixed_string("FixedString(8)"), Some(8_usize));
         assert_eq!(parse_fixed_string("FixedString(zz)"), None);
         assert_eq!(parse_fixed_string("Int8"), None);
+        assert_eq!(parse_fixed_string("FixedString"), None);
+        assert_eq!(parse_fixed_string("FixedString(8"), None);
     }
 }
@@ -1,4 +1,4 @@
-use std::{iter::FusedIterator, marker, mem, ptr, slice};
+use std::{fmt, iter::FusedIterator, marker, mem, ptr, slice};
 
 use crate::{
     column::{Column, Simple},
@@ -72,6 +72,7 @@ pub trait Iterable<'a, K: ColumnType> {
     ) -> Result<Self::Iter>;
 }
 
+#[derive(Clone, Copy)]
 enum StringInnerIterator<'a> {
     String(&'a StringPool),
     FixedString(*const u8, usize),
@@ -304,6 +305,204 @@ impl<'a> Iterable<'a, Simple> for &[u8] {
     }
 }
 
+/// Strings of one row of an `Array(String)` or `Array(FixedString(N))` column,
+/// or of a whole string column in `ColumnView`, borrowed from the column data.
+#[derive(Clone, Copy)]
+pub struct StringsView<'a> {
+    inner: StringInnerIterator<'a>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> StringsView<'a> {
+    /// Returns the number of strings in the row.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns `true` if the row is an empty array.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the string at `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+        self.iter().nth(index)
+    }
+
+    /// Returns an iterator over the strings of the row.
+    pub fn iter(&self) -> StringIterator<'a> {
+        StringIterator {
+            inner: self.inner,
+            index: self.start,
+            size: self.end,
+        }
+    }
+}
+
+impl<'a> IntoIterator for StringsView<'a> {
+    type Item = &'a [u8];
+    type IntoIter = StringIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl fmt::Debug for StringsView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Data of a whole column borrowed from a block, see `QueryResult::for_each_block_typed`.
+///
+/// `String`, `FixedString(N)` and `LowCardinality(String)` columns are `Strings`.
+#[derive(Clone, Copy, Debug)]
+pub enum ColumnView<'a> {
+    UInt8(&'a [u8]),
+    UInt16(&'a [u16]),
+    UInt32(&'a [u32]),
+    UInt64(&'a [u64]),
+    Int8(&'a [i8]),
+    Int16(&'a [i16]),
+    Int32(&'a [i32]),
+    Int64(&'a [i64]),
+    Float32(&'a [f32]),
+    Float64(&'a [f64]),
+    Strings(StringsView<'a>),
+}
+
+impl<'a> ColumnView<'a> {
+    pub(crate) fn new(column: &'a Column<Simple>) -> Result<Self> {
+        let sql_type = match column.sql_type() {
+            SqlType::LowCardinality(inner) => inner.clone(),
+            sql_type => sql_type,
+        };
+
+        Ok(match sql_type {
+            SqlType::UInt8 => ColumnView::UInt8(column.iter::<u8>()?.as_slice()),
+            SqlType::UInt16 => ColumnView::UInt16(column.iter::<u16>()?.as_slice()),
+            SqlType::UInt32 => ColumnView::UInt32(column.iter::<u32>()?.as_slice()),
+            SqlType::UInt64 => ColumnView::UInt64(column.iter::<u64>()?.as_slice()),
+            SqlType::Int8 => ColumnView::Int8(column.iter::<i8>()?.as_slice()),
+            SqlType::Int16 => ColumnView::Int16(column.iter::<i16>()?.as_slice()),
+            SqlType::Int32 => ColumnView::Int32(column.iter::<i32>()?.as_slice()),
+            SqlType::Int64 => ColumnView::Int64(column.iter::<i64>()?.as_slice()),
+            SqlType::Float32 => ColumnView::Float32(column.iter::<f32>()?.as_slice()),
+            SqlType::Float64 => ColumnView::Float64(column.iter::<f64>()?.as_slice()),
+            SqlType::String | SqlType::FixedString(_) => {
+                let strings = column.iter::<&[u8]>()?;
+                ColumnView::Strings(StringsView {
+                    inner: strings.inner,
+                    start: 0,
+                    end: strings.size,
+                })
+            }
+            _ => {
+                return Err(Error::FromSql(FromSqlError::InvalidType {
+                    src: column.sql_type().to_string(),
+                    dst: "ColumnView".into(),
+                }))
+            }
+        })
+    }
+
+    /// Returns the number of rows.
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnView::UInt8(values) => values.len(),
+            ColumnView::UInt16(values) => values.len(),
+            ColumnView::UInt32(values) => values.len(),
+            ColumnView::UInt64(values) => values.len(),
+            ColumnView::Int8(values) => values.len(),
+            ColumnView::Int16(values) => values.len(),
+            ColumnView::Int32(values) => values.len(),
+            ColumnView::Int64(values) => values.len(),
+            ColumnView::Float32(values) => values.len(),
+            ColumnView::Float64(values) => values.len(),
+            ColumnView::Strings(strings) => strings.len(),
+        }
+    }
+
+    /// Returns `true` if the column has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Iterates rows of an `Array(String)` column as `StringsView`, see `Column::string_arrays_iter`.
+pub struct StringArrayIterator<'a> {
+    inner: StringInnerIterator<'a>,
+    offsets: &'a [u64],
+    index: usize,
+}
+
+impl<'a> StringArrayIterator<'a> {
+    pub(crate) fn new(column: &'a Column<Simple>, column_type: SqlType) -> Result<Self> {
+        let strings = if let SqlType::Array(inner_type) = column_type {
+            <&[u8]>::iter(column, inner_type.clone())?
+        } else {
+            return Err(Error::FromSql(FromSqlError::InvalidType {
+                src: column.sql_type().to_string(),
+                dst: "Array(String)".into(),
+            }));
+        };
+
+        let offsets = unsafe {
+            let mut ptr: *const u8 = ptr::null();
+            let mut size: usize = 0;
+            column.get_internal(
+                &[&mut ptr, &mut size as *mut usize as *mut *const u8],
+                column_type.level(),
+                0,
+            )?;
+            assert_ne!(ptr, ptr::null());
+            slice::from_raw_parts(ptr as *const u64, size)
+        };
+
+        Ok(StringArrayIterator {
+            inner: strings.inner,
+            offsets,
+            index: 0,
+        })
+    }
+}
+
+impl ExactSizeIterator for StringArrayIterator<'_> {
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.offsets.len() - self.index
+    }
+}
+
+impl<'a> Iterator for StringArrayIterator<'a> {
+    type Item = StringsView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = *self.offsets.get(self.index)? as usize;
+        let start = match self.index {
+            0 => 0,
+            index => self.offsets[index - 1] as usize,
+        };
+
+        self.index += 1;
+        Some(StringsView {
+            inner: self.inner,
+            start,
+            end,
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let exact = self.len();
+        (exact, Some(exact))
+    }
+}
+
+impl FusedIterator for StringArrayIterator<'_> {}
+
 impl<'a, T> Iterable<'a, Simple> for Option<T>
 where
     T: Iterable<'a, Simple>,
@@ -316,7 +515,7 @@ where
         props: u32,
     ) -> Result<Self::Iter> {
         let inner = if let SqlType::Nullable(inner_type) = column_type {
-            T::iter(column, inner_type.clone())?
+            T::iter_with_props(column, inner_type.clone(), props)?
         } else {
             return Err(Error::FromSql(FromSqlError::InvalidType {
                 src: column.sql_type().to_string(),
@@ -372,7 +571,7 @@ where
             column.get_internal(
                 &[&mut ptr, &mut size as *mut usize as *mut *const u8],
                 column_type.level(),
-                0,
+                props,
             )?;
             assert_ne!(ptr, ptr::null());
             slice::from_raw_parts(ptr as *const u64, size)
@@ -387,18 +586,14 @@ where
     }
 }
 
+/// Iterates the chunks of a `Complex` column in order. The iterators of all chunks
+/// are made up front, so a type mismatch fails `iter` instead of panicking in `next`.
 pub struct ComplexIterator<'a, T>
 where
     T: Iterable<'a, Simple>,
 {
-    column_type: SqlType,
-
-    data: &'a Vec<ArcColumnData>,
-
-    current_index: usize,
+    chunks: std::vec::IntoIter<<T as Iterable<'a, Simple>>::Iter>,
     current: Option<<T as Iterable<'a, Simple>>::Iter>,
-
-    _marker: marker::PhantomData<T>,
 }
 
 impl<'a, T> Iterator for ComplexIterator<'a, T>
@@ -408,35 +603,11 @@ where
     type Item = <<T as Iterable<'a, Simple>>::Iter as Iterator>::Item;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current_index == self.data.len() && self.current.is_none() {
-            return None;
-        }
-
-        if self.current.is_none() {
-            let column: Column<Simple> = Column {
-                name: String::new(),
-                data: self.data[self.current_index].clone(),
-                _marker: marker::PhantomData,
-            };
-
-            let iter =
-                unsafe { T::iter(mem::transmute(&column), self.column_type.clone()) }.unwrap();
-
-            self.current = Some(iter);
-            self.current_index += 1;
-        }
-
-        let ret = match self.current {
-            None => None,
-            Some(ref mut iter) => iter.next(),
-        };
-
-        match ret {
-            None => {
-                self.current = None;
-                self.next()
+        loop {
+            if let Some(item) = self.current.as_mut().and_then(Iterator::next) {
+                return Some(item);
             }
-            Some(r) => Some(r),
+            self.current = Some(self.chunks.next()?);
         }
     }
 }
@@ -464,14 +635,281 @@ where
             &*data
         };
 
-        Ok(ComplexIterator {
-            column_type,
-            data,
+        let chunks = data
+            .iter()
+            .map(|data| {
+                let column: Column<Simple> = Column {
+                    name: String::new(),
+                    data: data.clone(),
+                    _marker: marker::PhantomData,
+                };
+                // The iterators point into the chunk data, which outlives the column.
+                unsafe {
+                    T::iter_with_props(
+                        mem::transmute::<&Column<Simple>, &'a Column<Simple>>(&column),
+                        column_type.clone(),
+                        props,
+                    )
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-            current_index: 0,
+        Ok(ComplexIterator {
+            chunks: chunks.into_iter(),
             current: None,
-
-            _marker: marker::PhantomData,
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        block::Block,
+        column::{self, ArcColumnWrapper, ColumnData},
+        value::Value,
+    };
+
+    fn nullable_array_column(rows: &[Vec<Option<u32>>]) -> Column<Simple> {
+        let sql_type = SqlType::Array(SqlType::Nullable(SqlType::UInt32.into()).into());
+        let mut data =
+            <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, rows.len()).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            for row in rows {
+                let values = row.iter().map(|v| Value::from(*v)).collect();
+                data.push(Value::Array(
+                    SqlType::Nullable(SqlType::UInt32.into()).into(),
+                    Arc::new(values),
+                ));
+            }
+        }
+        column::new_column("vals", data)
+    }
+
+    #[test]
+    fn test_array_of_nullable() {
+        let rows = vec![
+            vec![Some(1_u32), None, Some(3)],
+            vec![],
+            vec![None],
+            vec![Some(4), Some(5)],
+            vec![None, None, Some(6), None],
+        ];
+        let column = nullable_array_column(&rows);
+
+        let actual: Vec<Vec<Option<u32>>> = column
+            .iter::<Vec<Option<u32>>>()
+            .unwrap()
+            .map(|row| row.into_iter().map(|v| v.copied()).collect())
+            .collect();
+
+        assert_eq!(actual, rows);
+    }
+
+    #[test]
+    fn test_complex_order() {
+        let blocks = [
+            Block::<Simple>::new().column("id", vec![1_u32, 2]),
+            Block::<Simple>::new().column("id", Vec::<u32>::new()),
+            Block::<Simple>::new().column("id", vec![3_u32]),
+            Block::<Simple>::new().column("id", vec![4_u32, 5, 6]),
+        ];
+        let block = Block::concat(&blocks);
+        let column = block.get_column("id").unwrap();
+
+        let actual: Vec<u32> = column.iter::<u32>().unwrap().copied().collect();
+        assert_eq!(actual, [1, 2, 3, 4, 5, 6]);
+
+        assert!(column.iter::<u64>().is_err());
+        assert!(column.iter::<&[u8]>().is_err());
+    }
+
+    #[test]
+    fn test_array_of_nullable_complex() {
+        let first = vec![vec![Some(1_u32), None], vec![]];
+        let second = vec![vec![None, Some(2)], vec![Some(3)]];
+        let blocks = [
+            Block::<Simple>::new().column("vals", nullable_array_column(&first)),
+            Block::<Simple>::new().column("vals", nullable_array_column(&second)),
+        ];
+        let block = Block::concat(&blocks);
+
+        let actual: Vec<Vec<Option<u32>>> = block
+            .get_column("vals")
+            .unwrap()
+            .iter::<Vec<Option<u32>>>()
+            .unwrap()
+            .map(|row| row.into_iter().map(|v| v.copied()).collect())
+            .collect();
+
+        assert_eq!(actual, [first, second].concat());
+    }
+
+    fn nested_array_column(rows: &[Vec<Vec<f64>>]) -> Column<Simple> {
+        let inner_type = SqlType::Array(SqlType::Float64.into());
+        let sql_type = SqlType::Array(inner_type.clone().into());
+        let mut data =
+            <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, rows.len()).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            for row in rows {
+                let values = row
+                    .iter()
+                    .map(|vs| {
+                        let vs = vs.iter().copied().map(Value::from).collect();
+                        Value::Array(SqlType::Float64.into(), Arc::new(vs))
+                    })
+                    .collect();
+                data.push(Value::Array(inner_type.clone().into(), Arc::new(values)));
+            }
+        }
+        column::new_column("vals", data)
+    }
+
+    #[test]
+    fn test_nested_arrays() {
+        let rows = vec![
+            vec![vec![1.0, 2.0], vec![], vec![3.0]],
+            vec![],
+            vec![vec![]],
+            vec![vec![4.0], vec![5.0, 6.0, 7.0]],
+        ];
+        let column = nested_array_column(&rows);
+
+        let actual: Vec<Vec<Vec<f64>>> = column
+            .iter::<Vec<Vec<f64>>>()
+            .unwrap()
+            .map(|row| {
+                row.into_iter()
+                    .map(|vs| vs.into_iter().copied().collect())
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(actual, rows);
+    }
+
+    #[test]
+    fn test_nested_arrays_load() {
+        let rows = vec![
+            vec![vec![1.0], vec![2.0, 3.0]],
+            vec![],
+            vec![vec![], vec![4.0]],
+        ];
+        let block = Block::<Simple>::new().column("vals", nested_array_column(&rows));
+
+        let mut encoder = crate::binary::Encoder::new();
+        block.write(&mut encoder);
+        let mut reader = std::io::Cursor::new(encoder.get_buffer_ref());
+        let block = Block::<Simple>::load(&mut reader, chrono_tz::Tz::Zulu).unwrap();
+
+        let actual: Vec<Vec<Vec<f64>>> = block
+            .get_column("vals")
+            .unwrap()
+            .iter::<Vec<Vec<f64>>>()
+            .unwrap()
+            .map(|row| {
+                row.into_iter()
+                    .map(|vs| vs.into_iter().copied().collect())
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(actual, rows);
+        assert_eq!(block.get::<Vec<f64>, _>(0, "vals").ok(), None);
+    }
+
+    #[test]
+    fn test_nested_arrays_empty() {
+        let column = nested_array_column(&[]);
+        assert_eq!(column.iter::<Vec<Vec<f64>>>().unwrap().count(), 0);
+
+        let column = nested_array_column(&[vec![], vec![]]);
+        let actual: Vec<usize> = column
+            .iter::<Vec<Vec<f64>>>()
+            .unwrap()
+            .map(|row| row.len())
+            .collect();
+        assert_eq!(actual, [0, 0]);
+    }
+
+    const NULLABLE_STRINGS: [Option<&str>; 8] = [
+        None,
+        Some("foo"),
+        None,
+        None,
+        Some(""),
+        Some("bar baz"),
+        None,
+        Some("x"),
+    ];
+
+    fn as_strs<'a>(values: impl Iterator<Item = Option<&'a [u8]>>) -> Vec<Option<&'a str>> {
+        values
+            .map(|v| v.map(|bytes| std::str::from_utf8(bytes).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_nullable_strings() {
+        let block = Block::<Simple>::new().column("s", NULLABLE_STRINGS.to_vec());
+        let block = Block::from_bytes(&block.to_bytes(), chrono_tz::Tz::Zulu).unwrap();
+        let column = block.get_column("s").unwrap();
+
+        let actual = as_strs(column.iter::<Option<&[u8]>>().unwrap());
+        assert_eq!(actual, NULLABLE_STRINGS);
+
+        for (row, expected) in NULLABLE_STRINGS.iter().enumerate() {
+            let value: Option<String> = block.get(row, "s").unwrap();
+            assert_eq!(value.as_deref(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_nullable_strings_complex() {
+        let (first, second) = NULLABLE_STRINGS.split_at(3);
+        let blocks = [
+            Block::<Simple>::new().column("s", first.to_vec()),
+            Block::<Simple>::new().column("s", second.to_vec()),
+        ];
+        let block = Block::concat(&blocks);
+        let column = block.get_column("s").unwrap();
+
+        let actual = as_strs(column.iter::<Option<&[u8]>>().unwrap());
+        assert_eq!(actual, NULLABLE_STRINGS);
+    }
+
+    #[test]
+    fn test_array_of_nullable_strings() {
+        let rows = vec![
+            NULLABLE_STRINGS[..2].to_vec(),
+            vec![],
+            NULLABLE_STRINGS[2..].to_vec(),
+        ];
+        let item_type = SqlType::Nullable(SqlType::String.into());
+        let sql_type = SqlType::Array(item_type.clone().into());
+        let mut data =
+            <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, rows.len()).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            for row in &rows {
+                let values = row.iter().map(|v| Value::from(*v)).collect();
+                data.push(Value::Array(item_type.clone().into(), Arc::new(values)));
+            }
+        }
+        let block = Block::<Simple>::new().column("s", column::new_column::<Simple>("s", data));
+        let block = Block::from_bytes(&block.to_bytes(), chrono_tz::Tz::Zulu).unwrap();
+
+        let actual: Vec<Vec<Option<&str>>> = block
+            .get_column("s")
+            .unwrap()
+            .iter::<Vec<Option<&[u8]>>>()
+            .unwrap()
+            .map(|row| as_strs(row.into_iter()))
+            .collect();
+        assert_eq!(actual, rows);
+    }
+}
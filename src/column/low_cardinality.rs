@@ -0,0 +1,377 @@
+use std::sync::Arc;
+
+use chrono_tz::Tz;
+use either::Either;
+
+use crate::column::{
+    column_data::{ArcColumnData, BoxColumnData},
+    ArcColumnWrapper, ColumnData,
+};
+
+use crate::{
+    binary::{Encoder, ReadEx},
+    error::{DriverError, Error, FromSqlError, Result},
+    types::SqlType,
+    value::{Value, ValueRef},
+};
+
+/// `SharedDictionariesWithAdditionalKeys`, the only serialization version.
+const KEYS_VERSION: u64 = 1;
+
+const INDEX_TYPE_MASK: u64 = 0xff;
+const NEED_GLOBAL_DICTIONARY_BIT: u64 = 1 << 8;
+const HAS_ADDITIONAL_KEYS_BIT: u64 = 1 << 9;
+
+const INDEX_U8: u64 = 0;
+const INDEX_U16: u64 = 1;
+const INDEX_U32: u64 = 2;
+const INDEX_U64: u64 = 3;
+
+/// `LowCardinality(T)` is sent as a dictionary of keys and an index into it for every row.
+/// The values are materialized on load, so the column behaves like a plain `T` column.
+///
+/// For `LowCardinality(Nullable(T))` the dictionary has type `T` and the key 0 stands for NULL.
+///
+/// Only top-level `LowCardinality` columns are supported: inside of `Array`, `Map` or
+/// `Tuple` the dictionary header is sent before the data of the enclosing column, so
+/// these loaders reject it with `check_not_nested`.
+pub(crate) struct LowCardinalityColumnData {
+    pub(crate) inner: ArcColumnData,
+}
+
+impl LowCardinalityColumnData {
+    pub(crate) fn load<R: ReadEx>(
+        reader: &mut R,
+        type_name: &str,
+        size: usize,
+        tz: Tz,
+    ) -> Result<Self> {
+        let sql_type = SqlType::from_type_name(type_name)?;
+        let mut inner = <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type.clone(), size)?;
+
+        if size == 0 {
+            return Ok(Self { inner });
+        }
+
+        let version: u64 = reader.read_scalar()?;
+        if version != KEYS_VERSION {
+            return Err(Error::Driver(DriverError::MalformedData(format!(
+                "Unknown LowCardinality keys version {}.",
+                version
+            ))));
+        }
+
+        let serialization_type: u64 = reader.read_scalar()?;
+        if serialization_type & NEED_GLOBAL_DICTIONARY_BIT != 0 {
+            return Err(Error::Other(
+                "LowCardinality global dictionary is not supported.".into(),
+            ));
+        }
+        if serialization_type & HAS_ADDITIONAL_KEYS_BIT == 0 {
+            return Err(Error::Other(
+                "LowCardinality column without keys is not supported.".into(),
+            ));
+        }
+
+        let (nullable, dictionary_type) = match sql_type {
+            SqlType::Nullable(nested) => (true, nested.clone()),
+            _ => (false, sql_type),
+        };
+
+        let keys_count: u64 = reader.read_scalar()?;
+        let keys = <dyn ColumnData>::load_data::<ArcColumnWrapper, _>(
+            reader,
+            &dictionary_type.to_string(),
+            keys_count as usize,
+            tz,
+        )?;
+
+        let rows: u64 = reader.read_scalar()?;
+        if rows as usize != size {
+            return Err(Error::Driver(DriverError::MalformedData(format!(
+                "LowCardinality column has {} rows, expected {}.",
+                rows, size
+            ))));
+        }
+
+        let indexes = read_indexes(reader, serialization_type & INDEX_TYPE_MASK, size)?;
+
+        let data = Arc::get_mut(&mut inner).unwrap();
+        for index in indexes {
+            let index = index as usize;
+            if index >= keys.len() {
+                return Err(Error::Driver(DriverError::MalformedData(format!(
+                    "LowCardinality index {} is out of dictionary.",
+                    index
+                ))));
+            }
+
+            let value = match (nullable, index) {
+                (true, 0) => Value::Nullable(Either::Left(dictionary_type.clone().into())),
+                (true, _) => Value::Nullable(Either::Right(Box::new(keys.at(index).into()))),
+                (false, _) => keys.at(index).into(),
+            };
+            data.push(value);
+        }
+
+        Ok(Self { inner })
+    }
+}
+
+/// Fails if `type_name`, the type of a nested column, contains a `LowCardinality`.
+pub(crate) fn check_not_nested(type_name: &str) -> Result<()> {
+    if type_name.contains("LowCardinality(") {
+        return Err(Error::FromSql(FromSqlError::UnsupportedColumnType(
+            type_name.to_string().into(),
+        )));
+    }
+    Ok(())
+}
+
+fn read_indexes<R: ReadEx>(reader: &mut R, index_type: u64, size: usize) -> Result<Vec<u64>> {
+    let mut indexes = Vec::with_capacity(size);
+    for _ in 0..size {
+        let index = match index_type {
+            INDEX_U8 => u64::from(reader.read_scalar::<u8>()?),
+            INDEX_U16 => u64::from(reader.read_scalar::<u16>()?),
+            INDEX_U32 => u64::from(reader.read_scalar::<u32>()?),
+            INDEX_U64 => reader.read_scalar::<u64>()?,
+            _ => {
+                return Err(Error::Driver(DriverError::MalformedData(format!(
+                    "Unknown LowCardinality index type {}.",
+                    index_type
+                ))))
+            }
+        };
+        indexes.push(index);
+    }
+    Ok(indexes)
+}
+
+impl ColumnData for LowCardinalityColumnData {
+    fn sql_type(&self) -> SqlType {
+        SqlType::LowCardinality(self.inner.sql_type().into())
+    }
+
+    /// Writes a dictionary with a key for every row, NULL being the key 0.
+    fn save(&self, encoder: &mut Encoder, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+
+        let (nullable, dictionary_type) = match self.inner.sql_type() {
+            SqlType::Nullable(nested) => (true, nested.clone()),
+            sql_type => (false, sql_type),
+        };
+
+        let mut keys =
+            <dyn ColumnData>::from_type::<ArcColumnWrapper>(dictionary_type.clone(), end - start)
+                .unwrap();
+        let mut indexes = Vec::with_capacity(end - start);
+        {
+            let keys = Arc::get_mut(&mut keys).unwrap();
+            if nullable {
+                keys.push(Value::default(dictionary_type));
+            }
+
+            for i in start..end {
+                match self.inner.at(i) {
+                    ValueRef::Nullable(Either::Left(_)) => indexes.push(0_u64),
+                    ValueRef::Nullable(Either::Right(value)) => {
+                        indexes.push(keys.len() as u64);
+                        keys.push((*value).into());
+                    }
+                    value => {
+                        indexes.push(keys.len() as u64);
+                        keys.push(value.into());
+                    }
+                }
+            }
+        }
+
+        encoder.write(KEYS_VERSION);
+        encoder.write(INDEX_U64 | HAS_ADDITIONAL_KEYS_BIT);
+        encoder.write(keys.len() as u64);
+        keys.save(encoder, 0, keys.len());
+        encoder.write(indexes.len() as u64);
+        for index in indexes {
+            encoder.write(index);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn push(&mut self, value: Value) {
+        Arc::get_mut(&mut self.inner).unwrap().push(value);
+    }
+
+    fn at(&self, index: usize) -> ValueRef<'_> {
+        self.inner.at(index)
+    }
+
+    fn clone_instance(&self) -> BoxColumnData {
+        Box::new(Self {
+            inner: self.inner.clone(),
+        })
+    }
+
+    unsafe fn get_internal(
+        &self,
+        pointers: &[*mut *const u8],
+        level: u8,
+        props: u32,
+    ) -> Result<()> {
+        self.inner.get_internal(pointers, level, props)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        block::Block,
+        column::{self, Simple},
+    };
+
+    fn low_cardinality_block(values: Vec<Value>, sql_type: SqlType) -> Block<Simple> {
+        let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(
+            SqlType::LowCardinality(sql_type.into()),
+            values.len(),
+        )
+        .unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            for value in values {
+                data.push(value);
+            }
+        }
+        Block::<Simple>::new().column("lc", column::new_column::<Simple>("lc", data))
+    }
+
+    #[test]
+    fn test_nested_rejected() {
+        for type_name in [
+            "Array(LowCardinality(String))",
+            "Map(LowCardinality(String), UInt8)",
+            "Tuple(UInt8, Array(LowCardinality(String)))",
+        ] {
+            // Rejected before anything is read, otherwise the dictionary header would be
+            // taken for offsets.
+            let mut reader = Cursor::new(vec![1_u8; 64]);
+            match <dyn ColumnData>::load_data::<ArcColumnWrapper, _>(
+                &mut reader,
+                type_name,
+                1,
+                Tz::Zulu,
+            ) {
+                Err(Error::FromSql(FromSqlError::UnsupportedColumnType(name))) => {
+                    assert!(name.contains("LowCardinality(String)"))
+                }
+                Err(err) => panic!("unexpected error {}", err),
+                Ok(_) => panic!("{} should be rejected", type_name),
+            }
+            assert_eq!(reader.position(), 0);
+        }
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let block = low_cardinality_block(
+            vec!["foo".into(), "bar".into(), "foo".into()],
+            SqlType::String,
+        );
+
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let rblock = Block::load(&mut reader, Tz::Zulu).unwrap();
+
+        assert_eq!(block, rblock);
+        assert_eq!(
+            rblock.get_column("lc").unwrap().sql_type().to_string(),
+            "LowCardinality(String)"
+        );
+
+        let values: Vec<&[u8]> = rblock
+            .get_column("lc")
+            .unwrap()
+            .iter::<&[u8]>()
+            .unwrap()
+            .collect();
+        assert_eq!(values, [b"foo", b"bar", b"foo"]);
+        assert_eq!(rblock.get::<String, _>(1, "lc").unwrap(), "bar");
+    }
+
+    #[test]
+    fn test_write_and_read_nullable() {
+        let block = low_cardinality_block(
+            vec![Some("foo").into(), None::<&str>.into(), Some("").into()],
+            SqlType::Nullable(SqlType::String.into()),
+        );
+
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let rblock = Block::load(&mut reader, Tz::Zulu).unwrap();
+
+        assert_eq!(block, rblock);
+
+        let values: Vec<Option<&[u8]>> = rblock
+            .get_column("lc")
+            .unwrap()
+            .iter::<Option<&[u8]>>()
+            .unwrap()
+            .collect();
+        assert_eq!(values, [Some(&b"foo"[..]), None, Some(&b""[..])]);
+        assert_eq!(rblock.get::<Option<String>, _>(1, "lc").unwrap(), None);
+    }
+
+    #[test]
+    fn test_load_index_types() {
+        for (index_type, width) in [
+            (INDEX_U8, 1),
+            (INDEX_U16, 2),
+            (INDEX_U32, 4),
+            (INDEX_U64, 8),
+        ] {
+            let mut encoder = Encoder::new();
+            encoder.write(KEYS_VERSION);
+            encoder.write(index_type | HAS_ADDITIONAL_KEYS_BIT);
+            encoder.write(2_u64);
+            encoder.string("a");
+            encoder.string("b");
+            encoder.write(4_u64);
+            for index in [1_u8, 0, 0, 1] {
+                let mut bytes = vec![0_u8; width];
+                bytes[0] = index;
+                encoder.write_bytes(&bytes);
+            }
+
+            let mut reader = Cursor::new(encoder.get_buffer_ref());
+            let data = LowCardinalityColumnData::load(&mut reader, "String", 4, Tz::Zulu).unwrap();
+
+            let values: Vec<String> = (0..4).map(|i| data.at(i).as_string().unwrap()).collect();
+            assert_eq!(values, ["b", "a", "a", "b"]);
+        }
+    }
+
+    #[test]
+    fn test_load_bad_index() {
+        let mut encoder = Encoder::new();
+        encoder.write(KEYS_VERSION);
+        encoder.write(INDEX_U8 | HAS_ADDITIONAL_KEYS_BIT);
+        encoder.write(1_u64);
+        encoder.string("a");
+        encoder.write(1_u64);
+        encoder.write(1_u8);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        assert!(LowCardinalityColumnData::load(&mut reader, "String", 1, Tz::Zulu).is_err());
+    }
+}
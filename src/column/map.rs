@@ -0,0 +1,259 @@
+use chrono_tz::Tz;
+use std::sync::Arc;
+
+use crate::column::{
+    array::check_offsets,
+    column_data::{ArcColumnData, BoxColumnData},
+    list::List,
+    low_cardinality::check_not_nested,
+    ArcColumnWrapper, ColumnData,
+};
+
+use crate::{
+    binary::{Encoder, ReadEx},
+    error::{DriverError, Error, Result},
+    types::SqlType,
+    value::{Value, ValueRef},
+};
+
+/// `Map(K, V)` is stored as `Array(Tuple(K, V))`: offsets followed by keys and values columns.
+pub(crate) struct MapColumnData {
+    pub(crate) keys: ArcColumnData,
+    pub(crate) values: ArcColumnData,
+    pub(crate) offsets: List<u64>,
+}
+
+impl MapColumnData {
+    pub(crate) fn load<R: ReadEx>(
+        reader: &mut R,
+        key_type: &str,
+        value_type: &str,
+        rows: usize,
+        tz: Tz,
+    ) -> Result<Self> {
+        check_not_nested(key_type)?;
+        check_not_nested(value_type)?;
+
+        let mut offsets = List::with_capacity(rows);
+        offsets.resize(rows, 0_u64);
+        reader.read_bytes(offsets.as_mut())?;
+        check_offsets(&offsets)?;
+
+        let size = match rows {
+            0 => 0,
+            _ => usize::try_from(offsets.at(rows - 1)).map_err(|_| {
+                Error::Driver(DriverError::MalformedData(format!(
+                    "map offset {} is out of range.",
+                    offsets.at(rows - 1)
+                )))
+            })?,
+        };
+        let keys = <dyn ColumnData>::load_data::<ArcColumnWrapper, _>(reader, key_type, size, tz)?;
+        let values =
+            <dyn ColumnData>::load_data::<ArcColumnWrapper, _>(reader, value_type, size, tz)?;
+
+        // `at` slices keys and values by the offsets, so both must cover the last one.
+        if keys.len() != size || values.len() != size {
+            return Err(Error::Driver(DriverError::MalformedData(format!(
+                "map offsets end at {} but there are {} keys and {} values.",
+                size,
+                keys.len(),
+                values.len()
+            ))));
+        }
+
+        Ok(MapColumnData {
+            keys,
+            values,
+            offsets,
+        })
+    }
+}
+
+impl ColumnData for MapColumnData {
+    fn sql_type(&self) -> SqlType {
+        let key_type = self.keys.sql_type();
+        let value_type = self.values.sql_type();
+        SqlType::Map(key_type.into(), value_type.into())
+    }
+
+    fn save(&self, encoder: &mut Encoder, start: usize, end: usize) {
+        // Offsets of a slice are relative to its first entry, as for arrays.
+        let first = if start > 0 {
+            self.offsets.at(start - 1)
+        } else {
+            0_u64
+        };
+        let mut offset = first;
+
+        for i in start..end {
+            offset = self.offsets.at(i);
+            encoder.write(offset - first);
+        }
+
+        self.keys.save(encoder, first as usize, offset as usize);
+        self.values.save(encoder, first as usize, offset as usize);
+    }
+
+    fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    fn push(&mut self, value: Value) {
+        if let Value::Map(_, _, entries) = value {
+            let offsets_len = self.offsets.len();
+            let prev = if offsets_len == 0 {
+                0_usize
+            } else {
+                self.offsets.at(offsets_len - 1) as usize
+            };
+
+            self.offsets.push((prev + entries.len()) as u64);
+
+            let keys_column = Arc::get_mut(&mut self.keys).unwrap();
+            for (k, _) in entries.iter() {
+                keys_column.push(k.clone());
+            }
+
+            let values_column = Arc::get_mut(&mut self.values).unwrap();
+            for (_, v) in entries.iter() {
+                values_column.push(v.clone());
+            }
+        } else {
+            panic!("value should be a map")
+        }
+    }
+
+    fn at(&self, index: usize) -> ValueRef<'_> {
+        let key_type = self.keys.sql_type();
+        let value_type = self.values.sql_type();
+
+        let start = if index > 0 {
+            self.offsets.at(index - 1) as usize
+        } else {
+            0_usize
+        };
+        let end = self.offsets.at(index) as usize;
+        let mut entries = Vec::with_capacity(end - start);
+        for i in start..end {
+            entries.push((self.keys.at(i), self.values.at(i)));
+        }
+        ValueRef::Map(key_type.into(), value_type.into(), Arc::new(entries))
+    }
+
+    fn clone_instance(&self) -> BoxColumnData {
+        Box::new(Self {
+            keys: self.keys.clone(),
+            values: self.values.clone(),
+            offsets: self.offsets.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, io::Cursor};
+
+    use super::*;
+    use crate::{
+        block::Block,
+        column::{self, Simple},
+    };
+
+    #[test]
+    fn test_write_and_read() {
+        let sql_type = SqlType::Map(SqlType::String.into(), SqlType::UInt64.into());
+        let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, 2).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            data.push(Value::Map(
+                SqlType::String.into(),
+                SqlType::UInt64.into(),
+                Arc::new(vec![("a".into(), 1_u64.into()), ("b".into(), 2_u64.into())]),
+            ));
+            data.push(Value::Map(
+                SqlType::String.into(),
+                SqlType::UInt64.into(),
+                Arc::new(vec![]),
+            ));
+        }
+        let block = Block::<Simple>::new().column("m", column::new_column::<Simple>("m", data));
+
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let rblock = Block::load(&mut reader, Tz::Zulu).unwrap();
+
+        assert_eq!(block, rblock);
+
+        let first: HashMap<String, u64> = rblock.get(0, "m").unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first["b"], 2);
+
+        let second: Vec<(String, u64)> = rblock.get(1, "m").unwrap();
+        assert!(second.is_empty());
+    }
+
+    #[test]
+    fn test_save_slice() {
+        let sql_type = SqlType::Map(SqlType::UInt32.into(), SqlType::UInt32.into());
+        let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, 3).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            for entries in [vec![(1_u32, 2_u32)], vec![(3, 4), (5, 6)], vec![(7, 8)]] {
+                data.push(Value::Map(
+                    SqlType::UInt32.into(),
+                    SqlType::UInt32.into(),
+                    Arc::new(
+                        entries
+                            .into_iter()
+                            .map(|(k, v)| (k.into(), v.into()))
+                            .collect(),
+                    ),
+                ));
+            }
+        }
+
+        let mut encoder = Encoder::new();
+        data.save(&mut encoder, 1, 3);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let loaded = MapColumnData::load(&mut reader, "UInt32", "UInt32", 2, Tz::Zulu).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.at(0), data.at(1));
+        assert_eq!(loaded.at(1), data.at(2));
+    }
+
+    #[test]
+    fn test_load_malformed_offsets() {
+        let mut decreasing = Encoder::new();
+        decreasing.write(2_u64);
+        decreasing.write(1_u64);
+        for item in [1_u32, 2, 3, 4] {
+            decreasing.write(item);
+        }
+
+        let mut reader = Cursor::new(decreasing.get_buffer_ref());
+        match MapColumnData::load(&mut reader, "UInt32", "UInt32", 2, Tz::Zulu) {
+            Err(Error::Driver(DriverError::MalformedData(message))) => assert_eq!(
+                message,
+                "offset 1 of row 1 is less than the previous offset 2."
+            ),
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("the offsets should be rejected"),
+        }
+
+        // The last offset points past the keys and values sent.
+        let mut overflowing = Encoder::new();
+        overflowing.write(1_u64);
+        overflowing.write(5_u64);
+        for item in [1_u32, 2, 3, 4] {
+            overflowing.write(item);
+        }
+
+        let mut reader = Cursor::new(overflowing.get_buffer_ref());
+        assert!(MapColumnData::load(&mut reader, "UInt32", "UInt32", 2, Tz::Zulu).is_err());
+    }
+}
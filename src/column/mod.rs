@@ -4,28 +4,43 @@ use chrono_tz::Tz;
 
 use crate::{
     binary::{Encoder, ReadEx},
-    column::{column_data::ArcColumnData, iter::Iterable},
-    error::Result,
+    column::{
+        column_data::ArcColumnData,
+        fixed_string::{FixedStringAdapter, NullableFixedStringAdapter},
+        iter::{Iterable, StringArrayIterator},
+        string::StringAdapter,
+    },
+    error::{Error, FromSqlError, Result},
     types::SqlType,
     value::{Value, ValueRef},
 };
 
 use self::chunk::ChunkColumnData;
-pub(crate) use self::{column_data::ColumnData, string_pool::StringPool};
-pub use self::{concat::ConcatColumnData, numeric::VectorColumnData};
+pub(crate) use self::{column_data::ColumnData, string::StringColumnData, string_pool::StringPool};
+pub use self::{
+    concat::ConcatColumnData,
+    iter::{ColumnView, StringsView},
+    numeric::VectorColumnData,
+};
 
+mod aggregate;
 mod array;
+mod boolean;
 mod chunk;
 mod column_data;
 mod concat;
-mod factory;
+pub(crate) mod factory;
 pub(crate) mod fixed_string;
 pub(crate) mod iter;
 mod list;
+mod low_cardinality;
+mod map;
+mod nothing;
 mod nullable;
 mod numeric;
 mod string;
 mod string_pool;
+mod tuple;
 
 /// Represents Clickhouse Column
 pub struct Column<K: ColumnType> {
@@ -110,6 +125,25 @@ impl Column<Simple> {
             }
         }
     }
+
+    /// Returns the values of a `String` or `FixedString(N)` column as byte slices
+    /// borrowed from the column data, without building a `ValueRef` per row.
+    ///
+    /// `FixedString(N)` values keep their trailing `\0` padding.
+    pub fn strings_iter(&self) -> Result<impl ExactSizeIterator<Item = &[u8]>> {
+        self.iter::<&[u8]>()
+    }
+
+    /// Returns the rows of an `Array(String)` or `Array(FixedString(N))` column as views
+    /// borrowing the strings from the column data, unlike `iter::<Vec<&[u8]>>()`
+    /// which allocates a `Vec` per row.
+    pub fn string_arrays_iter(&self) -> Result<impl ExactSizeIterator<Item = StringsView<'_>>> {
+        let sql_type = match self.sql_type() {
+            SqlType::LowCardinality(inner) => inner.clone(),
+            sql_type => sql_type,
+        };
+        StringArrayIterator::new(self, sql_type)
+    }
 }
 
 impl<K: ColumnType> Column<K> {
@@ -130,7 +164,7 @@ impl<K: ColumnType> Column<K> {
     /// #         "password".to_string(),
     /// #         true,
     /// #     )
-    /// #     .build();
+    /// #     .build()?;
 
     /// #     let pool = Pool::new(config);
     /// #     let mut client = pool.get_handle().await?;
@@ -157,16 +191,24 @@ impl<K: ColumnType> Column<K> {
     /// # ret.unwrap()
     /// ```
     pub fn iter<'a, T: Iterable<'a, K>>(&'a self) -> Result<T::Iter> {
-        <T as Iterable<'a, K>>::iter(self, self.sql_type())
+        let sql_type = match self.sql_type() {
+            SqlType::LowCardinality(inner) => inner.clone(),
+            sql_type => sql_type,
+        };
+        <T as Iterable<'a, K>>::iter(self, sql_type)
     }
 }
 
 impl<K: ColumnType> Column<K> {
-    pub(crate) fn read<R: ReadEx>(reader: &mut R, size: usize, tz: Tz) -> Result<Column<K>> {
-        let name = reader.read_string()?;
-        let type_name = reader.read_string()?;
-        let data =
-            <dyn ColumnData>::load_data::<ArcColumnWrapper, _>(reader, &type_name, size, tz)?;
+    /// Reads the data of a column whose name and type were read by the caller.
+    pub(crate) fn read<R: ReadEx>(
+        reader: &mut R,
+        name: String,
+        type_name: &str,
+        size: usize,
+        tz: Tz,
+    ) -> Result<Column<K>> {
+        let data = <dyn ColumnData>::load_data::<ArcColumnWrapper, _>(reader, type_name, size, tz)?;
         let column = Self {
             name,
             data,
@@ -211,74 +253,57 @@ impl<K: ColumnType> Column<K> {
         }
     }
 
-    // pub(crate) fn cast_to(self, dst_type: SqlType) -> Result<Self> {
-    //     let src_type = self.sql_type();
-
-    //     if dst_type == src_type {
-    //         return Ok(self);
-    //     }
-
-    //     match (dst_type.clone(), src_type.clone()) {
-    //         (SqlType::FixedString(str_len), SqlType::String) => {
-    //             let name = self.name().to_owned();
-    //             let adapter = FixedStringAdapter {
-    //                 column: self,
-    //                 str_len,
-    //             };
-    //             Ok(Column {
-    //                 name,
-    //                 data: Arc::new(adapter),
-    //                 _marker: marker::PhantomData,
-    //             })
-    //         }
-    //         (
-    //             SqlType::Nullable(SqlType::FixedString(str_len)),
-    //             SqlType::Nullable(SqlType::String),
-    //         ) => {
-    //             let name = self.name().to_owned();
-    //             let adapter = NullableFixedStringAdapter {
-    //                 column: self,
-    //                 str_len: *str_len,
-    //             };
-    //             Ok(Column {
-    //                 name,
-    //                 data: Arc::new(adapter),
-    //                 _marker: marker::PhantomData,
-    //             })
-    //         }
-    //         (SqlType::String, SqlType::Array(SqlType::UInt8)) => {
-    //             let name = self.name().to_owned();
-    //             let adapter = StringAdapter { column: self };
-    //             Ok(Column {
-    //                 name,
-    //                 data: Arc::new(adapter),
-    //                 _marker: marker::PhantomData,
-    //             })
-    //         }
-    //         (SqlType::FixedString(n), SqlType::Array(SqlType::UInt8)) => {
-    //             let string_column = self.cast_to(SqlType::String)?;
-    //             string_column.cast_to(SqlType::FixedString(n))
-    //         }
-    //         _ => {
-    //             if let Some(data) = self.data.cast_to(&self.data, &dst_type) {
-    //                 let name = self.name().to_owned();
-    //                 Ok(Column {
-    //                     name,
-    //                     data,
-    //                     _marker: marker::PhantomData,
-    //                 })
-    //             } else {
-    //                 anyhow::bail!(
-    //                     format!(
-    //                         "InvalidType {} -> {}",
-    //                         src_type,
-    //                         dst_type
-    //                     )
-    //                 )
-    //             }
-    //         }
-    //     }
-    // }
+    /// Casts the column to `dst_type` on the client side.
+    ///
+    /// Supported casts are `String` to `FixedString(N)`, `Nullable(String)` to
+    /// `Nullable(FixedString(N))` and `Array(UInt8)` to `String` or `FixedString(N)`.
+    /// The cast column is meant to be written: values are converted when the column
+    /// is serialized.
+    pub fn cast_to(self, dst_type: SqlType) -> Result<Self> {
+        let src_type = self.sql_type();
+
+        if dst_type == src_type {
+            return Ok(self);
+        }
+
+        match (dst_type.clone(), src_type.clone()) {
+            (SqlType::FixedString(str_len), SqlType::String) => {
+                let name = self.name().to_owned();
+                let adapter = FixedStringAdapter {
+                    column: self,
+                    str_len,
+                };
+                Ok(new_column(&name, Arc::new(adapter)))
+            }
+            (
+                SqlType::Nullable(SqlType::FixedString(str_len)),
+                SqlType::Nullable(SqlType::String),
+            ) => {
+                let name = self.name().to_owned();
+                let adapter = NullableFixedStringAdapter {
+                    column: self,
+                    str_len: *str_len,
+                };
+                Ok(new_column(&name, Arc::new(adapter)))
+            }
+            (SqlType::String, SqlType::Array(SqlType::UInt8)) => {
+                let name = self.name().to_owned();
+                let adapter = StringAdapter { column: self };
+                Ok(new_column(&name, Arc::new(adapter)))
+            }
+            (SqlType::FixedString(n), SqlType::Array(SqlType::UInt8)) => {
+                let string_column = self.cast_to(SqlType::String)?;
+                string_column.cast_to(SqlType::FixedString(n))
+            }
+            _ => match self.data.cast_to(&self.data, &dst_type) {
+                Some(data) => Ok(new_column(self.name(), data)),
+                None => Err(Error::FromSql(FromSqlError::InvalidType {
+                    src: src_type.to_string(),
+                    dst: dst_type.to_string(),
+                })),
+            },
+        }
+    }
 
     pub(crate) fn push(&mut self, value: Value) {
         loop {
@@ -353,3 +378,108 @@ impl ColumnWrapper for BoxColumnWrapper {
         unimplemented!()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::block::Block;
+
+    fn write_and_read(column: Column<Simple>) -> Block<Simple> {
+        let block = Block::<Simple>::new().column("s", column);
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        Block::load(&mut reader, Tz::Zulu).unwrap()
+    }
+
+    #[test]
+    fn test_cast_string_to_fixed_string() {
+        let data = Vec::<&str>::column_from::<ArcColumnWrapper>(vec!["ab", "abcdef"]);
+        let column = new_column::<Simple>("s", data)
+            .cast_to(SqlType::FixedString(4))
+            .unwrap();
+        assert_eq!(column.sql_type(), SqlType::FixedString(4));
+
+        let block = write_and_read(column);
+        assert_eq!(
+            block.get_column("s").unwrap().sql_type(),
+            SqlType::FixedString(4)
+        );
+        assert_eq!(block.get::<&[u8], _>(0, "s").unwrap(), b"ab\0\0");
+        assert_eq!(block.get::<&[u8], _>(1, "s").unwrap(), b"abcd");
+    }
+
+    #[test]
+    fn test_cast_bytes_to_string() {
+        let data = Vec::<Vec<u8>>::column_from::<ArcColumnWrapper>(vec![b"foo".to_vec(), vec![]]);
+        let column = new_column::<Simple>("s", data)
+            .cast_to(SqlType::String)
+            .unwrap();
+
+        let block = write_and_read(column);
+        assert_eq!(block.get::<String, _>(0, "s").unwrap(), "foo");
+        assert_eq!(block.get::<String, _>(1, "s").unwrap(), "");
+    }
+
+    #[test]
+    fn test_strings_iter() {
+        let data = Vec::<&str>::column_from::<ArcColumnWrapper>(vec!["foo", "", "bar"]);
+        let column = new_column::<Simple>("s", data);
+        let values: Vec<&[u8]> = column.strings_iter().unwrap().collect();
+        assert_eq!(values, [&b"foo"[..], b"", b"bar"]);
+
+        let block = write_and_read(column.cast_to(SqlType::FixedString(2)).unwrap());
+        let values: Vec<&[u8]> = block
+            .get_column("s")
+            .unwrap()
+            .strings_iter()
+            .unwrap()
+            .collect();
+        assert_eq!(values, [b"fo", b"\0\0", b"ba"]);
+
+        let data = Vec::<u32>::column_from::<ArcColumnWrapper>(vec![1]);
+        assert!(new_column::<Simple>("n", data).strings_iter().is_err());
+    }
+
+    #[test]
+    fn test_string_arrays_iter() {
+        let block = write_and_read(
+            Block::<Simple>::new()
+                .column("tags", vec![vec!["a", "bc"], vec![], vec!["d"]])
+                .get_column("tags")
+                .unwrap()
+                .clone(),
+        );
+        let column = block.get_column("s").unwrap();
+
+        let rows: Vec<StringsView> = column.string_arrays_iter().unwrap().collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].iter().collect::<Vec<_>>(), [b"a" as &[u8], b"bc"]);
+        assert_eq!(rows[0].get(1), Some(&b"bc"[..]));
+        assert_eq!(rows[0].get(2), None);
+        assert!(rows[1].is_empty());
+        assert_eq!(format!("{:?}", rows[2]), "[[100]]");
+
+        let data = Vec::<&str>::column_from::<ArcColumnWrapper>(vec!["foo"]);
+        assert!(new_column::<Simple>("s", data)
+            .string_arrays_iter()
+            .is_err());
+    }
+
+    #[test]
+    fn test_cast_invalid() {
+        let data = Vec::<u32>::column_from::<ArcColumnWrapper>(vec![1, 2]);
+        let result = new_column::<Simple>("s", data).cast_to(SqlType::String);
+
+        match result {
+            Err(Error::FromSql(FromSqlError::InvalidType { src, dst })) => {
+                assert_eq!(src, "UInt32");
+                assert_eq!(dst, "String");
+            }
+            _ => panic!("cast from UInt32 to String should fail"),
+        }
+    }
+}
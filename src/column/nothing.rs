@@ -0,0 +1,136 @@
+use either::Either;
+
+use crate::{
+    binary::{Encoder, ReadEx},
+    error::Result,
+    types::SqlType,
+    value::{Value, ValueRef},
+};
+
+use crate::column::column_data::{BoxColumnData, ColumnData};
+
+/// `Nothing` is the type of `NULL` and of the items of `[]`, so it has no values:
+/// every row reads as NULL. The server still sends a byte per row, which is skipped.
+pub(crate) struct NothingColumnData {
+    len: usize,
+}
+
+impl NothingColumnData {
+    pub(crate) fn with_capacity(_capacity: usize) -> Self {
+        Self { len: 0 }
+    }
+
+    pub(crate) fn load<R: ReadEx>(reader: &mut R, size: usize) -> Result<Self> {
+        let mut data = vec![0_u8; size];
+        reader.read_bytes(&mut data)?;
+        Ok(Self { len: size })
+    }
+}
+
+impl ColumnData for NothingColumnData {
+    fn sql_type(&self) -> SqlType {
+        SqlType::Nothing
+    }
+
+    fn save(&self, encoder: &mut Encoder, start: usize, end: usize) {
+        encoder.write_bytes(&vec![b'0'; end - start]);
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn push(&mut self, _value: Value) {
+        self.len += 1;
+    }
+
+    fn at(&self, _index: usize) -> ValueRef<'_> {
+        ValueRef::Nullable(Either::Left(SqlType::Nothing.into()))
+    }
+
+    fn clone_instance(&self) -> BoxColumnData {
+        Box::new(Self { len: self.len })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{io::Cursor, sync::Arc};
+
+    use chrono_tz::Tz;
+
+    use super::*;
+    use crate::{
+        block::Block,
+        column::{self, ArcColumnWrapper, Simple},
+    };
+
+    fn column(sql_type: SqlType, values: Vec<Value>) -> column::Column<Simple> {
+        let mut data =
+            <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, values.len()).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            for value in values {
+                data.push(value);
+            }
+        }
+        column::new_column("", data)
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let null = || Value::Nullable(Either::Left(SqlType::Nothing.into()));
+        let empty = || Value::Array(SqlType::Nothing.into(), Arc::new(Vec::new()));
+
+        let block = Block::<Simple>::new()
+            .column(
+                "NULL",
+                column(
+                    SqlType::Nullable(SqlType::Nothing.into()),
+                    vec![null(), null()],
+                ),
+            )
+            .column(
+                "[]",
+                column(
+                    SqlType::Array(SqlType::Nothing.into()),
+                    vec![empty(), empty()],
+                ),
+            );
+
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder);
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let block = Block::load(&mut reader, Tz::Zulu).unwrap();
+
+        assert_eq!(
+            block.get_column("NULL").unwrap().sql_type().to_string(),
+            "Nullable(Nothing)"
+        );
+        assert_eq!(block.get::<Option<u32>, _>(0, "NULL").unwrap(), None);
+        assert_eq!(block.get::<Option<String>, _>(1, "NULL").unwrap(), None);
+
+        assert_eq!(
+            block.get_column("[]").unwrap().sql_type().to_string(),
+            "Array(Nothing)"
+        );
+        assert!(block.get::<Vec<u32>, _>(0, "[]").unwrap().is_empty());
+        assert!(block.get::<Vec<String>, _>(1, "[]").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_load_skips_bytes() {
+        let mut encoder = Encoder::new();
+        encoder.write_bytes(b"000");
+        encoder.write(42_u8);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let data = NothingColumnData::load(&mut reader, 3).unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(
+            data.at(1),
+            ValueRef::Nullable(Either::Left(SqlType::Nothing.into()))
+        );
+        assert_eq!(reader.read_scalar::<u8>().unwrap(), 42);
+    }
+}
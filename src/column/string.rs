@@ -32,6 +32,16 @@ impl StringColumnData {
         }
     }
 
+    /// Same as `with_capacity` with the total size of the strings known up front.
+    pub(crate) fn with_byte_capacity(capacity: usize, bytes: usize) -> Self {
+        Self {
+            pool: StringPool::with_byte_capacity(capacity, bytes),
+        }
+    }
+
+    /// The native format sends only the length of each string, not the total, so the
+    /// pool is sized from the number of rows. `with_byte_capacity` applies when the
+    /// total is known, e.g. when blocks are merged.
     pub(crate) fn load<T: ReadEx>(reader: &mut T, size: usize) -> Result<Self> {
         let mut data = Self::with_capacity(size);
 
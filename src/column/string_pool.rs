@@ -15,6 +15,8 @@ pub(crate) struct StringPool {
     pointers: Vec<StringPtr>,
     position: usize,
     capacity: usize,
+    /// Size of the next chunk when the total size of the strings is known.
+    bytes_hint: Option<usize>,
 }
 
 pub(crate) struct StringIter<'a> {
@@ -41,7 +43,8 @@ where
     T: AsRef<[u8]>,
 {
     fn from(source: Vec<T>) -> Self {
-        let mut pool = StringPool::with_capacity(source.len());
+        let bytes = source.iter().map(|s| s.as_ref().len()).sum();
+        let mut pool = StringPool::with_byte_capacity(source.len(), bytes);
         for s in source.iter() {
             let mut b = pool.allocate(s.as_ref().len());
             b.write_all(s.as_ref()).unwrap();
@@ -57,9 +60,24 @@ impl StringPool {
             chunks: Vec::new(),
             position: 0,
             capacity,
+            bytes_hint: None,
         }
     }
 
+    /// Same as `with_capacity`, but the first chunk holds exactly `bytes` bytes
+    /// instead of an estimate from the number of strings.
+    pub(crate) fn with_byte_capacity(capacity: usize, bytes: usize) -> StringPool {
+        let mut pool = StringPool::with_capacity(capacity);
+        pool.reserve_bytes(bytes);
+        pool
+    }
+
+    /// Makes the next chunk hold at least `bytes` bytes, so that many strings of a known
+    /// total size are written without allocating chunk after chunk.
+    pub(crate) fn reserve_bytes(&mut self, bytes: usize) {
+        self.bytes_hint = Some(bytes);
+    }
+
     pub(crate) fn allocate(&mut self, size: usize) -> &mut [u8] {
         if self.free_space() < size || self.chunks.is_empty() {
             self.reserve(size);
@@ -98,9 +116,12 @@ impl StringPool {
 
     fn reserve(&mut self, size: usize) {
         use std::cmp::max;
+        let chunk_size = match self.bytes_hint.take() {
+            Some(bytes) => bytes,
+            None => self.capacity * AVG_STR_SIZE,
+        };
         self.position = 0;
-        self.chunks
-            .push(vec![0_u8; max(self.capacity * AVG_STR_SIZE, size)]);
+        self.chunks.push(vec![0_u8; max(chunk_size, size)]);
     }
 
     #[inline(always)]
@@ -169,4 +190,43 @@ mod test {
             assert_eq!(s, format!("text-{}", i));
         }
     }
+
+    fn fill(pool: &mut StringPool, count: usize, len: usize) {
+        for _ in 0..count {
+            pool.allocate(len).fill(b'x');
+        }
+    }
+
+    #[test]
+    fn test_byte_capacity_allocations() {
+        // 100 strings of 200 bytes do not fit the estimate of 80 bytes per string.
+        let mut pool = StringPool::with_capacity(100);
+        fill(&mut pool, 100, 200);
+        assert_eq!(pool.chunks.len(), 3);
+
+        let mut pool = StringPool::with_byte_capacity(100, 100 * 200);
+        fill(&mut pool, 100, 200);
+        assert_eq!(pool.chunks.len(), 1);
+        assert_eq!(pool.chunks[0].len(), 100 * 200);
+        assert!(pool.strings().all(|s| s.len() == 200));
+
+        // The hint only sizes the next chunk, later ones fall back to the estimate.
+        fill(&mut pool, 1, 10);
+        assert_eq!(pool.chunks.len(), 2);
+        assert_eq!(pool.chunks[1].len(), 100 * AVG_STR_SIZE);
+
+        pool.reserve_bytes(5000);
+        fill(&mut pool, 1, 8000 - 10);
+        fill(&mut pool, 1, 1);
+        assert_eq!(pool.chunks.len(), 3);
+        assert_eq!(pool.chunks[2].len(), 5000);
+    }
+
+    #[test]
+    fn test_from_vec_single_chunk() {
+        let source: Vec<String> = (0..1000).map(|i| "y".repeat(i % 300)).collect();
+        let pool = StringPool::from(source.clone());
+        assert_eq!(pool.chunks.len(), 1);
+        assert!(pool.strings().eq(source.iter().map(String::as_bytes)));
+    }
 }
@@ -0,0 +1,132 @@
+use chrono_tz::Tz;
+use std::sync::Arc;
+
+use crate::column::{
+    column_data::{ArcColumnData, BoxColumnData},
+    low_cardinality::check_not_nested,
+    ArcColumnWrapper, ColumnData,
+};
+
+use crate::{
+    binary::{Encoder, ReadEx},
+    error::Result,
+    types::SqlType,
+    value::{Value, ValueRef},
+};
+
+/// Each element of `Tuple(T1, T2, ...)` is stored as a separate column of the same length.
+pub(crate) struct TupleColumnData {
+    pub(crate) inner: Vec<ArcColumnData>,
+}
+
+impl TupleColumnData {
+    pub(crate) fn load<R: ReadEx>(
+        reader: &mut R,
+        type_names: &[&str],
+        size: usize,
+        tz: Tz,
+    ) -> Result<Self> {
+        for type_name in type_names {
+            check_not_nested(type_name)?;
+        }
+
+        let mut inner = Vec::with_capacity(type_names.len());
+        for type_name in type_names {
+            inner.push(<dyn ColumnData>::load_data::<ArcColumnWrapper, _>(
+                reader, type_name, size, tz,
+            )?);
+        }
+
+        Ok(TupleColumnData { inner })
+    }
+}
+
+impl ColumnData for TupleColumnData {
+    fn sql_type(&self) -> SqlType {
+        SqlType::Tuple(self.inner.iter().map(|column| column.sql_type()).collect())
+    }
+
+    fn save(&self, encoder: &mut Encoder, start: usize, end: usize) {
+        for column in &self.inner {
+            column.save(encoder, start, end);
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self.inner.first() {
+            None => 0,
+            Some(column) => column.len(),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        if let Value::Tuple(vs) = value {
+            assert_eq!(vs.len(), self.inner.len(), "tuple size mismatch");
+
+            for (column, v) in self.inner.iter_mut().zip(vs.iter()) {
+                let inner_column = Arc::get_mut(column).unwrap();
+                inner_column.push(v.clone());
+            }
+        } else {
+            panic!("value should be a tuple")
+        }
+    }
+
+    fn at(&self, index: usize) -> ValueRef<'_> {
+        let vs = self.inner.iter().map(|column| column.at(index)).collect();
+        ValueRef::Tuple(Arc::new(vs))
+    }
+
+    fn clone_instance(&self) -> BoxColumnData {
+        Box::new(Self {
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        block::Block,
+        column::{self, Simple},
+    };
+
+    #[test]
+    fn test_write_and_read() {
+        let sql_type = SqlType::Tuple(vec![
+            SqlType::UInt64,
+            SqlType::String,
+            SqlType::Array(SqlType::UInt8.into()),
+        ]);
+        let mut data = <dyn ColumnData>::from_type::<ArcColumnWrapper>(sql_type, 2).unwrap();
+        {
+            let data = Arc::get_mut(&mut data).unwrap();
+            data.push(Value::Tuple(Arc::new(vec![
+                1_u64.into(),
+                "foo".into(),
+                Value::Array(SqlType::UInt8.into(), Arc::new(vec![1_u8.into()])),
+            ])));
+            data.push(Value::Tuple(Arc::new(vec![
+                2_u64.into(),
+                "bar".into(),
+                Value::Array(SqlType::UInt8.into(), Arc::new(vec![])),
+            ])));
+        }
+        let block = Block::<Simple>::new().column("t", column::new_column::<Simple>("t", data));
+
+        let mut encoder = Encoder::new();
+        block.write(&mut encoder);
+
+        let mut reader = Cursor::new(encoder.get_buffer_ref());
+        let rblock = Block::load(&mut reader, Tz::Zulu).unwrap();
+
+        assert_eq!(block, rblock);
+
+        let (id, name, bytes): (u64, String, Vec<u8>) = rblock.get(1, "t").unwrap();
+        assert_eq!((id, name.as_str(), bytes.len()), (2, "bar", 0));
+        assert!(rblock.get::<(u64, String), _>(0, "t").is_err());
+    }
+}
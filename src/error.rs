@@ -3,10 +3,11 @@ use std::io;
 use std::result;
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
+use std::sync::Arc;
 
 use thiserror::Error;
 use tokio::time::error::Elapsed;
-use url::ParseError;
+use url::{ParseError, Url};
 
 /// Result type alias for this library for ConnectionError.
 pub type ConnectionResult<T> = result::Result<T, ConnectionError>;
@@ -42,10 +43,55 @@ pub enum Error {
 #[derive(Debug, Error, Clone)]
 #[error("ERROR {} ({:?}): {}", name, code, message)]
 pub struct ServerError {
-    pub code: u32,
+    pub code: i32,
     pub name: String,
     pub message: String,
     pub stack_trace: String,
+    /// The exception which caused this one on the server.
+    #[source]
+    pub nested: Option<Box<ServerError>>,
+}
+
+/// Exception codes of failures which may pass when the query is sent again.
+const RETRIABLE_CODES: &[i32] = &[
+    159, // TIMEOUT_EXCEEDED
+    160, // TOO_SLOW
+    202, // TOO_MANY_SIMULTANEOUS_QUERIES
+    203, // NO_FREE_CONNECTION
+    209, // SOCKET_TIMEOUT
+    210, // NETWORK_ERROR
+    279, // ALL_CONNECTION_TRIES_FAILED
+    999, // KEEPER_EXCEPTION
+];
+
+impl ServerError {
+    /// Exception code, e.g. 241 for `MEMORY_LIMIT_EXCEEDED`.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    /// Exception class name, e.g. `DB::Exception`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn stack_trace(&self) -> &str {
+        &self.stack_trace
+    }
+
+    pub fn nested(&self) -> Option<&ServerError> {
+        self.nested.as_deref()
+    }
+
+    /// Returns `true` for transient failures, like timeouts or too many queries,
+    /// which may pass when the query is retried.
+    pub fn is_retriable(&self) -> bool {
+        RETRIABLE_CODES.contains(&self.code)
+    }
 }
 
 /// This type enumerates connection errors.
@@ -59,6 +105,17 @@ pub enum ConnectionError {
 
     #[error("TLS connection error: `{}`", _0)]
     TlsError(#[source] tokio_native_tls::native_tls::Error),
+
+    #[error("Unable to connect to any host: {}", hosts_errors(_0))]
+    AllHostsFailed(Vec<(Url, Error)>),
+
+    /// The port of the HTTP interface was given, the native protocol is served on
+    /// another one.
+    #[error(
+        "Port {} is the HTTP interface, the native protocol uses port 9000, or 9440 with TLS",
+        _0
+    )]
+    WrongPort(u16),
 }
 
 /// This type enumerates connection URL errors.
@@ -86,20 +143,57 @@ pub enum DriverError {
     #[error("Varint overflows a 64-bit integer.")]
     Overflow,
 
-    #[error("Unknown packet 0x{:x}.", packet)]
-    UnknownPacket { packet: u64 },
+    #[error("Unknown packet 0x{:x} at byte {} of the stream.", packet, offset)]
+    UnknownPacket { packet: u64, offset: u64 },
 
     #[error("Unexpected packet.")]
     UnexpectedPacket,
 
+    #[error("Unexpected packet 0x{:x} at byte {} of the stream.", packet, offset)]
+    UnexpectedPacketAt { packet: u64, offset: u64 },
+
     #[error("Timeout error.")]
     Timeout,
 
+    #[error(
+        "Block of about {} bytes exceeds the limit of {} bytes.",
+        estimate,
+        limit
+    )]
+    BlockTooLarge { estimate: usize, limit: usize },
+
+    #[error("The server closed the connection.")]
+    ConnectionClosed,
+
+    /// The connection failed after the query was sent. The query may have run,
+    /// so it is not retried. The cause is an `Error::IO` or `ConnectionClosed`.
+    #[error("The connection was lost during the query: {}", _0)]
+    QueryInterrupted(#[source] Arc<Error>),
+
+    /// The server sent data which can not be decoded, e.g. decreasing array offsets.
+    #[error("Malformed data: {}", _0)]
+    MalformedData(String),
+
+    #[error("No connection became available in the pool before the acquire timeout.")]
+    PoolExhausted,
+
     #[error("Invalid utf-8 sequence.")]
     Utf8Error(Utf8Error),
 
     #[error("UnknownSetting name {}", name)]
     UnknownSetting { name: String },
+
+    #[error("Bad compressed packet header.")]
+    BadCompressedPacketHeader,
+
+    #[error("Checksum mismatch of compressed block.")]
+    BadHash,
+
+    #[error("Unknown compression method 0x{:x}.", method)]
+    UnknownCompressionMethod { method: u8 },
+
+    #[error("Decompression error: {}", _0)]
+    Decompression(String),
 }
 
 /// This type enumerates cast from sql type errors.
@@ -119,6 +213,17 @@ pub enum FromSqlError {
 
     #[error("Unsupported column type `{0}`.")]
     UnsupportedColumnType(Cow<'static, str>),
+
+    #[error("Unexpected NULL value.")]
+    UnexpectedNull,
+}
+
+fn hosts_errors(errors: &[(Url, Error)]) -> String {
+    errors
+        .iter()
+        .map(|(host, err)| format!("`{}`: {}", host, err))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl Error {
@@ -152,6 +257,13 @@ impl From<DriverError> for Error {
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
+        // Unwrap errors which were wrapped into `io::Error` by `From<Error>`.
+        if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            if let Ok(inner) = err.into_inner().unwrap().downcast::<Error>() {
+                return *inner;
+            }
+            unreachable!()
+        }
         Error::IO(err)
     }
 }
@@ -160,7 +272,7 @@ impl From<Error> for io::Error {
     fn from(err: Error) -> Self {
         match err {
             Error::IO(error) => error,
-            e => io::Error::new(io::ErrorKind::Other, e.to_string()),
+            e => io::Error::new(io::ErrorKind::Other, e),
         }
     }
 }
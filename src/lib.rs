@@ -1,6 +1,7 @@
 mod client;
 mod inner_stream;
 pub mod pool;
+mod socks;
 mod stream;
 mod transport;
 
@@ -13,12 +14,23 @@ mod value;
 mod binary;
 mod protocol;
 
+#[cfg(feature = "arrow")]
+mod arrow;
+
+#[cfg(test)]
+mod mock_server;
+
 pub mod error;
 
 pub use crate::{
-    block::{Block, Row},
-    client::ClientHandle,
-    column::Complex,
+    block::{Block, FromRow, Row, Sliceable},
+    client::{ClientHandle, ColumnMeta, DescribedType},
+    column::{ColumnView, Complex, Simple, StringsView},
     error::{Error as ClickhouseError, Result as ClickhouseResult},
-    pool::{Pool, PoolConfigBuilder},
+    pool::{Pool, PoolConfigBuilder, PoolMetrics, ProxyConfig},
+    query::{CancelToken, QueryProfileHandle, TotalsHandle},
+    types::{Compression, Lossy, QueryProfile, Trimmed},
 };
+
+#[cfg(feature = "derive")]
+pub use clickhouse_readonly_derive::FromRow;
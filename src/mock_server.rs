@@ -0,0 +1,186 @@
+//! A fake server for tests which need a connection.
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use url::Url;
+
+use crate::{
+    binary::Encoder,
+    block::Block,
+    pool::{PoolConfig, PoolConfigBuilder},
+    protocol,
+    types::ProfileInfo,
+};
+
+pub(crate) const MAJOR_VERSION: u64 = 23;
+pub(crate) const MINOR_VERSION: u64 = 8;
+pub(crate) const PATCH_VERSION: u64 = 3;
+pub(crate) const REVISION: u64 = 54465;
+pub(crate) const TIMEZONE: &str = "Europe/Berlin";
+
+/// Configuration of a test pool connecting to `url` as the default user.
+pub(crate) fn builder(url: Url) -> PoolConfigBuilder {
+    PoolConfigBuilder::new(url, "default".into(), "".into(), "".into(), false)
+}
+
+/// Same as `builder(url).build()`.
+pub(crate) fn config(url: Url) -> PoolConfig {
+    builder(url).build().unwrap()
+}
+
+/// Starts a server which answers the handshake and pings, but never answers a query.
+pub(crate) async fn stalled_server() -> Url {
+    serve(None, false, usize::MAX).await
+}
+
+/// Same as `stalled_server`, but only accepts one connection: later ones are refused.
+pub(crate) async fn single_connection_server() -> Url {
+    serve(None, false, 1).await
+}
+
+/// Starts a server which answers every query with `blocks` followed by the end of stream.
+pub(crate) async fn query_server(blocks: Vec<Block>) -> Url {
+    serve(Some(query_response(&blocks)), false, usize::MAX).await
+}
+
+/// Starts a server which answers every query with `blocks`, then `profile` and the
+/// end of stream.
+pub(crate) async fn profile_server(blocks: Vec<Block>, profile: ProfileInfo) -> Url {
+    let mut response = query_response(&blocks);
+    response.pop(); // the end of stream goes after the profile
+
+    let mut encoder = Encoder::from(response);
+    encoder.uvarint(protocol::SERVER_PROFILE_INFO);
+    encoder.uvarint(profile.rows);
+    encoder.uvarint(profile.blocks);
+    encoder.uvarint(profile.bytes);
+    encoder.write(profile.applied_limit as u8);
+    encoder.uvarint(profile.rows_before_limit);
+    encoder.write(profile.calculated_rows_before_limit as u8);
+    encoder.uvarint(protocol::SERVER_END_OF_STREAM);
+    serve(Some(encoder.get_buffer()), false, usize::MAX).await
+}
+
+/// Starts a server which answers a query with `block` and closes the connection
+/// `cut` bytes before the end of the block.
+pub(crate) async fn closing_server(block: Block, cut: usize) -> Url {
+    let mut response = query_response(&[block]);
+    response.truncate(response.len() - 1 - cut); // without the end of stream
+    serve(Some(response), true, usize::MAX).await
+}
+
+/// Starts a server which answers every query with an exception.
+pub(crate) async fn exception_server(code: i32, message: &str) -> Url {
+    let mut encoder = Encoder::new();
+    encoder.uvarint(protocol::SERVER_EXCEPTION);
+    encoder.write(code);
+    encoder.string("DB::Exception");
+    encoder.string(message);
+    encoder.string(""); // stack trace
+    encoder.write(0_u8); // no nested exception
+    serve(Some(encoder.get_buffer()), false, usize::MAX).await
+}
+
+fn query_response(blocks: &[Block]) -> Vec<u8> {
+    let mut encoder = Encoder::new();
+    for block in blocks {
+        encoder.uvarint(protocol::SERVER_DATA);
+        encoder.string(""); // temporary table
+        encoder.write_bytes(&block.to_bytes());
+    }
+    encoder.uvarint(protocol::SERVER_END_OF_STREAM);
+    encoder.get_buffer()
+}
+
+/// Serves up to `connections` connections, then closes the listener.
+async fn serve(
+    query_response: Option<Vec<u8>>,
+    close_after_query: bool,
+    connections: usize,
+) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+
+    tokio::spawn(async move {
+        for _ in 0..connections {
+            let socket = match listener.accept().await {
+                Ok((socket, _)) => socket,
+                Err(_) => return,
+            };
+            tokio::spawn(serve_connection(
+                socket,
+                query_response.clone(),
+                close_after_query,
+            ));
+        }
+    });
+
+    url
+}
+
+async fn serve_connection(
+    mut socket: TcpStream,
+    query_response: Option<Vec<u8>>,
+    close_after_query: bool,
+) {
+    let mut buf = vec![0_u8; 4096];
+    loop {
+        let n = socket.read(&mut buf).await.unwrap_or(0);
+        if n == 0 {
+            return;
+        }
+
+        // `Cancel` and `Ping` are a single byte, so they may come right before the next packet.
+        let mut encoder = Encoder::new();
+        let mut close = false;
+        for &code in &buf[..n] {
+            match (code as u64, &query_response) {
+                (protocol::CLIENT_HELLO, _) => {
+                    encoder.uvarint(protocol::SERVER_HELLO);
+                    encoder.string("ClickHouse");
+                    encoder.uvarint(MAJOR_VERSION);
+                    encoder.uvarint(MINOR_VERSION);
+                    encoder.uvarint(REVISION);
+                    encoder.string(TIMEZONE);
+                    encoder.string("mock"); // display name
+                    encoder.uvarint(PATCH_VERSION);
+                }
+                (protocol::CLIENT_PING, _) => {
+                    encoder.uvarint(protocol::SERVER_PONG);
+                    continue;
+                }
+                (protocol::CLIENT_QUERY, Some(response)) => {
+                    encoder.write_bytes(response);
+                    close = close_after_query;
+                }
+                (protocol::CLIENT_CANCEL, _) => continue,
+                _ => {}
+            }
+            break;
+        }
+        if encoder.get_buffer_ref().is_empty() {
+            continue;
+        }
+        socket.write_all(&encoder.get_buffer()).await.unwrap();
+        if close {
+            return;
+        }
+    }
+}
+
+/// Starts a server which accepts connections but never answers, not even the handshake.
+pub(crate) async fn silent_server() -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            sockets.push(socket);
+        }
+    });
+
+    url
+}
@@ -2,18 +2,29 @@ use pin_project::pin_project;
 use std::task::{Context, Poll};
 use std::{future::Future, pin::Pin};
 
-use crate::{client::ClientHandle, error::Result, pool::Pool};
+use tokio::time::{self, Sleep};
+
+use crate::{
+    client::ClientHandle,
+    error::{DriverError, Error, Result},
+    pool::Pool,
+};
 
 /// Future that resolves to a `ClientHandle`.
 #[pin_project]
 pub struct GetHandle {
     #[pin]
     pool: Pool,
+    /// Started on the first poll when `acquire_timeout` is set.
+    deadline: Option<Pin<Box<Sleep>>>,
 }
 
 impl GetHandle {
     pub(crate) fn new(pool: &Pool) -> Self {
-        Self { pool: pool.clone() }
+        Self {
+            pool: pool.clone(),
+            deadline: None,
+        }
     }
 }
 
@@ -21,6 +32,21 @@ impl Future for GetHandle {
     type Output = Result<ClientHandle>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.project().pool.poll(cx)
+        let this = self.project();
+
+        if let Some(timeout) = this.pool.config.acquire_timeout {
+            this.deadline
+                .get_or_insert_with(|| Box::pin(time::sleep(timeout)));
+        }
+
+        let result = this.pool.poll(cx);
+        if result.is_pending() {
+            if let Some(deadline) = this.deadline {
+                if deadline.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready(Err(Error::Driver(DriverError::PoolExhausted)));
+                }
+            }
+        }
+        result
     }
 }
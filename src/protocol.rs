@@ -1,11 +1,26 @@
 //! Clickhouse protocol keys receiver and parsed at packets.
 
 pub const DBMS_MIN_REVISION_WITH_QUOTA_KEY_IN_CLIENT_INFO: u64 = 54060;
-//pub const DBMS_MIN_REVISION_WITH_SETTINGS_SERIALIZED_AS_STRINGS: u64 = 54429;
+pub const DBMS_MIN_REVISION_WITH_SERVER_DISPLAY_NAME: u64 = 54372;
+pub const DBMS_MIN_REVISION_WITH_VERSION_PATCH: u64 = 54401;
+pub const DBMS_MIN_REVISION_WITH_CLIENT_WRITE_INFO: u64 = 54420;
+pub const DBMS_MIN_REVISION_WITH_SETTINGS_SERIALIZED_AS_STRINGS: u64 = 54429;
+pub const DBMS_MIN_REVISION_WITH_INTERSERVER_SECRET: u64 = 54441;
+pub const DBMS_MIN_REVISION_WITH_OPENTELEMETRY: u64 = 54442;
+pub const DBMS_MIN_PROTOCOL_VERSION_WITH_DISTRIBUTED_DEPTH: u64 = 54448;
+pub const DBMS_MIN_PROTOCOL_VERSION_WITH_INITIAL_QUERY_START_TIME: u64 = 54449;
+
+/// Flags of a setting serialized as a string.
+pub const SETTING_FLAG_IMPORTANT: u64 = 0x01;
+pub const SETTING_FLAG_CUSTOM: u64 = 0x02;
 
 /// Allows only read from DataBase
 pub const READONLY_LEVEL: u64 = 1;
 pub const READONLY_FLAG: &str = "readonly";
+/// Setting which stops the query on the server, derived from the query timeout.
+pub const MAX_EXECUTION_TIME: &str = "max_execution_time";
+/// Setting which picks the compression of the blocks the server sends, LZ4 by default.
+pub const NETWORK_COMPRESSION_METHOD: &str = "network_compression_method";
 
 pub const CLIENT_HELLO: u64 = 0;
 pub const CLIENT_QUERY: u64 = 1;
@@ -13,7 +28,7 @@ pub const CLIENT_DATA: u64 = 2;
 pub const CLIENT_CANCEL: u64 = 3;
 pub const CLIENT_PING: u64 = 4;
 
-// pub const COMPRESS_ENABLE: u64 = 1;
+pub const COMPRESS_ENABLE: u64 = 1;
 pub const COMPRESS_DISABLE: u64 = 0;
 
 pub const STATE_COMPLETE: u64 = 2;
@@ -31,14 +46,18 @@ pub const SERVER_EXTREMES: u64 = 8;
 pub mod client_info {
     use crate::binary::Encoder;
 
+    /// Client name used when `PoolConfig` does not set one.
     pub static CLIENT_NAME: &str = "RustCHDriver";
 
-    pub const CLICK_HOUSE_REVISION: u64 = 54213;
+    /// The highest protocol revision the client speaks, a connection uses
+    /// the lower of it and the server revision.
+    pub const CLICK_HOUSE_REVISION: u64 = 54449;
     pub const CLICK_HOUSE_DBMSVERSION_MAJOR: u64 = 1;
     pub const CLICK_HOUSE_DBMSVERSION_MINOR: u64 = 1;
+    pub const CLICK_HOUSE_DBMSVERSION_PATCH: u64 = 0;
 
-    pub fn write(encoder: &mut Encoder) {
-        encoder.string(CLIENT_NAME);
+    pub fn write(encoder: &mut Encoder, client_name: &str) {
+        encoder.string(client_name);
         encoder.uvarint(CLICK_HOUSE_DBMSVERSION_MAJOR);
         encoder.uvarint(CLICK_HOUSE_DBMSVERSION_MINOR);
         encoder.uvarint(CLICK_HOUSE_REVISION);
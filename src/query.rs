@@ -1,15 +1,30 @@
-use std::marker::PhantomData;
+use std::{
+    cmp, io,
+    marker::PhantomData,
+    mem,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{self, Poll, Waker},
+    time::Duration,
+};
 
-use futures_core::stream::BoxStream;
-use futures_util::{StreamExt, TryStreamExt};
+use either::Either;
+use futures_core::{ready, stream::BoxStream, Stream};
+use futures_util::{task::AtomicWaker, FutureExt, StreamExt, TryStreamExt};
+use tokio::{sync::mpsc, task::JoinHandle};
 
 use crate::{
-    block::{Block, BlockRef, Row, Rows},
+    block::{Block, BlockRef, ColumnIdx, Row, Rows},
     client::{with_timeout, ClientHandle},
-    column::Simple,
+    column::{Column, ColumnView, Simple},
     error::{Error, Result},
+    protocol,
     query::block_stream::BlockStream,
-    types::Cmd,
+    types::{Cmd, FromSql, QueryProfile, SqlType},
+    value::{Value, ValueRef},
     Complex,
 };
 
@@ -17,6 +32,18 @@ use crate::{
 pub struct Query {
     sql: String,
     id: String,
+    settings: Vec<(String, String)>,
+    params: Vec<(String, Vec<u8>)>,
+    readonly_level: u8,
+    trace_context: Option<TraceContext>,
+}
+
+/// OpenTelemetry context of the span the query belongs to.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TraceContext {
+    pub(crate) trace_id: u128,
+    pub(crate) span_id: u64,
+    pub(crate) flags: u8,
 }
 
 impl Query {
@@ -24,9 +51,14 @@ impl Query {
         Self {
             sql: sql.as_ref().to_string(),
             id: "".to_string(),
+            settings: Vec::new(),
+            params: Vec::new(),
+            readonly_level: protocol::READONLY_LEVEL as u8,
+            trace_context: None,
         }
     }
 
+    /// Sets the `query_id` shown in `system.query_log`. By default the server generates one.
     pub fn id(self, id: impl AsRef<str>) -> Self {
         Self {
             id: id.as_ref().to_string(),
@@ -34,6 +66,71 @@ impl Query {
         }
     }
 
+    /// Adds a setting sent along with the query, e.g. `max_execution_time`.
+    /// Setting the same key again replaces the value.
+    ///
+    /// The `readonly` setting is ignored here, see `readonly_level`.
+    pub fn with_setting(mut self, key: &str, value: impl ToString) -> Self {
+        if key == protocol::READONLY_FLAG {
+            return self;
+        }
+
+        let value = value.to_string();
+        match self.settings.iter_mut().find(|(k, _)| k == key) {
+            Some(setting) => setting.1 = value,
+            None => self.settings.push((key.to_string(), value)),
+        }
+        self
+    }
+
+    /// Sets the value of a `{name:Type}` parameter of the query, e.g.
+    /// `Query::new("SELECT {id:UInt64}").param("id", 42_u64)`.
+    /// Setting the same name again replaces the value.
+    ///
+    /// Values are sent separately from the SQL as `param_<name>` settings,
+    /// so they can't change the query. Strings are sent as they are, even if
+    /// they are not valid UTF-8.
+    pub fn param(mut self, name: &str, value: impl Into<Value>) -> Self {
+        let mut bytes = Vec::new();
+        format_param(&ValueRef::from(&value.into()), false, &mut bytes);
+        match self.params.iter_mut().find(|(k, _)| k == name) {
+            Some(param) => param.1 = bytes,
+            None => self.params.push((name.to_string(), bytes)),
+        }
+        self
+    }
+
+    /// Sets the `readonly` setting of the query, 1 by default.
+    /// Level 2 also allows changing settings, level 0 lifts the restrictions.
+    pub fn readonly_level(self, level: u8) -> Result<Self> {
+        if level > 2 {
+            return Err(Error::Other(
+                format!("Invalid readonly level {}, expected 0, 1 or 2.", level).into(),
+            ));
+        }
+        Ok(Self {
+            readonly_level: level,
+            ..self
+        })
+    }
+
+    /// Sends an OpenTelemetry trace context with the query, so the spans of the server
+    /// become children of `span_id` in the trace `trace_id`. `flags` are the W3C trace
+    /// flags, 1 for a sampled trace.
+    ///
+    /// Servers older than the OpenTelemetry support silently ignore it. A zero
+    /// `trace_id` sends no context.
+    pub fn with_trace_context(self, trace_id: u128, span_id: u64, flags: u8) -> Self {
+        Self {
+            trace_context: Some(TraceContext {
+                trace_id,
+                span_id,
+                flags,
+            }),
+            ..self
+        }
+    }
+
     pub(crate) fn get_sql(&self) -> &str {
         &self.sql
     }
@@ -41,6 +138,86 @@ impl Query {
     pub(crate) fn get_id(&self) -> &str {
         &self.id
     }
+
+    pub(crate) fn get_settings(&self) -> &[(String, String)] {
+        &self.settings
+    }
+
+    pub(crate) fn get_readonly_level(&self) -> u8 {
+        self.readonly_level
+    }
+
+    pub(crate) fn get_params(&self) -> &[(String, Vec<u8>)] {
+        &self.params
+    }
+
+    pub(crate) fn get_trace_context(&self) -> Option<TraceContext> {
+        self.trace_context
+    }
+}
+
+/// Formats a parameter the way the server parses it: the value itself in the escaped
+/// format, values inside of arrays, tuples and maps as quoted literals.
+///
+/// This is also how the server prints values in the `TabSeparated` format. Strings are
+/// written as raw bytes, so binary values survive.
+fn format_param(value: &ValueRef, nested: bool, out: &mut Vec<u8>) {
+    match value {
+        ValueRef::String(s) => {
+            if nested {
+                out.push(b'\'');
+            }
+            for &byte in *s {
+                match byte {
+                    b'\\' => out.extend_from_slice(b"\\\\"),
+                    b'\t' => out.extend_from_slice(b"\\t"),
+                    b'\n' => out.extend_from_slice(b"\\n"),
+                    b'\r' => out.extend_from_slice(b"\\r"),
+                    b'\0' => out.extend_from_slice(b"\\0"),
+                    b'\'' if nested => out.extend_from_slice(b"\\'"),
+                    byte => out.push(byte),
+                }
+            }
+            if nested {
+                out.push(b'\'');
+            }
+        }
+        ValueRef::Nullable(Either::Left(_)) if nested => out.extend_from_slice(b"NULL"),
+        ValueRef::Nullable(Either::Left(_)) => out.extend_from_slice(b"\\N"),
+        ValueRef::Nullable(Either::Right(v)) => format_param(v, nested, out),
+        ValueRef::Array(_, vs) => {
+            out.push(b'[');
+            format_params(vs, out);
+            out.push(b']');
+        }
+        ValueRef::Tuple(vs) => {
+            out.push(b'(');
+            format_params(vs, out);
+            out.push(b')');
+        }
+        ValueRef::Map(_, _, entries) => {
+            out.push(b'{');
+            for (i, (k, v)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                format_param(k, true, out);
+                out.push(b':');
+                format_param(v, true, out);
+            }
+            out.push(b'}');
+        }
+        value => out.extend_from_slice(value.to_string().as_bytes()),
+    }
+}
+
+fn format_params(values: &[ValueRef], out: &mut Vec<u8>) {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        format_param(value, true, out);
+    }
 }
 
 impl<T> From<T> for Query
@@ -52,6 +229,88 @@ where
     }
 }
 
+/// Handle which cancels a running query.
+///
+/// After `cancel()` the stream stops yielding blocks and drains the remaining
+/// packets, so it should be polled until it returns `None` to get the
+/// connection back in a clean state.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl CancelToken {
+    /// Asks the server to stop executing the query.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Release);
+        self.inner.waker.wake();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn register(&self, waker: &Waker) {
+        self.inner.waker.register(waker);
+    }
+}
+
+/// Handle to the profile info which the server sends at the end of a query.
+#[derive(Clone, Default)]
+pub struct QueryProfileHandle {
+    inner: Arc<Mutex<Option<QueryProfile>>>,
+}
+
+impl QueryProfileHandle {
+    /// Returns the profile info, or `None` if it was not received yet.
+    pub fn get(&self) -> Option<QueryProfile> {
+        *self.inner.lock().unwrap()
+    }
+
+    pub(crate) fn set(&self, profile: QueryProfile) {
+        *self.inner.lock().unwrap() = Some(profile);
+    }
+}
+
+/// Handle to the `WITH TOTALS` and `extremes` blocks which the server sends
+/// after the data.
+#[derive(Clone, Default)]
+pub struct TotalsHandle {
+    inner: Arc<Mutex<TotalsState>>,
+}
+
+#[derive(Default)]
+struct TotalsState {
+    totals: Option<Block>,
+    extremes: Option<Block>,
+}
+
+impl TotalsHandle {
+    /// Returns the totals block, or `None` if it was not received (yet).
+    pub fn totals(&self) -> Option<Block> {
+        self.inner.lock().unwrap().totals.clone()
+    }
+
+    /// Returns the extremes block, or `None` if it was not received (yet).
+    pub fn extremes(&self) -> Option<Block> {
+        self.inner.lock().unwrap().extremes.clone()
+    }
+
+    pub(crate) fn set_totals(&self, block: Block) {
+        self.inner.lock().unwrap().totals = Some(block);
+    }
+
+    pub(crate) fn set_extremes(&self, block: Block) {
+        self.inner.lock().unwrap().extremes = Some(block);
+    }
+}
+
 /// Result of a query or statement execution.
 pub struct QueryResult<'a> {
     pub(crate) client: &'a mut ClientHandle,
@@ -60,27 +319,12 @@ pub struct QueryResult<'a> {
 
 impl<'a> QueryResult<'a> {
     /// Fetch data from table. It returns a block that contains all rows.
-    pub async fn fetch_all(self) -> Result<Block<Complex>> {
-        let timeout = match self.client.context.config.query_timeout {
-            Some(timeout) => timeout,
-            None => {
-                return Err(Error::Other(
-                    "Query timeout was not set on `PoolConfig`".into(),
-                ))
-            }
-        };
+    pub async fn fetch_block(self) -> Result<Block<Complex>> {
+        let timeout = self.query_timeout()?;
 
         with_timeout(
             async {
-                let blocks = self
-                    ._stream_blocks(false)
-                    .try_fold(Vec::new(), |mut blocks, block| {
-                        if !block.is_empty() {
-                            blocks.push(block);
-                        }
-                        futures_util::future::ready(Ok(blocks))
-                    })
-                    .await?;
+                let blocks = self.collect_blocks().await?;
                 Ok(Block::concat(blocks.as_slice()))
             },
             timeout,
@@ -88,11 +332,277 @@ impl<'a> QueryResult<'a> {
         .await
     }
 
+    /// Fetch all rows of the result. Every row keeps its block alive.
+    pub async fn fetch_all(self) -> Result<Vec<Row<'static, Simple>>> {
+        let timeout = self.query_timeout()?;
+
+        with_timeout(
+            async {
+                let blocks = self.collect_blocks().await?;
+
+                let row_count = blocks.iter().map(|block| block.row_count()).sum();
+                let mut rows = Vec::with_capacity(row_count);
+                for block in blocks {
+                    rows.extend(Rows {
+                        row: 0,
+                        block_ref: BlockRef::Owned(Arc::new(block)),
+                        kind: PhantomData,
+                    });
+                }
+                Ok(rows)
+            },
+            timeout,
+        )
+        .await
+    }
+
+    /// Fetch the whole result as one column per field. The data of all blocks is copied
+    /// into owned, compact columns, so every column supports `iter::<T>()`.
+    ///
+    /// An empty result yields columns of the right types with no rows.
+    pub async fn fetch_columns(self) -> Result<Vec<Column<Simple>>> {
+        let timeout = self.query_timeout()?;
+
+        with_timeout(
+            async {
+                let handles = StreamHandles::default();
+                let header = handles.header.clone();
+                let blocks = self.collect_blocks_with(handles).await?;
+
+                let block = if blocks.is_empty() {
+                    header.lock().unwrap().take().unwrap_or_default()
+                } else {
+                    Block::merge(&blocks)?
+                };
+                Ok(block.columns().to_vec())
+            },
+            timeout,
+        )
+        .await
+    }
+
+    /// Fetch the whole result as Arrow record batches, one per data block.
+    ///
+    /// See `Block::to_record_batch` for the type mapping.
+    #[cfg(feature = "arrow")]
+    pub async fn fetch_arrow(self) -> Result<Vec<arrow_array::RecordBatch>> {
+        let timeout = self.query_timeout()?;
+
+        with_timeout(
+            async {
+                let blocks = self.collect_blocks().await?;
+                blocks.iter().map(Block::to_record_batch).collect()
+            },
+            timeout,
+        )
+        .await
+    }
+
+    async fn collect_blocks(self) -> Result<Vec<Block>> {
+        self.collect_blocks_with(StreamHandles::default()).await
+    }
+
+    async fn collect_blocks_with(self, handles: StreamHandles) -> Result<Vec<Block>> {
+        self._stream_blocks(false, handles)
+            .try_fold(Vec::new(), |mut blocks, block| {
+                if !block.is_empty() {
+                    blocks.push(block);
+                }
+                futures_util::future::ready(Ok(blocks))
+            })
+            .await
+    }
+
+    /// Fetch the first row of the result. The rest of the result is read and dropped,
+    /// so the connection can be reused. Returns `None` if there are no rows.
+    pub async fn fetch_one(self) -> Result<Option<Row<'static, Simple>>> {
+        let timeout = self.query_timeout()?;
+
+        with_timeout(
+            async {
+                let mut stream = self._stream_blocks(false, StreamHandles::default());
+
+                let mut first_block = None;
+                while let Some(block) = stream.next().await {
+                    let block = block?;
+                    if first_block.is_none() && block.row_count() > 0 {
+                        first_block = Some(block);
+                    }
+                }
+
+                match first_block {
+                    None => Ok(None),
+                    Some(block) => {
+                        let mut rows = Rows {
+                            row: 0,
+                            block_ref: BlockRef::Owned(Arc::new(block)),
+                            kind: PhantomData,
+                        };
+                        match rows.next() {
+                            Some(row) => Ok(Some(row)),
+                            None => Err(Error::Other("Unable to read the first row".into())),
+                        }
+                    }
+                }
+            },
+            timeout,
+        )
+        .await
+    }
+
+    /// Fetch the only cell of a result like `SELECT count() FROM t`. The rest of the
+    /// result is read and dropped, like in `fetch_one`.
+    ///
+    /// Fails if there are no rows or the result has more than one column.
+    pub async fn fetch_scalar<T>(self) -> Result<T>
+    where
+        T: for<'b> FromSql<'b>,
+    {
+        let row = match self.fetch_one().await? {
+            None => return Err(Error::Other("The result has no rows.".into())),
+            Some(row) => row,
+        };
+        if row.len() != 1 {
+            return Err(Error::Other(
+                format!("Expected one column, the result has {}.", row.len()).into(),
+            ));
+        }
+        T::from_sql(row.values().swap_remove(0))
+    }
+
     pub fn stream_blocks(self) -> BoxStream<'a, Result<Block>> {
-        self._stream_blocks(true)
+        self._stream_blocks(true, StreamHandles::default())
+    }
+
+    /// Same as `stream_blocks`, but starts with the header block the server sends first.
+    ///
+    /// The header has the columns of the result and no rows, so the schema is known
+    /// even when the result is empty.
+    pub fn stream_blocks_with_header(self) -> BoxStream<'a, Result<Block>> {
+        self._stream_blocks(false, StreamHandles::default())
+    }
+
+    /// Returns the names and types of the result columns, read from the header block.
+    ///
+    /// The rows are not read: the rest of the result is drained before the next query
+    /// on the connection. A statement without a result yields no columns.
+    pub async fn describe(self) -> Result<Vec<(String, SqlType)>> {
+        let timeout = self.query_timeout()?;
+
+        with_timeout(
+            async {
+                let mut stream = self.stream_blocks_with_header();
+                let header = match stream.next().await {
+                    Some(block) => block?,
+                    None => return Ok(Vec::new()),
+                };
+
+                Ok(header
+                    .columns()
+                    .iter()
+                    .map(|column| (column.name().to_string(), column.sql_type()))
+                    .collect())
+            },
+            timeout,
+        )
+        .await
+    }
+
+    /// Writes the result to `out` in the `TabSeparated` format and returns the number
+    /// of rows. Blocks are written as they arrive, so the result is not buffered.
+    ///
+    /// Cells are escaped the way the server prints them: tabs, line breaks, zero bytes
+    /// and backslashes in strings are escaped, NULL is `\N` and arrays look like
+    /// `['a','b']`. Strings are written as they are, even if they are not valid UTF-8.
+    ///
+    /// Like streams, the export is only limited by `query_timeout` between packets,
+    /// so it may run as long as the server keeps sending data.
+    pub async fn write_tsv<W: io::Write>(self, mut out: W) -> Result<u64> {
+        let mut stream = self.stream_blocks();
+        let mut rows = 0_u64;
+        let mut line = Vec::new();
+        while let Some(block) = stream.next().await {
+            let block = block?;
+            for row in 0..block.row_count() {
+                line.clear();
+                for (i, column) in block.columns().iter().enumerate() {
+                    if i > 0 {
+                        line.push(b'\t');
+                    }
+                    format_param(&column.at(row), false, &mut line);
+                }
+                line.push(b'\n');
+                out.write_all(&line)?;
+            }
+            rows += block.row_count() as u64;
+        }
+        out.flush()?;
+        Ok(rows)
     }
 
-    fn _stream_blocks(self, skip_first_block: bool) -> BoxStream<'a, Result<Block>> {
+    /// Same as `stream_blocks`, but also returns a token to cancel the query.
+    pub fn stream_blocks_cancellable(self) -> (BoxStream<'a, Result<Block>>, CancelToken) {
+        let handles = StreamHandles::default();
+        let token = handles.cancel.clone();
+        (self._stream_blocks(true, handles), token)
+    }
+
+    /// Same as `stream_blocks`, but also returns a handle to the profile info of the query.
+    /// The profile info is available once the stream is exhausted.
+    pub fn stream_blocks_with_profile(self) -> (BoxStream<'a, Result<Block>>, QueryProfileHandle) {
+        let handles = StreamHandles::default();
+        let profile = handles.profile.clone();
+        (self._stream_blocks(true, handles), profile)
+    }
+
+    /// Same as `stream_blocks`, but also returns a handle to the totals and extremes
+    /// blocks, which are available once the stream is exhausted.
+    ///
+    /// Other methods skip these blocks.
+    pub fn stream_blocks_with_totals(self) -> (BoxStream<'a, Result<Block>>, TotalsHandle) {
+        let handles = StreamHandles::default();
+        let totals = handles.totals.clone();
+        (self._stream_blocks(true, handles), totals)
+    }
+
+    /// Same as `stream_blocks`, but a task reads and decodes up to `n` blocks ahead,
+    /// so the network and the consumer work at the same time. Memory stays bounded:
+    /// the task waits while `n` blocks are buffered.
+    ///
+    /// The connection moves to the task and comes back once the stream is exhausted.
+    /// If the stream is dropped earlier, the task is aborted and the handle reconnects
+    /// on its next query.
+    pub fn stream_blocks_buffered(self, n: usize) -> BoxStream<'a, Result<Block>> {
+        let placeholder = self.client.placeholder();
+        let mut client = mem::replace(self.client, placeholder);
+        let query = self.query;
+        let (sender, receiver) = mpsc::channel(cmp::max(n, 1));
+
+        let task = tokio::spawn(async move {
+            {
+                let mut stream = client.query(query).stream_blocks();
+                while let Some(block) = stream.next().await {
+                    let failed = block.is_err();
+                    if sender.send(block).await.is_err() || failed {
+                        break;
+                    }
+                }
+            }
+            client
+        });
+
+        Box::pin(BufferedBlocks {
+            client: self.client,
+            receiver,
+            task: Some(task),
+        })
+    }
+
+    fn _stream_blocks(
+        self,
+        skip_first_block: bool,
+        handles: StreamHandles,
+    ) -> BoxStream<'a, Result<Block>> {
         let query = self.query.clone();
 
         self.client
@@ -104,10 +614,107 @@ impl<'a> QueryResult<'a> {
 
                 let inner = c.inner.take().unwrap().call(Cmd::SendQuery(query, context));
 
-                BlockStream::<'a>::new(c, inner, skip_first_block)
+                BlockStream::<'a>::new(c, inner, skip_first_block, handles)
             })
     }
 
+    fn query_timeout(&self) -> Result<Duration> {
+        match self.client.context.config.query_timeout {
+            Some(timeout) => Ok(timeout),
+            None => Err(Error::Other(
+                "Query timeout was not set on `PoolConfig`".into(),
+            )),
+        }
+    }
+
+    /// Calls `f` for every row of the result. Unlike `stream`, rows borrow their block,
+    /// so no reference counting is involved.
+    ///
+    /// Stops at the first error returned by `f`; in that case the query is cancelled
+    /// and the rest of the result is drained, so the connection can be reused.
+    pub async fn for_each_row<F>(self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&Row<Simple>) -> Result<()>,
+    {
+        let handles = StreamHandles::default();
+        let cancel = handles.cancel.clone();
+        let mut stream = self._stream_blocks(true, handles);
+
+        let mut result = Ok(());
+        while let Some(block) = stream.next().await {
+            let block = block?;
+            if result.is_err() {
+                continue;
+            }
+
+            for row in block.rows() {
+                if let Err(err) = f(&row) {
+                    result = Err(err);
+                    cancel.cancel();
+                    break;
+                }
+            }
+        }
+        result
+    }
+
+    /// Calls `f` for every block of the result with views of the `schema` columns,
+    /// in the order of `schema`. The views borrow the block data, so the memory used
+    /// does not grow with the size of the result.
+    ///
+    /// The schema is checked with `Block::validate_schema` once, on the first block.
+    /// Numeric columns are passed as slices and string columns as `StringsView`,
+    /// other types are rejected.
+    ///
+    /// Stops at the first error like `for_each_row`.
+    pub async fn for_each_block_typed<F>(self, schema: &[(&str, SqlType)], mut f: F) -> Result<()>
+    where
+        F: FnMut(&[ColumnView]) -> Result<()>,
+    {
+        let handles = StreamHandles::default();
+        let cancel = handles.cancel.clone();
+        let mut stream = self._stream_blocks(true, handles);
+
+        let mut indexes: Option<Vec<usize>> = None;
+        let mut result = Ok(());
+        while let Some(block) = stream.next().await {
+            let block = block?;
+            if result.is_err() {
+                continue;
+            }
+
+            result = match indexes {
+                Some(ref indexes) => Ok(indexes),
+                None => block_indexes(&block, schema).map(|found| &*indexes.insert(found)),
+            }
+            .and_then(|indexes| {
+                let views = indexes
+                    .iter()
+                    .map(|&i| ColumnView::new(&block.columns()[i]))
+                    .collect::<Result<Vec<_>>>()?;
+                f(&views)
+            });
+
+            if result.is_err() {
+                cancel.cancel();
+            }
+        }
+        result
+    }
+
+    /// Maps every row of the result with `mapper` and pushes it into `sink`.
+    /// Like `for_each_row`, it borrows one block at a time.
+    pub async fn rows_into<T, F>(self, mut mapper: F, sink: &mut Vec<T>) -> Result<()>
+    where
+        F: FnMut(&Row<Simple>) -> Result<T>,
+    {
+        self.for_each_row(|row| {
+            sink.push(mapper(row)?);
+            Ok(())
+        })
+        .await
+    }
+
     pub fn stream(self) -> BoxStream<'a, Result<Row<'static, Simple>>> {
         Box::pin(
             self.stream_blocks()
@@ -137,19 +744,87 @@ impl<'a> QueryResult<'a> {
     }
 }
 
+/// Blocks read ahead by the task of `stream_blocks_buffered`.
+struct BufferedBlocks<'a> {
+    client: &'a mut ClientHandle,
+    receiver: mpsc::Receiver<Result<Block>>,
+    /// Returns the connection once the result is read.
+    task: Option<JoinHandle<ClientHandle>>,
+}
+
+impl<'a> Stream for BufferedBlocks<'a> {
+    type Item = Result<Block>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(block) = ready!(self.receiver.poll_recv(cx)) {
+            return Poll::Ready(Some(block));
+        }
+
+        let task = match self.task.as_mut() {
+            None => return Poll::Ready(None),
+            Some(task) => task,
+        };
+        let result = ready!(task.poll_unpin(cx));
+        self.task = None;
+
+        match result {
+            Ok(client) => {
+                *self.client = client;
+                Poll::Ready(None)
+            }
+            Err(err) => Poll::Ready(Some(Err(Error::Other(
+                format!("Reading blocks ahead failed: {}", err).into(),
+            )))),
+        }
+    }
+}
+
+impl<'a> Drop for BufferedBlocks<'a> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            // Dropping the task's stream leaves its connection to the pool.
+            task.abort();
+        }
+    }
+}
+
+/// Positions of the `schema` columns in `block`, after checking their types.
+fn block_indexes(block: &Block, schema: &[(&str, SqlType)]) -> Result<Vec<usize>> {
+    block.validate_schema(schema)?;
+    schema
+        .iter()
+        .map(|(name, _)| name.get_index(block.columns()))
+        .collect()
+}
+
+/// Handles shared between a caller and a running `BlockStream`.
+#[derive(Clone, Default)]
+pub(crate) struct StreamHandles {
+    pub(crate) cancel: CancelToken,
+    pub(crate) profile: QueryProfileHandle,
+    pub(crate) totals: TotalsHandle,
+    /// The first block of the result, it describes the columns even if there are no rows.
+    pub(crate) header: Arc<Mutex<Option<Block>>>,
+}
+
 pub mod block_stream {
     use std::{
+        future::Future,
         pin::Pin,
+        sync::Arc,
         task::{self, Poll},
+        time::Duration,
     };
 
     use futures_core::Stream;
     use futures_util::StreamExt;
+    use tokio::time::{self, Instant, Sleep};
 
     use crate::{
         block::Block,
         client::ClientHandle,
         error::{DriverError, Error, Result},
+        query::StreamHandles,
         transport::PacketStream,
         types::Packet,
     };
@@ -160,6 +835,11 @@ pub mod block_stream {
         eof: bool,
         block_index: usize,
         skip_first_block: bool,
+        handles: StreamHandles,
+        cancel_sent: bool,
+        /// Longest wait for the next packet, `PoolConfig.query_timeout`.
+        timeout: Option<Duration>,
+        deadline: Option<Pin<Box<Sleep>>>,
     }
 
     impl<'a> Drop for BlockStream<'a> {
@@ -183,13 +863,37 @@ pub mod block_stream {
             client: &mut ClientHandle,
             inner: PacketStream,
             skip_first_block: bool,
+            handles: StreamHandles,
         ) -> BlockStream {
+            let timeout = client.context.config.query_timeout;
             BlockStream {
                 client,
                 inner,
                 eof: false,
                 block_index: 0,
                 skip_first_block,
+                handles,
+                cancel_sent: false,
+                timeout,
+                deadline: None,
+            }
+        }
+
+        /// Returns `true` once no packet arrived for `timeout`.
+        fn poll_deadline(&mut self, cx: &mut task::Context<'_>) -> bool {
+            let timeout = match self.timeout {
+                None => return false,
+                Some(timeout) => timeout,
+            };
+            let deadline = self
+                .deadline
+                .get_or_insert_with(|| Box::pin(time::sleep(timeout)));
+            deadline.as_mut().poll(cx).is_ready()
+        }
+
+        fn reset_deadline(&mut self) {
+            if let (Some(timeout), Some(deadline)) = (self.timeout, self.deadline.as_mut()) {
+                deadline.as_mut().reset(Instant::now() + timeout);
             }
         }
     }
@@ -206,14 +910,47 @@ pub mod block_stream {
                     return Poll::Ready(None);
                 }
 
+                self.handles.cancel.register(cx.waker());
+                if !self.cancel_sent && self.handles.cancel.is_cancelled() {
+                    // Remaining packets are drained until `Eof`, which keeps the transport consistent.
+                    self.inner.cancel();
+                    self.cancel_sent = true;
+                }
+
                 let packet = match self.inner.poll_next_unpin(cx) {
-                    Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
-                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Some(Err(err))) => {
+                        let err = match Error::from(err) {
+                            err @ (Error::IO(_) | Error::Driver(DriverError::ConnectionClosed)) => {
+                                // As after a timeout, the connection is not reused.
+                                self.eof = true;
+                                Error::Driver(DriverError::QueryInterrupted(Arc::new(err)))
+                            }
+                            // The rest of the block is unread, so the connection is not reused.
+                            err @ Error::Driver(DriverError::BlockTooLarge { .. }) => {
+                                self.eof = true;
+                                err
+                            }
+                            err => err,
+                        };
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    Poll::Pending => {
+                        if self.poll_deadline(cx) {
+                            // The transport is left unread: `Drop` marks it inconsistent
+                            // and, with the pool detached, the connection is not reused.
+                            self.eof = true;
+                            return Poll::Ready(Some(Err(Error::Driver(DriverError::Timeout))));
+                        }
+                        return Poll::Pending;
+                    }
                     Poll::Ready(None) => {
                         self.eof = true;
                         continue;
                     }
-                    Poll::Ready(Some(Ok(packet))) => packet,
+                    Poll::Ready(Some(Ok(packet))) => {
+                        self.reset_deadline();
+                        packet
+                    }
                 };
 
                 match packet {
@@ -224,22 +961,699 @@ pub mod block_stream {
                         }
                         self.eof = true;
                     }
-                    Packet::ProfileInfo(_) | Packet::Progress(_) => {}
+                    Packet::ProfileInfo(info) => self.handles.profile.set(info.into()),
+                    Packet::Progress(_) => {}
                     Packet::Exception(exception) => {
                         self.eof = true;
                         return Poll::Ready(Some(Err(exception.into())));
                     }
                     Packet::Block(block) => {
                         self.block_index += 1;
+                        if self.block_index == 1 {
+                            *self.handles.header.lock().unwrap() = Some(block.clone());
+                        }
+                        if self.cancel_sent {
+                            continue;
+                        }
                         if (self.block_index > 1 || !self.skip_first_block) && !block.is_empty() {
                             return Poll::Ready(Some(Ok(block)));
                         }
                     }
+                    Packet::Totals(block) => self.handles.totals.set_totals(block),
+                    Packet::Extremes(block) => self.handles.totals.set_extremes(block),
                     _ => {
-                        return Poll::Ready(Some(Err(Error::Driver(DriverError::UnexpectedPacket))))
+                        return Poll::Ready(Some(Err(Error::Driver(
+                            DriverError::UnexpectedPacketAt {
+                                packet: packet.code(),
+                                offset: self.inner.packet_offset(),
+                            },
+                        ))))
                     }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{
+        error::DriverError,
+        mock_server::{
+            self, closing_server, config, exception_server, query_server, stalled_server,
+        },
+        pool::Pool,
+        types::ProfileInfo,
+    };
+
+    #[tokio::test]
+    async fn test_stream_inactivity_timeout() {
+        let url = stalled_server().await;
+        let config = mock_server::builder(url)
+            .with_query_timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let pool = Pool::new(config);
+        let mut client = pool.get_handle().await.unwrap();
+
+        let started = Instant::now();
+        let mut stream = client.query("SELECT 1").stream_blocks();
+        match stream.next().await {
+            Some(Err(Error::Driver(DriverError::Timeout))) => {}
+            Some(Err(err)) => panic!("unexpected error {}", err),
+            Some(Ok(_)) | None => panic!("the stream should time out"),
+        }
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(stream.next().await.is_none());
+        drop(stream);
+
+        // The handle is still usable: the next query clears or replaces the connection.
+        match client.query("SELECT 1").fetch_all().await {
+            Err(Error::Driver(DriverError::Timeout)) => {}
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("the query should time out"),
+        }
+    }
+
+    #[test]
+    fn test_param() {
+        let query = Query::new("SELECT {s:String}, {a:Array(Nullable(String))}")
+            .param("s", "it's a\ttab")
+            .param(
+                "a",
+                Value::Array(
+                    SqlType::Nullable(SqlType::String.into()).into(),
+                    Arc::new(vec![Some("x'y").into(), None::<&str>.into()]),
+                ),
+            )
+            .param("n", None::<u32>)
+            .param(
+                "t",
+                Value::Tuple(Arc::new(vec![1_u8.into(), "a\\b".into()])),
+            )
+            .param("s", "it's\\");
+
+        assert_eq!(
+            query.get_params(),
+            [
+                ("s".to_string(), b"it's\\\\".to_vec()),
+                ("a".to_string(), b"['x\\'y',NULL]".to_vec()),
+                ("n".to_string(), b"\\N".to_vec()),
+                ("t".to_string(), b"(1,'a\\\\b')".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_param_binary() {
+        let bytes = vec![0xff_u8, b'\t', 0x80, b'\''];
+        let query = Query::new("SELECT {b:String}, {a:Array(String)}")
+            .param("b", Value::String(Arc::new(bytes.clone())))
+            .param(
+                "a",
+                Value::Array(
+                    SqlType::String.into(),
+                    Arc::new(vec![Value::String(Arc::new(bytes))]),
+                ),
+            );
+
+        assert_eq!(
+            query.get_params(),
+            [
+                ("b".to_string(), b"\xff\\t\x80'".to_vec()),
+                ("a".to_string(), b"['\xff\\t\x80\\'']".to_vec()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_columns() {
+        let header = Block::new()
+            .column("id", Vec::<u32>::new())
+            .column("name", Vec::<String>::new());
+        let first = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec!["a", "b"]);
+        let second = Block::new()
+            .column("id", vec![3_u32])
+            .column("name", vec!["c"]);
+
+        let url = query_server(vec![header, first, second]).await;
+        let pool = Pool::new(config(url));
+        let mut client = pool.get_handle().await.unwrap();
+
+        let columns = client
+            .query("SELECT id, name")
+            .fetch_columns()
+            .await
+            .unwrap();
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].name(), "id");
+        assert_eq!(columns[1].sql_type(), SqlType::String);
+
+        let ids: Vec<u32> = columns[0].iter::<u32>().unwrap().copied().collect();
+        assert_eq!(ids, [1, 2, 3]);
+        let names: Vec<&[u8]> = columns[1].iter::<&[u8]>().unwrap().collect();
+        assert_eq!(names, [b"a", b"b", b"c"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_columns_empty() {
+        let header = Block::new()
+            .column("id", Vec::<u32>::new())
+            .column("name", Vec::<String>::new());
+
+        let url = query_server(vec![header]).await;
+        let pool = Pool::new(config(url));
+        let mut client = pool.get_handle().await.unwrap();
+
+        let columns = client
+            .query("SELECT id, name")
+            .fetch_columns()
+            .await
+            .unwrap();
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].sql_type(), SqlType::UInt32);
+        assert_eq!(columns[1].name(), "name");
+        assert!(columns.iter().all(|column| column.len() == 0));
+    }
+
+    #[tokio::test]
+    async fn test_stream_blocks_with_header() {
+        let header = Block::new().column("id", Vec::<u32>::new());
+        let data = Block::new().column("id", vec![1_u32, 2]);
+
+        let url = query_server(vec![header.clone(), Block::new(), data.clone()]).await;
+        let pool = Pool::new(config(url));
+        let mut client = pool.get_handle().await.unwrap();
+
+        let blocks: Vec<Block> = client
+            .query("SELECT id")
+            .stream_blocks_with_header()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(blocks, [header.clone(), data.clone()]);
+
+        let blocks: Vec<Block> = client
+            .query("SELECT id")
+            .stream_blocks()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(blocks, [data]);
+
+        let url = query_server(vec![header.clone()]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+        let blocks: Vec<Block> = client
+            .query("SELECT id")
+            .stream_blocks_with_header()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].row_count(), 0);
+        assert_eq!(
+            blocks[0].get_column("id").unwrap().sql_type(),
+            SqlType::UInt32
+        );
+    }
+
+    #[tokio::test]
+    async fn test_describe() {
+        let header = Block::new()
+            .column("id", Vec::<u32>::new())
+            .column("name", Vec::<String>::new());
+        let data = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec!["a", "b"]);
+
+        let url = query_server(vec![header, data]).await;
+        let pool = Pool::new(config(url));
+        let mut client = pool.get_handle().await.unwrap();
+
+        let columns = client.query("SELECT id, name").describe().await.unwrap();
+        assert_eq!(
+            columns,
+            [
+                ("id".to_string(), SqlType::UInt32),
+                ("name".to_string(), SqlType::String)
+            ]
+        );
+
+        let rows = client.query("SELECT id, name").fetch_all().await.unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let url = exception_server(60, "Table default.missing doesn't exist").await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+        match client.query("SELECT * FROM missing").describe().await {
+            Err(Error::Server(e)) => assert_eq!(e.code(), 60),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_scalar() {
+        let header = Block::new().column("count()", Vec::<u64>::new());
+        let data = Block::new().column("count()", vec![42_u64]);
+        let url = query_server(vec![header, data]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+
+        let count: u64 = client
+            .query("SELECT count() FROM t")
+            .fetch_scalar()
+            .await
+            .unwrap();
+        assert_eq!(count, 42);
+
+        let empty = Block::new().column("id", Vec::<u32>::new());
+        let url = query_server(vec![empty]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+        let err = client
+            .query("SELECT id FROM t")
+            .fetch_scalar::<u32>()
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Other error: `The result has no rows.`");
+
+        let data = Block::new()
+            .column("id", vec![1_u32])
+            .column("name", vec!["a"]);
+        let url = query_server(vec![data]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+        assert!(client
+            .query("SELECT id, name FROM t")
+            .fetch_scalar::<u32>()
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_tsv() {
+        let header = Block::new()
+            .column("id", Vec::<u32>::new())
+            .column("name", Vec::<Option<String>>::new())
+            .column("tags", Vec::<Vec<String>>::new());
+        let first = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec![Some("a\tb"), None])
+            .column("tags", vec![vec!["x", "it's"], vec![]]);
+        let second = Block::new()
+            .column("id", vec![3_u32])
+            .column("name", vec![Some("line\nbreak\\")])
+            .column("tags", vec![vec!["y"]]);
+
+        let url = query_server(vec![header, first, second]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+
+        let mut out = Vec::new();
+        let rows = client
+            .query("SELECT id, name, tags")
+            .write_tsv(&mut out)
+            .await
+            .unwrap();
+
+        assert_eq!(rows, 3);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1\ta\\tb\t['x','it\\'s']\n2\t\\N\t[]\n3\tline\\nbreak\\\\\t['y']\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_tsv_binary() {
+        let header = Block::new().column("data", Vec::<&[u8]>::new());
+        let block = Block::new().column("data", vec![&b"\xff\xfe\r\n\0a"[..]]);
+
+        let url = query_server(vec![header, block]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+
+        let mut out = Vec::new();
+        let rows = client
+            .query("SELECT data")
+            .write_tsv(&mut out)
+            .await
+            .unwrap();
+
+        assert_eq!(rows, 1);
+        assert_eq!(out, b"\xff\xfe\\r\\n\\0a\n");
+    }
+
+    #[tokio::test]
+    async fn test_stream_blocks_buffered() {
+        let header = Block::new().column("id", Vec::<u32>::new());
+        let blocks: Vec<Block> = (0..5_u32)
+            .map(|i| Block::new().column("id", vec![i]))
+            .collect();
+
+        let url = query_server([vec![header], blocks.clone()].concat()).await;
+        let pool = Pool::new(config(url));
+        let mut client = pool.get_handle().await.unwrap();
+
+        let actual: Vec<Block> = client
+            .query("SELECT id")
+            .stream_blocks_buffered(2)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(actual, blocks);
+        assert!(client.inner.is_some());
+
+        let rows = client.query("SELECT id").fetch_all().await.unwrap();
+        assert_eq!(rows.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_stream_blocks_buffered_dropped() {
+        let blocks: Vec<Block> = (0..5_u32)
+            .map(|i| Block::new().column("id", vec![i]))
+            .collect();
+
+        let url = query_server(blocks).await;
+        let pool = Pool::new(config(url));
+        let mut client = pool.get_handle().await.unwrap();
+
+        {
+            let mut stream = client.query("SELECT id").stream_blocks_buffered(1);
+            stream.next().await.unwrap().unwrap();
+        }
+        assert!(client.inner.is_none());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pool.metrics().ongoing, 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_closed_mid_block() {
+        let block = Block::new()
+            .column("id", vec![1_u32, 2, 3])
+            .column("name", vec!["a", "b", "c"]);
+
+        for cut in [0, 3] {
+            let url = closing_server(block.clone(), cut).await;
+            let config = mock_server::builder(url)
+                .with_query_timeout(Duration::from_secs(5))
+                .build()
+                .unwrap();
+            let pool = Pool::new(config);
+            let mut client = pool.get_handle().await.unwrap();
+
+            {
+                let mut stream = client.query("SELECT id, name").stream_blocks_with_header();
+                if cut == 0 {
+                    assert_eq!(stream.next().await.unwrap().unwrap(), block);
+                }
+                match stream.next().await {
+                    Some(Err(Error::Driver(DriverError::QueryInterrupted(_)))) => {}
+                    Some(Err(err)) => panic!("unexpected error {}", err),
+                    Some(Ok(_)) | None => panic!("the stream should fail"),
+                }
+            }
+            assert!(client.inner.as_ref().unwrap().inconsistent);
+
+            drop(client);
+            assert_eq!(pool.metrics().idle, 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_interrupted_cause() {
+        let block = Block::new().column("id", vec![1_u32, 2, 3]);
+        let url = closing_server(block, 3).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+
+        let err = client.query("SELECT id").fetch_all().await.err().unwrap();
+        let cause = match &err {
+            Error::Driver(DriverError::QueryInterrupted(cause)) => cause,
+            err => panic!("unexpected error {}", err),
+        };
+        assert!(matches!(
+            **cause,
+            Error::Driver(DriverError::ConnectionClosed)
+        ));
+
+        // The cause is also the source of the error.
+        let source = std::error::Error::source(&err).unwrap();
+        let source = std::error::Error::source(source).unwrap();
+        assert_eq!(source.to_string(), cause.to_string());
+
+        // The handle reconnects for the next query, which the server cuts again.
+        match client.query("SELECT id").fetch_all().await {
+            Err(Error::Driver(DriverError::QueryInterrupted(_))) => {}
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("the query should be interrupted"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_for_each_block_typed() {
+        let first = Block::new()
+            .column("name", vec!["a", "bb"])
+            .column("id", vec![1_u64, 2]);
+        let second = Block::new()
+            .column("name", vec!["ccc"])
+            .column("id", vec![3_u64]);
+
+        let url = query_server(vec![Block::new(), first, second]).await;
+        let pool = Pool::new(config(url));
+        let mut client = pool.get_handle().await.unwrap();
+
+        let schema = [("id", SqlType::UInt64), ("name", SqlType::String)];
+        let mut sum = 0;
+        let mut names = Vec::new();
+        client
+            .query("SELECT name, id")
+            .for_each_block_typed(&schema, |columns| {
+                match columns {
+                    [ColumnView::UInt64(ids), ColumnView::Strings(strings)] => {
+                        assert_eq!(ids.len(), strings.len());
+                        sum += ids.iter().sum::<u64>();
+                        names.extend(strings.iter().map(<[u8]>::len));
+                    }
+                    columns => panic!("unexpected columns {:?}", columns),
+                }
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(sum, 6);
+        assert_eq!(names, [1, 2, 3]);
+
+        let schema = [("id", SqlType::UInt32)];
+        let err = client
+            .query("SELECT name, id")
+            .for_each_block_typed(&schema, |_| panic!("the schema does not match"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Other error: `Column `id` has type UInt64, expected UInt32.`"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_block_bytes() {
+        let block = Block::new().column("id", vec![1_u64; 100]);
+        let url = query_server(vec![Block::new(), block]).await;
+        let config = mock_server::builder(url)
+            .with_max_block_bytes(799)
+            .build()
+            .unwrap();
+        let pool = Pool::new(config);
+        let mut client = pool.get_handle().await.unwrap();
+
+        // The second query runs on the same handle after the first one failed.
+        for _ in 0..2 {
+            let result = client.query("SELECT id").fetch_all().await;
+            assert!(matches!(
+                result.err().unwrap(),
+                Error::Driver(DriverError::BlockTooLarge {
+                    estimate: 800,
+                    limit: 799
+                })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_blocks_cancellable() {
+        let header = Block::new().column("id", Vec::<u32>::new());
+        let blocks: Vec<Block> = (0..3_u32)
+            .map(|i| Block::new().column("id", vec![i]))
+            .collect();
+
+        let url = query_server([vec![header], blocks.clone()].concat()).await;
+        let pool = Pool::new(config(url));
+        let mut client = pool.get_handle().await.unwrap();
+
+        {
+            let (mut stream, token) = client.query("SELECT id").stream_blocks_cancellable();
+            assert_eq!(stream.next().await.unwrap().unwrap(), blocks[0]);
+            assert!(!token.is_cancelled());
+
+            token.cancel();
+            assert!(token.is_cancelled());
+            // The rest of the result is drained without being yielded.
+            assert!(stream.next().await.is_none());
+        }
+        assert!(!client.inner.as_ref().unwrap().inconsistent);
+
+        let rows = client.query("SELECT id").fetch_all().await.unwrap();
+        assert_eq!(rows.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_one() {
+        let header = Block::new()
+            .column("id", Vec::<u32>::new())
+            .column("name", Vec::<String>::new());
+        let first = Block::new()
+            .column("id", vec![7_u32, 8])
+            .column("name", vec!["a", "b"]);
+        let second = Block::new()
+            .column("id", vec![9_u32])
+            .column("name", vec!["c"]);
+
+        let url = query_server(vec![header.clone(), Block::new(), first, second]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+
+        let row = client
+            .query("SELECT id, name")
+            .fetch_one()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.get::<u32, _>("id").unwrap(), 7);
+        assert_eq!(row.get::<String, _>("name").unwrap(), "a");
+
+        // The rest of the result was read, so the connection is ready.
+        assert!(!client.inner.as_ref().unwrap().inconsistent);
+
+        let url = query_server(vec![header]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+        let row = client.query("SELECT id, name").fetch_one().await.unwrap();
+        assert!(row.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_blocks_with_profile() {
+        let block = Block::new().column("id", vec![1_u32, 2]);
+        let profile = ProfileInfo {
+            rows: 2,
+            blocks: 1,
+            bytes: 8,
+            applied_limit: true,
+            rows_before_limit: 1000,
+            calculated_rows_before_limit: true,
+        };
+
+        let url = mock_server::profile_server(vec![Block::new(), block.clone()], profile).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+
+        let (mut stream, handle) = client
+            .query("SELECT id LIMIT 2")
+            .stream_blocks_with_profile();
+        assert_eq!(stream.next().await.unwrap().unwrap(), block);
+        assert!(stream.next().await.is_none());
+
+        let profile = handle.get().unwrap();
+        assert_eq!(profile.rows(), 2);
+        assert_eq!(profile.blocks(), 1);
+        assert_eq!(profile.bytes(), 8);
+        assert!(profile.applied_limit());
+        assert_eq!(profile.rows_before_limit(), 1000);
+        assert!(profile.calculated_rows_before_limit());
+
+        let url = query_server(vec![block]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+        let (stream, handle) = client.query("SELECT id").stream_blocks_with_profile();
+        stream.try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(handle.get(), None);
+    }
+
+    #[tokio::test]
+    async fn test_for_each_row() {
+        let header = Block::new().column("id", Vec::<u32>::new());
+        let blocks: Vec<Block> = [vec![1_u32, 2], vec![3], vec![4, 5]]
+            .into_iter()
+            .map(|ids| Block::new().column("id", ids))
+            .collect();
+
+        let url = query_server([vec![header], blocks].concat()).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+
+        let mut ids = Vec::new();
+        client
+            .query("SELECT id")
+            .for_each_row(|row| {
+                ids.push(row.get::<u32, _>("id")?);
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids, [1, 2, 3, 4, 5]);
+
+        // The first error stops the calls, the rest of the result is drained.
+        let mut calls = 0;
+        let err = client
+            .query("SELECT id")
+            .for_each_row(|row| {
+                calls += 1;
+                match row.get::<u32, _>("id")? {
+                    2 => Err(Error::Other("stop".into())),
+                    _ => Ok(()),
+                }
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Other error: `stop`");
+        assert_eq!(calls, 2);
+        assert!(!client.inner.as_ref().unwrap().inconsistent);
+
+        let rows = client.query("SELECT id").fetch_all().await.unwrap();
+        assert_eq!(rows.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_rows_into() {
+        let header = Block::new()
+            .column("id", Vec::<u32>::new())
+            .column("name", Vec::<String>::new());
+        let first = Block::new()
+            .column("id", vec![1_u32, 2])
+            .column("name", vec!["a", "b"]);
+        let second = Block::new()
+            .column("id", vec![3_u32])
+            .column("name", vec!["c"]);
+
+        let url = query_server(vec![header, first, second]).await;
+        let mut client = Pool::new(config(url)).get_handle().await.unwrap();
+
+        let mut sink = vec![(0, "existing".to_string())];
+        client
+            .query("SELECT id, name")
+            .rows_into(|row| Ok((row.get("id")?, row.get("name")?)), &mut sink)
+            .await
+            .unwrap();
+        assert_eq!(
+            sink,
+            [
+                (0_u32, "existing".to_string()),
+                (1, "a".to_string()),
+                (2, "b".to_string()),
+                (3, "c".to_string()),
+            ]
+        );
+
+        let mut sink: Vec<u32> = Vec::new();
+        let result = client
+            .query("SELECT id, name")
+            .rows_into(|row| row.get("missing"), &mut sink)
+            .await;
+        assert!(result.is_err());
+        assert!(sink.is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+//! SOCKS5 client handshake (RFC 1928) with username/password authentication (RFC 1929).
+
+use std::{io, net::IpAddr};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+use url::{Host, Url};
+
+use crate::pool::ProxyConfig;
+
+const VERSION: u8 = 5;
+const AUTH_VERSION: u8 = 1;
+
+const NO_AUTH: u8 = 0;
+const USERNAME_PASSWORD: u8 = 2;
+const NO_ACCEPTABLE_METHODS: u8 = 0xff;
+
+const CMD_CONNECT: u8 = 1;
+
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+/// Opens a connection to `addr` through the proxy. Host names are resolved by the proxy.
+pub(crate) async fn connect(proxy: &ProxyConfig, addr: &Url) -> io::Result<TcpStream> {
+    // Hosts of URLs like `tcp://127.0.0.1` are not parsed as IP addresses.
+    let host = match addr.host() {
+        None => return Err(invalid_input("No host name in the URL.")),
+        Some(Host::Domain(domain)) => match domain.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => Host::Ipv4(ip),
+            Ok(IpAddr::V6(ip)) => Host::Ipv6(ip),
+            Err(_) => Host::Domain(domain),
+        },
+        Some(host) => host,
+    };
+    let port = addr
+        .port_or_known_default()
+        .ok_or_else(|| invalid_input("No port number in the URL."))?;
+
+    let mut stream = TcpStream::connect(&proxy.addr).await?;
+    handshake(&mut stream, proxy.credentials.as_ref(), host, port).await?;
+    Ok(stream)
+}
+
+async fn handshake<S>(
+    stream: &mut S,
+    credentials: Option<&(String, String)>,
+    host: Host<&str>,
+    port: u16,
+) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match credentials {
+        None => stream.write_all(&[VERSION, 1, NO_AUTH]).await?,
+        Some(_) => {
+            stream
+                .write_all(&[VERSION, 2, NO_AUTH, USERNAME_PASSWORD])
+                .await?
+        }
+    }
+
+    let mut reply = [0_u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(proxy_error(format!(
+            "Unsupported SOCKS version {}.",
+            reply[0]
+        )));
+    }
+
+    match (reply[1], credentials) {
+        (NO_AUTH, _) => {}
+        (USERNAME_PASSWORD, Some((username, password))) => {
+            authenticate(stream, username, password).await?
+        }
+        (NO_ACCEPTABLE_METHODS, _) => {
+            return Err(proxy_error(
+                "The proxy accepts none of the authentication methods.".into(),
+            ))
+        }
+        (method, _) => {
+            return Err(proxy_error(format!(
+                "Unexpected authentication method {}.",
+                method
+            )))
+        }
+    }
+
+    let mut request = vec![VERSION, CMD_CONNECT, 0];
+    match host {
+        Host::Ipv4(ip) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Host::Ipv6(ip) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&ip.octets());
+        }
+        Host::Domain(domain) => {
+            request.push(ATYP_DOMAIN);
+            request.push(short_len(domain.as_bytes(), "host name")?);
+            request.extend_from_slice(domain.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0_u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(proxy_error(format!(
+            "Unsupported SOCKS version {}.",
+            reply[0]
+        )));
+    }
+    if reply[1] != 0 {
+        return Err(proxy_error(format!(
+            "The proxy failed to connect: {}.",
+            reply_message(reply[1])
+        )));
+    }
+
+    // The address the proxy bound for the connection, it is not used.
+    let bound_len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => stream.read_u8().await? as usize,
+        atyp => return Err(proxy_error(format!("Unexpected address type {}.", atyp))),
+    };
+    let mut bound = vec![0_u8; bound_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(())
+}
+
+async fn authenticate<S>(stream: &mut S, username: &str, password: &str) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut request = vec![AUTH_VERSION];
+    request.push(short_len(username.as_bytes(), "username")?);
+    request.extend_from_slice(username.as_bytes());
+    request.push(short_len(password.as_bytes(), "password")?);
+    request.extend_from_slice(password.as_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0_u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        return Err(proxy_error("The proxy rejected the credentials.".into()));
+    }
+    Ok(())
+}
+
+fn short_len(bytes: &[u8], what: &str) -> io::Result<u8> {
+    u8::try_from(bytes.len())
+        .map_err(|_| invalid_input(&format!("The {} is longer than 255 bytes.", what)))
+}
+
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn proxy_error(message: String) -> io::Error {
+    io::Error::other(message)
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::{io::duplex, net::TcpListener};
+
+    use super::*;
+    use crate::{mock_server, pool::Pool};
+
+    /// Starts a proxy which accepts `credentials` and forwards one connection to the
+    /// IPv4 address it is asked for.
+    async fn proxy(credentials: Option<(&'static str, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (mut client, _) = listener.accept().await.unwrap();
+
+            let mut greeting = [0_u8; 2];
+            client.read_exact(&mut greeting).await.unwrap();
+            let mut methods = vec![0_u8; greeting[1] as usize];
+            client.read_exact(&mut methods).await.unwrap();
+
+            match credentials {
+                None => client.write_all(&[VERSION, NO_AUTH]).await.unwrap(),
+                Some((username, password)) => {
+                    assert!(methods.contains(&USERNAME_PASSWORD));
+                    client
+                        .write_all(&[VERSION, USERNAME_PASSWORD])
+                        .await
+                        .unwrap();
+
+                    let mut request = vec![0_u8; 3 + username.len() + password.len()];
+                    client.read_exact(&mut request).await.unwrap();
+                    assert_eq!(&request[2..2 + username.len()], username.as_bytes());
+                    assert_eq!(&request[3 + username.len()..], password.as_bytes());
+                    client.write_all(&[AUTH_VERSION, 0]).await.unwrap();
+                }
+            }
+
+            let mut request = [0_u8; 10];
+            client.read_exact(&mut request).await.unwrap();
+            assert_eq!(request[..4], [VERSION, CMD_CONNECT, 0, ATYP_IPV4]);
+            let ip: [u8; 4] = request[4..8].try_into().unwrap();
+            let port = u16::from_be_bytes([request[8], request[9]]);
+
+            let mut server = TcpStream::connect((std::net::Ipv4Addr::from(ip), port))
+                .await
+                .unwrap();
+            client
+                .write_all(&[VERSION, 0, 0, ATYP_IPV4, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+        });
+
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_connect_through_proxy() {
+        for credentials in [None, Some(("user", "secret"))] {
+            let url = mock_server::stalled_server().await;
+            let mut proxy_config = ProxyConfig::new(proxy(credentials).await);
+            if let Some((username, password)) = credentials {
+                proxy_config = proxy_config.with_credentials(username, password);
+            }
+
+            let config = mock_server::builder(url)
+                .with_proxy(proxy_config)
+                .build()
+                .unwrap();
+            let client = Pool::new(config).get_handle().await.unwrap();
+            assert_eq!(client.server_revision(), mock_server::REVISION);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_domain() {
+        let (mut client, mut server) = duplex(1024);
+
+        let proxy = tokio::spawn(async move {
+            let mut greeting = [0_u8; 3];
+            server.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [VERSION, 1, NO_AUTH]);
+            server.write_all(&[VERSION, NO_AUTH]).await.unwrap();
+
+            let mut request = [0_u8; 5 + 9 + 2];
+            server.read_exact(&mut request).await.unwrap();
+            assert_eq!(request[..5], [VERSION, CMD_CONNECT, 0, ATYP_DOMAIN, 9]);
+            assert_eq!(&request[5..14], b"localhost");
+            assert_eq!(request[14..], 9000_u16.to_be_bytes());
+
+            let mut reply = vec![VERSION, 0, 0, ATYP_DOMAIN, 4];
+            reply.extend_from_slice(b"host\x23\x28");
+            server.write_all(&reply).await.unwrap();
+        });
+
+        handshake(&mut client, None, Host::Domain("localhost"), 9000)
+            .await
+            .unwrap();
+        proxy.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handshake_failure() {
+        let (mut client, mut server) = duplex(1024);
+
+        tokio::spawn(async move {
+            let mut greeting = [0_u8; 3];
+            server.read_exact(&mut greeting).await.unwrap();
+            server.write_all(&[VERSION, NO_AUTH]).await.unwrap();
+
+            let mut request = [0_u8; 10];
+            server.read_exact(&mut request).await.unwrap();
+            server
+                .write_all(&[VERSION, 5, 0, ATYP_IPV4, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+        });
+
+        let err = handshake(&mut client, None, Host::Ipv4([10, 0, 0, 1].into()), 9000)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The proxy failed to connect: connection refused."
+        );
+    }
+}
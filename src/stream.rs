@@ -1,6 +1,7 @@
 use std::{
     future::Future,
     io,
+    net::SocketAddr,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -9,16 +10,17 @@ use futures_util::future::BoxFuture;
 use futures_util::future::{select_ok, SelectOk, TryFutureExt};
 use futures_util::FutureExt;
 
-use tokio::net::TcpStream;
+use tokio::net::{lookup_host, TcpSocket, TcpStream};
 use tokio_native_tls::TlsStream;
 
 use pin_project::pin_project;
-use url::Url;
+use url::{Host, Url};
 
 use crate::{
     error::{ConnectionError, ConnectionResult},
     inner_stream::InnerStream,
     pool::PoolConfig,
+    socks,
 };
 
 type ConnectingFuture<T> = BoxFuture<'static, ConnectionResult<T>>;
@@ -95,51 +97,78 @@ pub(crate) struct ConnectingStream {
 }
 
 impl ConnectingStream {
-    pub(crate) fn new(addr: &Url, config: &PoolConfig) -> Self {
-        match addr.socket_addrs(|| None) {
-            Ok(addresses) => {
-                let streams: Vec<_> = addresses
-                    .iter()
-                    .copied()
-                    .map(|address| -> ConnectingFuture<TcpStream> {
-                        Box::pin(TcpStream::connect(address).map_err(ConnectionError::IOError))
-                    })
-                    .collect();
-
-                if streams.is_empty() {
-                    let err = io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        "Could not resolve to any address.",
-                    );
-                    return Self {
-                        state: State::Tcp(TcpState::Fail(Some(ConnectionError::IOError(err)))),
-                    };
+    /// Races connections to all the `addresses` of `addr`, or connects through the proxy
+    /// if one is configured.
+    pub(crate) fn new(
+        addr: &Url,
+        addresses: io::Result<Vec<SocketAddr>>,
+        config: &PoolConfig,
+    ) -> Self {
+        let streams: Vec<ConnectingFuture<TcpStream>> = match (&config.proxy, addresses) {
+            (Some(proxy), _) => {
+                let (proxy, addr) = (proxy.clone(), addr.clone());
+                vec![Box::pin(async move {
+                    socks::connect(&proxy, &addr)
+                        .await
+                        .map_err(ConnectionError::IOError)
+                })]
+            }
+            (None, Ok(addresses)) => addresses
+                .iter()
+                .copied()
+                .map(|address| -> ConnectingFuture<TcpStream> {
+                    Box::pin(
+                        connect(address, SocketOptions::from(config))
+                            .map_err(ConnectionError::IOError),
+                    )
+                })
+                .collect(),
+            (None, Err(err)) => {
+                return Self {
+                    state: State::Tcp(TcpState::Fail(Some(ConnectionError::IOError(err)))),
                 }
+            }
+        };
 
-                let socket = select_ok(streams);
+        if streams.is_empty() {
+            let err = io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Could not resolve to any address.",
+            );
+            return Self {
+                state: State::Tcp(TcpState::Fail(Some(ConnectionError::IOError(err)))),
+            };
+        }
 
-                if config.secure {
-                    return ConnectingStream::new_tls_connection(addr, socket);
-                }
+        let socket = select_ok(streams);
 
-                Self {
-                    state: State::Tcp(TcpState::Wait(socket)),
-                }
-            }
-            Err(err) => Self {
-                state: State::Tcp(TcpState::Fail(Some(ConnectionError::IOError(err)))),
-            },
+        if config.secure {
+            return ConnectingStream::new_tls_connection(addr, socket, config);
+        }
+
+        Self {
+            state: State::Tcp(TcpState::Wait(socket)),
         }
     }
 
-    fn new_tls_connection(addr: &Url, socket: SelectOk<ConnectingFuture<TcpStream>>) -> Self {
+    fn new_tls_connection(
+        addr: &Url,
+        socket: SelectOk<ConnectingFuture<TcpStream>>,
+        config: &PoolConfig,
+    ) -> Self {
         match addr.host_str().map(|host| host.to_owned()) {
             None => Self {
                 state: State::Tls(TlsState::Fail(Some(ConnectionError::TlsHostNotProvided))),
             },
             Some(host) => {
-                let mut builder = native_tls::TlsConnector::builder();
-                builder.danger_accept_invalid_certs(true);
+                let builder = match tls_connector_builder(config) {
+                    Ok(builder) => builder,
+                    Err(err) => {
+                        return Self {
+                            state: State::Tls(TlsState::Fail(Some(err.into()))),
+                        }
+                    }
+                };
 
                 Self {
                     state: State::Tls(TlsState::Wait(Box::pin(async move {
@@ -163,3 +192,154 @@ impl Future for ConnectingStream {
         self.project().state.poll(cx)
     }
 }
+
+/// Options of the sockets opened to the server, see `PoolConfigBuilder`.
+#[derive(Clone, Copy, Default)]
+struct SocketOptions {
+    bind_address: Option<SocketAddr>,
+    buffer_sizes: Option<(usize, usize)>,
+}
+
+impl From<&PoolConfig> for SocketOptions {
+    fn from(config: &PoolConfig) -> Self {
+        Self {
+            bind_address: config.bind_address,
+            buffer_sizes: config.socket_buffer_sizes,
+        }
+    }
+}
+
+/// Connects to `address`, setting up the socket with `options` first.
+async fn connect(address: SocketAddr, options: SocketOptions) -> io::Result<TcpStream> {
+    if options.bind_address.is_none() && options.buffer_sizes.is_none() {
+        return TcpStream::connect(address).await;
+    }
+
+    let socket = match address {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+
+    if let Some(bind_address) = options.bind_address {
+        if bind_address.is_ipv4() != address.is_ipv4() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} can't be reached from {}.", address, bind_address),
+            ));
+        }
+        socket.bind(bind_address)?;
+    }
+
+    if let Some((recv, send)) = options.buffer_sizes {
+        // Larger sizes are clamped by the OS anyway.
+        socket.set_recv_buffer_size(u32::try_from(recv).unwrap_or(u32::MAX))?;
+        socket.set_send_buffer_size(u32::try_from(send).unwrap_or(u32::MAX))?;
+    }
+
+    socket.connect(address).await
+}
+
+/// Resolves the host of `addr` without blocking the runtime.
+/// IP addresses are used as is, host names may yield both IPv4 and IPv6 addresses.
+pub(crate) async fn resolve(addr: &Url) -> io::Result<Vec<SocketAddr>> {
+    let port = addr
+        .port_or_known_default()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No port number in the URL."))?;
+
+    match addr.host() {
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No host name in the URL.",
+        )),
+        Some(Host::Ipv4(ip)) => Ok(vec![SocketAddr::new(ip.into(), port)]),
+        Some(Host::Ipv6(ip)) => Ok(vec![SocketAddr::new(ip.into(), port)]),
+        Some(Host::Domain(domain)) => Ok(lookup_host((domain, port)).await?.collect()),
+    }
+}
+
+fn tls_connector_builder(
+    config: &PoolConfig,
+) -> Result<native_tls::TlsConnectorBuilder, native_tls::Error> {
+    let mut builder = native_tls::TlsConnector::builder();
+
+    if !config.tls_verification {
+        builder.danger_accept_invalid_certs(true);
+    }
+
+    if let Some(ref pem) = config.ca_certificate {
+        builder.add_root_certificate(native_tls::Certificate::from_pem(pem)?);
+    }
+
+    if let Some((ref pkcs12, ref password)) = config.client_identity {
+        builder.identity(native_tls::Identity::from_pkcs12(pkcs12, password)?);
+    }
+
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{mock_server, pool::PoolConfigBuilder};
+
+    fn builder() -> PoolConfigBuilder {
+        mock_server::builder(Url::parse("tcp://localhost:9440").unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_connect_from_bind_address() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let options = SocketOptions {
+            bind_address: Some("127.0.0.1:0".parse().unwrap()),
+            ..Default::default()
+        };
+        let stream = connect(address, options).await.unwrap();
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(stream.local_addr().unwrap(), peer);
+
+        let options = SocketOptions {
+            bind_address: Some("[::1]:0".parse().unwrap()),
+            ..Default::default()
+        };
+        let err = connect(address, options).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_buffer_sizes() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        for buffer_sizes in [(1 << 20, 1 << 16), (usize::MAX, 0)] {
+            let options = SocketOptions {
+                buffer_sizes: Some(buffer_sizes),
+                ..Default::default()
+            };
+            let stream = connect(address, options).await.unwrap();
+            let (_, peer) = listener.accept().await.unwrap();
+            assert_eq!(stream.local_addr().unwrap(), peer);
+        }
+    }
+
+    #[test]
+    fn test_tls_connector_builder() {
+        assert!(tls_connector_builder(&builder().build().unwrap()).is_ok());
+
+        let config = builder().with_tls_verification(false).build().unwrap();
+        assert!(tls_connector_builder(&config).is_ok());
+
+        let config = builder()
+            .with_ca_certificate(b"not a pem".to_vec())
+            .build()
+            .unwrap();
+        assert!(tls_connector_builder(&config).is_err());
+
+        let config = builder()
+            .with_client_identity(b"not a pkcs12".to_vec(), "".into())
+            .build()
+            .unwrap();
+        assert!(tls_connector_builder(&config).is_err());
+    }
+}
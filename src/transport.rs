@@ -2,6 +2,7 @@
 use std::{
     collections::VecDeque,
     io::{self, Cursor},
+    mem,
     pin::Pin,
     ptr,
     sync::{
@@ -23,13 +24,17 @@ use futures_core::Stream;
 use futures_util::StreamExt;
 
 use crate::{
-    binary::Parser,
-    error::{Error, Result},
+    binary::{Encoder, Parser},
+    block::BlockLimit,
+    error::{DriverError, Error, Result},
     inner_stream::InnerStream,
     pool::{Inner, Pool},
-    types::{Cmd, Packet},
+    types::{Cmd, Compression, Packet},
 };
 
+/// Larger write buffers, e.g. of a query with a long SQL text, are not kept for reuse.
+const MAX_REUSED_WRITE_BUFFER: usize = 64 * 1024;
+
 /// Line transport
 #[pin_project(project = ClickhouseTransportProj)]
 pub(crate) struct ClickhouseTransport {
@@ -42,12 +47,21 @@ pub(crate) struct ClickhouseTransport {
     rd: Vec<u8>,
     /// Whether the buffer is known to be incomplete
     buf_is_incomplete: bool,
+    /// Number of bytes consumed from the socket before `rd`
+    rd_offset: u64,
+    /// Position of the last parsed packet in the server stream
+    packet_offset: u64,
     /// Current buffer to write to the socket
     wr: io::Cursor<Vec<u8>>,
     /// Queued commands
     cmds: VecDeque<Cmd>,
     /// Server time zone
     timezone: Option<Tz>,
+    /// Protocol revision negotiated in `Hello`
+    revision: u64,
+    /// Compression method negotiated for data blocks
+    compression: Compression,
+    block_limit: Option<BlockLimit>,
     /// Whether there are unread packets
     pub(crate) inconsistent: bool,
     status: Arc<TransportStatus>,
@@ -69,23 +83,39 @@ pub(crate) struct PacketStream {
     inner: Option<ClickhouseTransport>,
     state: PacketStreamState,
     read_block: bool,
+    packet_offset: u64,
 }
 
 impl ClickhouseTransport {
-    pub fn new(inner: InnerStream<TcpStream>, pool: Option<Pool>) -> Self {
+    pub fn new(
+        inner: InnerStream<TcpStream>,
+        pool: Option<Pool>,
+        compression: Compression,
+        block_limit: Option<BlockLimit>,
+    ) -> Self {
         ClickhouseTransport {
             inner,
             done: false,
             rd: vec![],
             buf_is_incomplete: false,
+            rd_offset: 0,
+            packet_offset: 0,
             wr: io::Cursor::new(vec![]),
             cmds: VecDeque::new(),
             timezone: None,
+            revision: 0,
+            compression,
+            block_limit,
             inconsistent: false,
             status: Arc::new(TransportStatus::new(pool)),
         }
     }
 
+    /// Whether the server closed the connection.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.done
+    }
+
     pub(crate) fn set_inside(&self, value: bool) {
         self.status.inside.store(value, Ordering::Release);
     }
@@ -116,6 +146,36 @@ impl ClickhouseTransport {
 
         Ok(transport)
     }
+
+    /// Skips the packets left by a failed query and reads until the answer to a ping,
+    /// cancelling the query first if it may still run.
+    pub(crate) async fn drain(mut self) -> Result<Self> {
+        if self.inconsistent {
+            self.cmds.push_back(Cmd::Cancel);
+        }
+        self.cmds.push_back(Cmd::Ping);
+
+        let mut transport = self;
+        loop {
+            let mut next = None;
+            let mut stream = transport.receive();
+
+            while let Some(packet) = stream.next().await {
+                match packet {
+                    Ok(Packet::Pong(mut inner)) => {
+                        inner.inconsistent = false;
+                        return Ok(inner);
+                    }
+                    // The end of the query, the pong follows it.
+                    Ok(Packet::Eof(inner)) => next = Some(inner),
+                    Err(e) => return Err(Error::IO(e)),
+                    _ => {}
+                }
+            }
+
+            transport = next.ok_or(Error::Driver(DriverError::UnexpectedPacket))?;
+        }
+    }
 }
 
 impl Drop for TransportStatus {
@@ -153,7 +213,14 @@ impl<'p> ClickhouseTransportProj<'p> {
         let ret = {
             let mut cursor = Cursor::new(&self.rd);
             let res = {
-                let mut parser = Parser::new(&mut cursor, *self.timezone);
+                let mut parser = Parser::new(
+                    &mut cursor,
+                    *self.timezone,
+                    *self.revision,
+                    *self.compression,
+                    *self.rd_offset,
+                )
+                .with_block_limit(*self.block_limit);
                 parser.parse_packet()
             };
 
@@ -161,6 +228,7 @@ impl<'p> ClickhouseTransportProj<'p> {
 
             if let Ok(Packet::Hello(_, ref packet)) = res {
                 *self.timezone = Some(packet.timezone);
+                *self.revision = packet.protocol_revision();
             }
 
             // TODO - better casting `WouldBlock` here
@@ -177,6 +245,8 @@ impl<'p> ClickhouseTransportProj<'p> {
             Poll::Pending => (),
             _ => {
                 // Data is consumed
+                *self.packet_offset = *self.rd_offset;
+                *self.rd_offset += pos as u64;
                 let new_len = self.rd.len() - pos;
                 unsafe {
                     ptr::copy(self.rd.as_ptr().add(pos), self.rd.as_mut_ptr(), new_len);
@@ -236,8 +306,15 @@ impl ClickhouseTransport {
                 match self.cmds.pop_front() {
                     None => return Poll::Ready(Ok(())),
                     Some(cmd) => {
-                        let bytes = cmd.get_packed_command()?;
-                        self.wr = Cursor::new(bytes)
+                        // The write buffer of the connection is reused for every command.
+                        let mut buffer = mem::take(self.wr.get_mut());
+                        if buffer.capacity() > MAX_REUSED_WRITE_BUFFER {
+                            buffer = Vec::new();
+                        }
+                        let mut encoder = Encoder::from(buffer);
+                        encoder.clear();
+                        cmd.encode(&mut encoder)?;
+                        self.wr = Cursor::new(encoder.get_buffer())
                     }
                 }
             }
@@ -281,6 +358,13 @@ impl Stream for ClickhouseTransport {
         // Try to parse the new data!
         let ret = this.try_parse_msg();
 
+        // More data is needed, but the server closed the connection.
+        if *this.done && matches!(ret, Poll::Pending) {
+            *this.inconsistent = true;
+            let err = Error::Driver(DriverError::ConnectionClosed);
+            return Poll::Ready(Some(Err(err.into())));
+        }
+
         *this.buf_is_incomplete = matches!(ret, Poll::Pending);
 
         ret
@@ -291,6 +375,19 @@ impl PacketStream {
     pub(crate) fn take_transport(&mut self) -> Option<ClickhouseTransport> {
         self.inner.take()
     }
+
+    /// Position of the last received packet in the server stream.
+    pub(crate) fn packet_offset(&self) -> u64 {
+        self.packet_offset
+    }
+
+    /// Queues `Cmd::Cancel` and switches the stream back to sending.
+    pub(crate) fn cancel(&mut self) {
+        if let Some(ref mut inner) = self.inner {
+            inner.cmds.push_back(Cmd::Cancel);
+            self.state = PacketStreamState::Ask;
+        }
+    }
 }
 
 impl Stream for PacketStream {
@@ -336,6 +433,9 @@ impl Stream for PacketStream {
                     match ret {
                         None => PacketStreamState::Done,
                         Some(packet) => {
+                            if let Some(ref inner) = self.inner {
+                                self.packet_offset = inner.packet_offset;
+                            }
                             let result = packet.bind(&mut self.inner);
                             PacketStreamState::Yield(Box::new(Some(result)))
                         }
@@ -369,10 +469,16 @@ impl Stream for PacketStream {
 impl ClickhouseTransport {
     pub fn call(mut self, req: Cmd) -> PacketStream {
         self.cmds.push_back(req);
+        self.receive()
+    }
+
+    /// Sends the queued commands and reads the answers.
+    fn receive(self) -> PacketStream {
         PacketStream {
             inner: Some(self),
             state: PacketStreamState::Ask,
             read_block: false,
+            packet_offset: 0,
         }
     }
 }
@@ -1,3 +1,5 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use log::trace;
 
 use crate::{
@@ -7,7 +9,8 @@ use crate::{
     column::Simple,
     error::Result,
     protocol::{self, client_info},
-    query::Query,
+    query::{Query, TraceContext},
+    types::Compression,
 };
 
 /// Represents Clickhouse commands.
@@ -19,89 +22,538 @@ pub(crate) enum Cmd {
 }
 
 impl Cmd {
-    /// Returns the packed command as a byte vector.
-    #[inline(always)]
-    pub(crate) fn get_packed_command(&self) -> Result<Vec<u8>> {
-        encode_command(self)
-    }
-}
-
-fn encode_command(cmd: &Cmd) -> Result<Vec<u8>> {
-    match cmd {
-        Cmd::Hello(context) => encode_hello(context),
-        Cmd::Ping => Ok(encode_ping()),
-        Cmd::SendQuery(query, context) => encode_query(query, context),
-        Cmd::Cancel => Ok(encode_cancel()),
+    /// Appends the packed command to `encoder`, whose buffer may be reused.
+    pub(crate) fn encode(&self, encoder: &mut Encoder) -> Result<()> {
+        match self {
+            Cmd::Hello(context) => encode_hello(context, encoder),
+            Cmd::Ping => encode_ping(encoder),
+            Cmd::SendQuery(query, context) => encode_query(query, context, encoder)?,
+            Cmd::Cancel => encode_cancel(encoder),
+        }
+        Ok(())
     }
 }
 
-fn encode_hello(context: &Context) -> Result<Vec<u8>> {
+fn encode_hello(context: &Context, encoder: &mut Encoder) {
     trace!("[hello]");
 
-    let mut encoder = Encoder::new();
     encoder.uvarint(protocol::CLIENT_HELLO);
-    client_info::write(&mut encoder);
+    client_info::write(encoder, &context.config.client_name);
 
-    let config = context.config.clone();
+    let config = &context.config;
 
     encoder.string(&config.database);
     encoder.string(&config.username);
     encoder.string(&config.password);
-
-    Ok(encoder.get_buffer())
 }
 
-fn encode_ping() -> Vec<u8> {
+fn encode_ping(encoder: &mut Encoder) {
     trace!("[ping]         -> ping");
 
-    let mut encoder = Encoder::new();
     encoder.uvarint(protocol::CLIENT_PING);
-    encoder.get_buffer()
 }
 
-fn encode_cancel() -> Vec<u8> {
+fn encode_cancel(encoder: &mut Encoder) {
     trace!("[cancel]");
 
-    let mut encoder = Encoder::new();
     encoder.uvarint(protocol::CLIENT_CANCEL);
-    encoder.get_buffer()
 }
 
-fn encode_query(query: &Query, context: &Context) -> Result<Vec<u8>> {
+fn encode_query(query: &Query, context: &Context, encoder: &mut Encoder) -> Result<()> {
     trace!("[send query] {}", query.get_sql());
 
-    let mut encoder = Encoder::new();
     encoder.uvarint(protocol::CLIENT_QUERY);
-    encoder.string(""); // readonly
+    encoder.string(query.get_id()); // query_id, the server generates one when empty
 
+    let revision = context.server_info.protocol_revision();
     {
         let hostname = &context.hostname;
         encoder.uvarint(1);
         encoder.string("");
         encoder.string(query.get_id()); // initial_query_id;
         encoder.string("[::ffff:127.0.0.1]:0");
+        if revision >= protocol::DBMS_MIN_PROTOCOL_VERSION_WITH_INITIAL_QUERY_START_TIME {
+            encoder.write(start_time_micros());
+        }
         encoder.uvarint(1); // iface type TCP;
         encoder.string(hostname);
         encoder.string(hostname);
     }
-    client_info::write(&mut encoder);
+    client_info::write(encoder, &context.config.client_name);
 
-    if context.server_info.revision >= protocol::DBMS_MIN_REVISION_WITH_QUOTA_KEY_IN_CLIENT_INFO {
+    if revision >= protocol::DBMS_MIN_REVISION_WITH_QUOTA_KEY_IN_CLIENT_INFO {
         encoder.string("");
     }
+    if revision >= protocol::DBMS_MIN_PROTOCOL_VERSION_WITH_DISTRIBUTED_DEPTH {
+        encoder.uvarint(0);
+    }
+    if revision >= protocol::DBMS_MIN_REVISION_WITH_VERSION_PATCH {
+        encoder.uvarint(client_info::CLICK_HOUSE_DBMSVERSION_PATCH);
+    }
+    if revision >= protocol::DBMS_MIN_REVISION_WITH_OPENTELEMETRY {
+        encode_trace_context(query.get_trace_context(), encoder);
+    }
 
-    // settings
-    encoder.string(protocol::READONLY_FLAG);
-    encoder.uvarint(protocol::READONLY_LEVEL);
-    encoder.string("");
+    let config = &context.config;
+    let mut defaults = Vec::new();
+    if let Some(limit) = config.max_execution_time {
+        defaults.push((
+            protocol::MAX_EXECUTION_TIME,
+            timeout_secs(limit).to_string(),
+        ));
+    }
+    if config.compression == Compression::Zstd {
+        defaults.push((protocol::NETWORK_COMPRESSION_METHOD, "ZSTD".to_string()));
+    }
+    encode_settings(query, encoder, revision, &defaults);
+
+    if revision >= protocol::DBMS_MIN_REVISION_WITH_INTERSERVER_SECRET {
+        encoder.string("");
+    }
 
     encoder.uvarint(protocol::STATE_COMPLETE);
 
-    encoder.uvarint(protocol::COMPRESS_DISABLE);
+    let compression = context.config.compression;
+    encoder.uvarint(if compression.is_enabled() {
+        protocol::COMPRESS_ENABLE
+    } else {
+        protocol::COMPRESS_DISABLE
+    });
 
     encoder.string(query.get_sql());
 
-    Block::<Simple>::default().send_data(&mut encoder);
+    Block::<Simple>::default().send_data(encoder, compression)
+}
+
+fn encode_trace_context(trace: Option<TraceContext>, encoder: &mut Encoder) {
+    match trace {
+        Some(trace) if trace.trace_id != 0 => {
+            encoder.write(1_u8);
+            // The trace id goes as a UUID: the high half first, both halves little-endian.
+            encoder.write((trace.trace_id >> 64) as u64);
+            encoder.write(trace.trace_id as u64);
+            encoder.write(trace.span_id);
+            encoder.string(""); // tracestate
+            encoder.write(trace.flags);
+        }
+        _ => encoder.write(0_u8), // no trace context
+    }
+}
+
+fn start_time_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as u64)
+}
+
+/// Whole seconds of `max_execution_time`, rounded up so the server never stops
+/// before the configured limit.
+fn timeout_secs(timeout: Duration) -> u64 {
+    let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+    secs.max(1)
+}
+
+/// Settings are written as strings with flags since revision 54429, older servers
+/// expect numeric values as uvarint and the rest as strings.
+/// Query parameters follow as `param_<name>` settings.
+///
+/// `defaults` are settings chosen by the client, such as `max_execution_time`. Each is
+/// added unless the query sets it itself.
+fn encode_settings(
+    query: &Query,
+    encoder: &mut Encoder,
+    revision: u64,
+    defaults: &[(&str, String)],
+) {
+    let as_strings = revision >= protocol::DBMS_MIN_REVISION_WITH_SETTINGS_SERIALIZED_AS_STRINGS;
+
+    let readonly_level = u64::from(query.get_readonly_level());
+    encoder.string(protocol::READONLY_FLAG);
+    if as_strings {
+        encoder.uvarint(protocol::SETTING_FLAG_IMPORTANT);
+        encoder.string(readonly_level.to_string());
+    } else {
+        encoder.uvarint(readonly_level);
+    }
+
+    let settings = query.get_settings();
+    let defaults = defaults
+        .iter()
+        .filter(|(key, _)| !settings.iter().any(|(k, _)| k == key))
+        .map(|(key, value)| (*key, value));
+    let settings = settings.iter().map(|(key, value)| (key.as_str(), value));
+
+    for (key, value) in settings.chain(defaults) {
+        encoder.string(key);
+        if as_strings {
+            encoder.uvarint(0);
+            encoder.string(value);
+            continue;
+        }
+        match value.parse::<u64>() {
+            Ok(value) => encoder.uvarint(value),
+            Err(_) => encoder.string(value),
+        }
+    }
+
+    for (name, value) in query.get_params() {
+        encoder.string(format!("param_{}", name));
+        if as_strings {
+            encoder.uvarint(protocol::SETTING_FLAG_CUSTOM);
+        }
+        encoder.byte_string(value);
+    }
+
+    encoder.string("");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{pool::PoolConfigBuilder, types::ServerInfo};
+
+    fn builder() -> PoolConfigBuilder {
+        PoolConfigBuilder::new(
+            "tcp://localhost:9000".parse().unwrap(),
+            "default".to_string(),
+            "user".to_string(),
+            "".to_string(),
+            false,
+        )
+    }
+
+    fn context(client_name: Option<&str>) -> Context {
+        let mut builder = builder();
+        if let Some(name) = client_name {
+            builder = builder.with_client_name(name.to_string());
+        }
+        context_with(builder)
+    }
+
+    fn context_with(builder: PoolConfigBuilder) -> Context {
+        Context {
+            server_info: ServerInfo::default(),
+            hostname: "localhost".to_string(),
+            config: builder.build().unwrap(),
+        }
+    }
+
+    fn packed_hello(context: &Context) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encode_hello(context, &mut encoder);
+        encoder.get_buffer()
+    }
+
+    fn packed_query(query: &Query, context: &Context) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encode_query(query, context, &mut encoder).unwrap();
+        encoder.get_buffer()
+    }
+
+    #[test]
+    fn test_encode_hello_client_name() {
+        let mut expected = Encoder::new();
+        expected.uvarint(protocol::CLIENT_HELLO);
+        expected.string("reporting-service");
+        expected.uvarint(client_info::CLICK_HOUSE_DBMSVERSION_MAJOR);
+        expected.uvarint(client_info::CLICK_HOUSE_DBMSVERSION_MINOR);
+        expected.uvarint(client_info::CLICK_HOUSE_REVISION);
+        expected.string("default");
+        expected.string("user");
+        expected.string("");
+
+        let hello = packed_hello(&context(Some("reporting-service")));
+        assert_eq!(hello, expected.get_buffer());
 
-    Ok(encoder.get_buffer())
+        let hello = packed_hello(&context(None));
+        assert!(hello
+            .windows(client_info::CLIENT_NAME.len())
+            .any(|w| w == client_info::CLIENT_NAME.as_bytes()));
+    }
+
+    #[test]
+    fn test_encode_query_id() {
+        let context = context(None);
+
+        let packet = packed_query(&Query::new("SELECT 1").id("trace-42"), &context);
+        let mut expected = Encoder::new();
+        expected.uvarint(protocol::CLIENT_QUERY);
+        expected.string("trace-42");
+        expected.uvarint(1);
+        expected.string("");
+        expected.string("trace-42");
+        let expected = expected.get_buffer();
+        assert_eq!(&packet[..expected.len()], &expected[..]);
+
+        let packet = packed_query(&Query::new("SELECT 1"), &context);
+        let mut expected = Encoder::new();
+        expected.uvarint(protocol::CLIENT_QUERY);
+        expected.string("");
+        expected.uvarint(1);
+        let expected = expected.get_buffer();
+        assert_eq!(&packet[..expected.len()], &expected[..]);
+    }
+
+    #[test]
+    fn test_encode_readonly_level() {
+        let query = Query::new("SELECT 1").readonly_level(2).unwrap();
+
+        let mut encoder = Encoder::new();
+        encode_settings(&query, &mut encoder, 0, &[]);
+
+        let mut expected = Encoder::new();
+        expected.string("readonly");
+        expected.uvarint(2);
+        expected.string("");
+
+        assert_eq!(encoder.get_buffer(), expected.get_buffer());
+        assert!(Query::new("SELECT 1").readonly_level(3).is_err());
+    }
+
+    #[test]
+    fn test_encode_settings() {
+        let query = Query::new("SELECT 1")
+            .with_setting("max_result_rows", 10)
+            .with_setting("readonly", 0)
+            .with_setting("result_overflow_mode", "break")
+            .with_setting("max_result_rows", 300)
+            .param("id", 42_u64);
+
+        let mut encoder = Encoder::new();
+        encode_settings(&query, &mut encoder, 0, &[]);
+
+        let mut expected = Encoder::new();
+        expected.string("readonly");
+        expected.uvarint(1);
+        expected.string("max_result_rows");
+        expected.uvarint(300);
+        expected.string("result_overflow_mode");
+        expected.string("break");
+        expected.string("param_id");
+        expected.string("42");
+        expected.string("");
+
+        assert_eq!(encoder.get_buffer(), expected.get_buffer());
+    }
+
+    #[test]
+    fn test_encode_max_execution_time() {
+        let max_execution_time = [(protocol::MAX_EXECUTION_TIME, "5".to_string())];
+        let mut encoder = Encoder::new();
+        encode_settings(
+            &Query::new("SELECT 1"),
+            &mut encoder,
+            0,
+            &max_execution_time,
+        );
+
+        let mut expected = Encoder::new();
+        expected.string("readonly");
+        expected.uvarint(1);
+        expected.string("max_execution_time");
+        expected.uvarint(5);
+        expected.string("");
+        assert_eq!(encoder.get_buffer(), expected.get_buffer());
+
+        // The setting of the query wins.
+        let query = Query::new("SELECT 1").with_setting("max_execution_time", 60);
+        let mut encoder = Encoder::new();
+        encode_settings(&query, &mut encoder, 0, &max_execution_time);
+
+        let mut expected = Encoder::new();
+        expected.string("readonly");
+        expected.uvarint(1);
+        expected.string("max_execution_time");
+        expected.uvarint(60);
+        expected.string("");
+        assert_eq!(encoder.get_buffer(), expected.get_buffer());
+
+        assert_eq!(timeout_secs(Duration::from_secs(5)), 5);
+        assert_eq!(timeout_secs(Duration::from_millis(5200)), 6);
+        assert_eq!(timeout_secs(Duration::from_millis(200)), 1);
+    }
+
+    #[test]
+    fn test_encode_query_max_execution_time() {
+        let contains = |packet: &[u8]| {
+            packet
+                .windows(protocol::MAX_EXECUTION_TIME.len())
+                .any(|w| w == protocol::MAX_EXECUTION_TIME.as_bytes())
+        };
+        let query = Query::new("SELECT 1");
+
+        // The inactivity timeout stays on the client: a query streaming blocks for
+        // longer than it is not aborted by the server.
+        let default = context(None);
+        assert!(default.config.query_timeout.is_some());
+        assert!(!contains(&packed_query(&query, &default)));
+
+        let timeout = context_with(builder().with_query_timeout(Duration::from_secs(30)));
+        assert!(!contains(&packed_query(&query, &timeout)));
+
+        let limit = context_with(
+            builder()
+                .with_query_timeout(Duration::from_secs(1))
+                .with_max_execution_time(Duration::from_millis(29_500)),
+        );
+        let mut expected = Encoder::new();
+        encode_settings(
+            &query,
+            &mut expected,
+            0,
+            &[(protocol::MAX_EXECUTION_TIME, "30".to_string())],
+        );
+        let expected = expected.get_buffer();
+        let packet = packed_query(&query, &limit);
+        assert!(packet.windows(expected.len()).any(|w| w == expected));
+    }
+
+    #[test]
+    fn test_encode_query_network_compression_method() {
+        let query = Query::new("SELECT 1");
+        let mut settings = Encoder::new();
+        settings.string(protocol::NETWORK_COMPRESSION_METHOD);
+        settings.uvarint(0);
+        settings.string("ZSTD");
+        let settings = settings.get_buffer();
+        let contains = |packet: &[u8]| packet.windows(settings.len()).any(|w| w == settings);
+
+        let mut context = context_with(builder().with_compression(Compression::Zstd));
+        context.server_info.revision = client_info::CLICK_HOUSE_REVISION;
+        assert!(contains(&packed_query(&query, &context)));
+
+        // The server compresses with LZ4 unless asked otherwise.
+        let mut context = context_with(builder().with_compression(Compression::Lz4));
+        context.server_info.revision = client_info::CLICK_HOUSE_REVISION;
+        assert!(!contains(&packed_query(&query, &context)));
+
+        // A setting of the query wins.
+        let mut context = context_with(builder().with_compression(Compression::Zstd));
+        context.server_info.revision = client_info::CLICK_HOUSE_REVISION;
+        let query = query.with_setting(protocol::NETWORK_COMPRESSION_METHOD, "LZ4");
+        assert!(!contains(&packed_query(&query, &context)));
+    }
+
+    #[test]
+    fn test_encode_settings_as_strings() {
+        let query = Query::new("SELECT 1")
+            .with_setting("max_result_rows", 300)
+            .param("id", 42_u64);
+
+        let mut encoder = Encoder::new();
+        encode_settings(
+            &query,
+            &mut encoder,
+            protocol::DBMS_MIN_REVISION_WITH_SETTINGS_SERIALIZED_AS_STRINGS,
+            &[],
+        );
+
+        let mut expected = Encoder::new();
+        expected.string("readonly");
+        expected.uvarint(protocol::SETTING_FLAG_IMPORTANT);
+        expected.string("1");
+        expected.string("max_result_rows");
+        expected.uvarint(0);
+        expected.string("300");
+        expected.string("param_id");
+        expected.uvarint(protocol::SETTING_FLAG_CUSTOM);
+        expected.string("42");
+        expected.string("");
+
+        assert_eq!(encoder.get_buffer(), expected.get_buffer());
+    }
+
+    #[test]
+    fn test_encode_query_revisions() {
+        let mut context = context(None);
+        let query = Query::new("SELECT 1").id("q");
+
+        // Old servers get the client info up to the quota key.
+        context.server_info.revision = 54213;
+        let packet = packed_query(&query, &context);
+        let mut expected = Encoder::new();
+        expected.uvarint(protocol::CLIENT_QUERY);
+        expected.string("q");
+        expected.uvarint(1);
+        expected.string("");
+        expected.string("q");
+        expected.string("[::ffff:127.0.0.1]:0");
+        expected.uvarint(1);
+        expected.string("localhost");
+        expected.string("localhost");
+        client_info::write(&mut expected, client_info::CLIENT_NAME);
+        expected.string("");
+        encode_settings(&query, &mut expected, 54213, &[]);
+        expected.uvarint(protocol::STATE_COMPLETE);
+        let expected = expected.get_buffer();
+        assert_eq!(&packet[..expected.len()], &expected[..]);
+
+        // Newer servers speak the client revision, with the start time and the fields
+        // after the quota key.
+        context.server_info.revision = 54465;
+        let packet = packed_query(&query, &context);
+        let mut head = Encoder::new();
+        head.uvarint(protocol::CLIENT_QUERY);
+        head.string("q");
+        head.uvarint(1);
+        head.string("");
+        head.string("q");
+        head.string("[::ffff:127.0.0.1]:0");
+        let head = head.get_buffer();
+        assert_eq!(&packet[..head.len()], &head[..]);
+
+        let start_time = u64::from_le_bytes(packet[head.len()..head.len() + 8].try_into().unwrap());
+        assert!(start_time > 0);
+
+        let mut tail = Encoder::new();
+        tail.uvarint(1);
+        tail.string("localhost");
+        tail.string("localhost");
+        client_info::write(&mut tail, client_info::CLIENT_NAME);
+        tail.string("");
+        tail.uvarint(0); // distributed depth
+        tail.uvarint(client_info::CLICK_HOUSE_DBMSVERSION_PATCH);
+        tail.write(0_u8);
+        encode_settings(&query, &mut tail, client_info::CLICK_HOUSE_REVISION, &[]);
+        tail.string("");
+        tail.uvarint(protocol::STATE_COMPLETE);
+        let tail = tail.get_buffer();
+        let start = head.len() + 8;
+        assert_eq!(&packet[start..start + tail.len()], &tail[..]);
+    }
+
+    #[test]
+    fn test_encode_query_trace_context() {
+        let mut context = context(None);
+        let plain = Query::new("SELECT 1").id("q");
+        let traced = plain.clone().with_trace_context(
+            0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10,
+            0x1112_1314_1516_1718,
+            1,
+        );
+
+        let mut expected = Encoder::new();
+        expected.write(1_u8);
+        expected.write(0x0102_0304_0506_0708_u64);
+        expected.write(0x090a_0b0c_0d0e_0f10_u64);
+        expected.write(0x1112_1314_1516_1718_u64);
+        expected.string("");
+        expected.write(1_u8);
+        let expected = expected.get_buffer();
+
+        context.server_info.revision = protocol::DBMS_MIN_REVISION_WITH_OPENTELEMETRY;
+        let packet = packed_query(&traced, &context);
+        assert!(packet.windows(expected.len()).any(|w| w == expected));
+        assert_eq!(
+            packet.len(),
+            packed_query(&plain, &context).len() + expected.len() - 1
+        );
+
+        // Older servers don't know the field, the context is dropped.
+        context.server_info.revision = protocol::DBMS_MIN_REVISION_WITH_OPENTELEMETRY - 1;
+        assert_eq!(
+            packed_query(&traced, &context),
+            packed_query(&plain, &context)
+        );
+    }
 }
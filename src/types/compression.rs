@@ -0,0 +1,15 @@
+/// Compression of data blocks transferred between client and server.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl Compression {
+    #[inline(always)]
+    pub(crate) fn is_enabled(self) -> bool {
+        self != Compression::None
+    }
+}
@@ -1,11 +1,14 @@
 pub(crate) use command::Cmd;
+pub use compression::Compression;
 pub(crate) use packet::Packet;
+pub use server_type::QueryProfile;
 pub(crate) use server_type::{ProfileInfo, Progress, ServerInfo};
-pub use sql_trait::{FromSql, HasSqlType};
+pub use sql_trait::{FromSql, HasSqlType, Lossy, Trimmed};
 pub use sql_type::SqlType;
 pub use stat_buffer::StatBuffer;
 
 mod command;
+mod compression;
 mod packet;
 mod server_type;
 mod stat_buffer;
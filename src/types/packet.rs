@@ -3,6 +3,7 @@ use std::fmt;
 use crate::{
     block::Block,
     error::ServerError,
+    protocol,
     types::{ProfileInfo, Progress, ServerInfo},
 };
 
@@ -14,6 +15,8 @@ pub(crate) enum Packet<S> {
     ProfileInfo(ProfileInfo),
     Exception(ServerError),
     Block(Block),
+    Totals(Block),
+    Extremes(Block),
     Eof(S),
 }
 
@@ -26,12 +29,29 @@ impl<S> fmt::Debug for Packet<S> {
             Packet::ProfileInfo(info) => write!(f, "ProfileInfo({:?})", info),
             Packet::Exception(e) => write!(f, "Exception({:?})", e),
             Packet::Block(b) => write!(f, "Block({:?})", b),
+            Packet::Totals(b) => write!(f, "Totals({:?})", b),
+            Packet::Extremes(b) => write!(f, "Extremes({:?})", b),
             Packet::Eof(_) => write!(f, "Eof"),
         }
     }
 }
 
 impl<S> Packet<S> {
+    /// The packet code of the protocol.
+    pub(crate) fn code(&self) -> u64 {
+        match self {
+            Packet::Hello(..) => protocol::SERVER_HELLO,
+            Packet::Pong(_) => protocol::SERVER_PONG,
+            Packet::Progress(_) => protocol::SERVER_PROGRESS,
+            Packet::ProfileInfo(_) => protocol::SERVER_PROFILE_INFO,
+            Packet::Exception(_) => protocol::SERVER_EXCEPTION,
+            Packet::Block(_) => protocol::SERVER_DATA,
+            Packet::Totals(_) => protocol::SERVER_TOTALS,
+            Packet::Extremes(_) => protocol::SERVER_EXTREMES,
+            Packet::Eof(_) => protocol::SERVER_END_OF_STREAM,
+        }
+    }
+
     pub fn bind<N>(self, transport: &mut Option<N>) -> Packet<N> {
         match self {
             Packet::Hello(_, server_info) => Packet::Hello(transport.take().unwrap(), server_info),
@@ -40,6 +60,8 @@ impl<S> Packet<S> {
             Packet::ProfileInfo(profile_info) => Packet::ProfileInfo(profile_info),
             Packet::Exception(exception) => Packet::Exception(exception),
             Packet::Block(block) => Packet::Block(block),
+            Packet::Totals(block) => Packet::Totals(block),
+            Packet::Extremes(block) => Packet::Extremes(block),
             Packet::Eof(_) => Packet::Eof(transport.take().unwrap()),
         }
     }
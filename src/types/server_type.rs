@@ -4,9 +4,19 @@ pub(crate) struct ServerInfo {
     pub revision: u64,
     pub minor_version: u64,
     pub major_version: u64,
+    pub patch_version: u64,
+    pub display_name: String,
     pub timezone: chrono_tz::Tz,
 }
 
+impl ServerInfo {
+    /// Revision of the protocol spoken on the connection.
+    pub(crate) fn protocol_revision(&self) -> u64 {
+        self.revision
+            .min(crate::protocol::client_info::CLICK_HOUSE_REVISION)
+    }
+}
+
 impl Default for ServerInfo {
     fn default() -> Self {
         Self {
@@ -14,6 +24,8 @@ impl Default for ServerInfo {
             revision: 0,
             minor_version: 0,
             major_version: 0,
+            patch_version: 0,
+            display_name: String::new(),
             timezone: chrono_tz::Tz::Zulu,
         }
     }
@@ -24,6 +36,8 @@ pub(crate) struct Progress {
     pub rows: u64,
     pub bytes: u64,
     pub total_rows: u64,
+    pub written_rows: u64,
+    pub written_bytes: u64,
 }
 
 #[derive(Copy, Clone, Default, Debug, PartialEq)]
@@ -36,6 +50,50 @@ pub(crate) struct ProfileInfo {
     pub calculated_rows_before_limit: bool,
 }
 
+/// Query summary sent by the server after the data.
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct QueryProfile {
+    info: ProfileInfo,
+}
+
+impl QueryProfile {
+    /// Number of rows in the result.
+    pub fn rows(&self) -> u64 {
+        self.info.rows
+    }
+
+    /// Number of blocks in the result.
+    pub fn blocks(&self) -> u64 {
+        self.info.blocks
+    }
+
+    /// Number of uncompressed bytes in the result.
+    pub fn bytes(&self) -> u64 {
+        self.info.bytes
+    }
+
+    /// Whether `LIMIT` was applied to the result.
+    pub fn applied_limit(&self) -> bool {
+        self.info.applied_limit
+    }
+
+    /// Number of rows the result would have without `LIMIT`.
+    pub fn rows_before_limit(&self) -> u64 {
+        self.info.rows_before_limit
+    }
+
+    /// Whether `rows_before_limit` was calculated by the server.
+    pub fn calculated_rows_before_limit(&self) -> bool {
+        self.info.calculated_rows_before_limit
+    }
+}
+
+impl From<ProfileInfo> for QueryProfile {
+    fn from(info: ProfileInfo) -> Self {
+        Self { info }
+    }
+}
+
 impl std::fmt::Debug for ServerInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
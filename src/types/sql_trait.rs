@@ -1,11 +1,11 @@
-use std::str::FromStr;
+use std::{borrow::Cow, collections::HashMap, hash::Hash, str::FromStr};
 
 use crate::error::{Error, FromSqlError, Result};
 use crate::types::sql_type::SqlType;
 use crate::value::value_ref::ValueRef;
 
 use either::Either;
-use ethnum::I256;
+use ethnum::{I256, U256};
 
 pub type FromSqlResult<T> = Result<T>;
 
@@ -35,6 +35,7 @@ has_sql_type! {
     i32: SqlType::Int32,
     i64: SqlType::Int64,
     I256: SqlType::Int256,
+    U256: SqlType::UInt256,
     &str: SqlType::String,
     String: SqlType::String,
     f32: SqlType::Float32,
@@ -66,6 +67,22 @@ macro_rules! from_sql_impl {
     };
 }
 
+impl<'a> FromSql<'a> for bool {
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Bool(v) => Ok(v),
+            ValueRef::UInt8(v) => Ok(v != 0),
+            _ => {
+                let from = SqlType::from(value.clone()).to_string();
+                Err(Error::FromSql(FromSqlError::InvalidType {
+                    src: from,
+                    dst: "bool".into(),
+                }))
+            }
+        }
+    }
+}
+
 impl<'a> FromSql<'a> for &'a str {
     fn from_sql(value: ValueRef<'a>) -> FromSqlResult<&'a str> {
         value.as_str()
@@ -111,6 +128,7 @@ impl<'a> FromSql<'a> for ethereum_types::U256 {
     fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
         match value {
             ValueRef::Int256(row) => Ok(ethereum_types::U256::from(&row.to_be_bytes())),
+            ValueRef::UInt256(row) => Ok(ethereum_types::U256::from(&row.to_be_bytes())),
             _ => {
                 let from = SqlType::from(value.clone()).to_string();
                 Err(Error::FromSql(FromSqlError::InvalidType {
@@ -122,12 +140,100 @@ impl<'a> FromSql<'a> for ethereum_types::U256 {
     }
 }
 
+/// Seconds since the epoch as a naive UTC timestamp, no timezone is applied.
+impl<'a> FromSql<'a> for chrono::NaiveDateTime {
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        let seconds = match value {
+            ValueRef::UInt32(v) => i64::from(v),
+            ValueRef::Int64(v) => v,
+            ValueRef::UInt64(v) => {
+                i64::try_from(v).map_err(|_| Error::FromSql(FromSqlError::OutOfRange))?
+            }
+            _ => {
+                let from = SqlType::from(value.clone()).to_string();
+                return Err(Error::FromSql(FromSqlError::InvalidType {
+                    src: from,
+                    dst: "NaiveDateTime".into(),
+                }));
+            }
+        };
+
+        chrono::DateTime::from_timestamp(seconds, 0)
+            .map(|dt| dt.naive_utc())
+            .ok_or(Error::FromSql(FromSqlError::OutOfRange))
+    }
+}
+
+/// A string with the trailing `\0` padding of `FixedString(N)` removed,
+/// e.g. `row.get::<Trimmed<String>, _>("code")`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Trimmed<T>(pub T);
+
+impl<T> Trimmed<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'a> FromSql<'a> for Trimmed<&'a str> {
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        value.as_str_trimmed().map(Trimmed)
+    }
+}
+
+impl<'a> FromSql<'a> for Trimmed<String> {
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        value.as_str_trimmed().map(|s| Trimmed(s.to_string()))
+    }
+}
+
+/// A string with invalid UTF-8 sequences replaced with `U+FFFD`,
+/// e.g. `row.get::<Lossy<String>, _>("payload")`.
+///
+/// Plain `String` and `&str` fail on invalid UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Lossy<T>(pub T);
+
+impl<T> Lossy<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'a> FromSql<'a> for Lossy<Cow<'a, str>> {
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        value.as_str_lossy().map(Lossy)
+    }
+}
+
+impl<'a> FromSql<'a> for Lossy<String> {
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        value.as_str_lossy().map(|s| Lossy(s.into_owned()))
+    }
+}
+
+/// The bytes of a `String` or `FixedString` value of exactly `N` bytes, like hashes and
+/// addresses stored as `FixedString(32)` or `FixedString(20)`.
+impl<'a, const N: usize> FromSql<'a> for [u8; N] {
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        let bytes = value.as_bytes()?;
+        bytes.try_into().map_err(|_| {
+            let from = SqlType::from(value.clone());
+            Error::FromSql(FromSqlError::InvalidType {
+                src: format!("{} of {} bytes", from, bytes.len()).into(),
+                dst: format!("[u8; {}]", N).into(),
+            })
+        })
+    }
+}
+
 macro_rules! from_sql_vec_impl {
     ( $( $t:ty: $k:pat => $f:expr ),* ) => {
         $(
             impl<'a> FromSql<'a> for Vec<$t> {
                 fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
                     match value {
+                        ValueRef::Array(SqlType::Nothing, _) => Ok(Vec::new()),
                         ValueRef::Array($k, vs) => {
                             let f: fn(ValueRef<'a>) -> FromSqlResult<$t> = $f;
                             let mut result = Vec::with_capacity(vs.len());
@@ -161,6 +267,7 @@ from_sql_vec_impl! {
 impl<'a> FromSql<'a> for Vec<u8> {
     fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
         match value {
+            ValueRef::Array(SqlType::Nothing, _) => Ok(Vec::new()),
             ValueRef::Array(SqlType::UInt8, vs) => {
                 let mut result = Vec::with_capacity(vs.len());
                 for v in vs.iter() {
@@ -179,6 +286,7 @@ macro_rules! from_sql_vec_impl {
             impl<'a> FromSql<'a> for Vec<$t> {
                 fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
                     match value {
+                        ValueRef::Array(SqlType::Nothing, _) => Ok(Vec::new()),
                         ValueRef::Array(SqlType::$k, vs) => {
                             let mut result = Vec::with_capacity(vs.len());
                             for v in vs.iter() {
@@ -239,6 +347,138 @@ where
     }
 }
 
+impl<'a, K, V> FromSql<'a> for HashMap<K, V>
+where
+    K: FromSql<'a> + Eq + Hash,
+    V: FromSql<'a>,
+{
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Map(_, _, entries) => {
+                let mut result = HashMap::with_capacity(entries.len());
+                for (k, v) in entries.iter() {
+                    result.insert(K::from_sql(k.clone())?, V::from_sql(v.clone())?);
+                }
+                Ok(result)
+            }
+            _ => {
+                let from = SqlType::from(value.clone()).to_string();
+                Err(Error::FromSql(FromSqlError::InvalidType {
+                    src: from,
+                    dst: "HashMap".into(),
+                }))
+            }
+        }
+    }
+}
+
+impl<'a, K, V> FromSql<'a> for Vec<(K, V)>
+where
+    K: FromSql<'a>,
+    V: FromSql<'a>,
+{
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Map(_, _, entries) => {
+                let mut result = Vec::with_capacity(entries.len());
+                for (k, v) in entries.iter() {
+                    result.push((K::from_sql(k.clone())?, V::from_sql(v.clone())?));
+                }
+                Ok(result)
+            }
+            ValueRef::Array(_, vs) => vs.iter().map(|v| <(K, V)>::from_sql(v.clone())).collect(),
+            _ => {
+                let from = SqlType::from(value.clone()).to_string();
+                Err(Error::FromSql(FromSqlError::InvalidType {
+                    src: from,
+                    dst: "Vec<(K, V)>".into(),
+                }))
+            }
+        }
+    }
+}
+
+/// Any value as JSON: arrays and tuples become arrays, maps become objects and NULL
+/// becomes `null`. `UInt64`, `Int64`, `Int256` and `UInt256` are written as strings,
+/// since JSON numbers lose precision beyond 2^53.
+#[cfg(feature = "serde")]
+impl<'a> FromSql<'a> for serde_json::Value {
+    fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        use serde_json::Value as Json;
+
+        Ok(match value {
+            ValueRef::Bool(v) => Json::Bool(v),
+            ValueRef::UInt8(v) => v.into(),
+            ValueRef::UInt16(v) => v.into(),
+            ValueRef::UInt32(v) => v.into(),
+            ValueRef::Int8(v) => v.into(),
+            ValueRef::Int16(v) => v.into(),
+            ValueRef::Int32(v) => v.into(),
+            ValueRef::UInt64(v) => Json::String(v.to_string()),
+            ValueRef::Int64(v) => Json::String(v.to_string()),
+            ValueRef::Int256(v) => Json::String(v.to_string()),
+            ValueRef::UInt256(v) => Json::String(v.to_string()),
+            // NaN and infinities have no JSON representation.
+            ValueRef::Float32(v) => {
+                serde_json::Number::from_f64(f64::from(v)).map_or(Json::Null, Json::Number)
+            }
+            ValueRef::Float64(v) => {
+                serde_json::Number::from_f64(v).map_or(Json::Null, Json::Number)
+            }
+            ValueRef::String(v) => Json::String(String::from_utf8_lossy(v).into_owned()),
+            ValueRef::Nullable(Either::Left(_)) => Json::Null,
+            ValueRef::Nullable(Either::Right(v)) => Json::from_sql(*v)?,
+            ValueRef::Array(_, vs) | ValueRef::Tuple(vs) => Json::Array(
+                vs.iter()
+                    .map(|v| Json::from_sql(v.clone()))
+                    .collect::<Result<_>>()?,
+            ),
+            ValueRef::Map(_, _, entries) => {
+                let mut object = serde_json::Map::with_capacity(entries.len());
+                for (k, v) in entries.iter() {
+                    let key = match k {
+                        ValueRef::String(k) => String::from_utf8_lossy(k).into_owned(),
+                        k => k.to_string(),
+                    };
+                    object.insert(key, Json::from_sql(v.clone())?);
+                }
+                Json::Object(object)
+            }
+        })
+    }
+}
+
+macro_rules! from_sql_tuple_impl {
+    ( $( $n:literal => ( $( $t:ident: $i:tt ),+ ) ),* ) => {
+        $(
+            impl<'a, $( $t ),+> FromSql<'a> for ( $( $t, )+ )
+            where
+                $( $t: FromSql<'a> ),+
+            {
+                fn from_sql(value: ValueRef<'a>) -> FromSqlResult<Self> {
+                    match value {
+                        ValueRef::Tuple(ref vs) if vs.len() == $n => {
+                            Ok(( $( $t::from_sql(vs[$i].clone())?, )+ ))
+                        }
+                        _ => {
+                            let from = SqlType::from(value.clone()).to_string();
+                            Err(Error::FromSql(FromSqlError::InvalidType {
+                                src: from,
+                                dst: stringify!(( $( $t ),+ )).into(),
+                            }))
+                        }
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_sql_tuple_impl! {
+    2 => (A: 0, B: 1),
+    3 => (A: 0, B: 1, C: 2)
+}
+
 from_sql_impl! {
     u8: UInt8,
     u16: UInt16,
@@ -250,6 +490,7 @@ from_sql_impl! {
     i32: Int32,
     i64: Int64,
     I256: Int256,
+    U256: UInt256,
 
     f32: Float32,
     f64: Float64
@@ -257,7 +498,7 @@ from_sql_impl! {
 
 #[cfg(test)]
 mod test {
-    use crate::types::sql_trait::FromSql;
+    use crate::types::sql_trait::{FromSql, Lossy, Trimmed};
     use crate::value::value_ref::ValueRef;
 
     #[test]
@@ -267,6 +508,119 @@ mod test {
         assert_eq!(actual, 42_u8);
     }
 
+    #[test]
+    fn test_bool() {
+        assert!(bool::from_sql(ValueRef::Bool(true)).unwrap());
+        assert!(bool::from_sql(ValueRef::from(2_u8)).unwrap());
+        assert!(!bool::from_sql(ValueRef::from(0_u8)).unwrap());
+        assert!(bool::from_sql(ValueRef::from(1_u16)).is_err());
+    }
+
+    #[test]
+    fn test_byte_array() {
+        let hash = [7_u8; 32];
+        let actual = <[u8; 32]>::from_sql(ValueRef::String(&hash)).unwrap();
+        assert_eq!(actual, hash);
+
+        let actual = <[u8; 20]>::from_sql(ValueRef::String(&hash[..20])).unwrap();
+        assert_eq!(actual, [7_u8; 20]);
+
+        let err = <[u8; 16]>::from_sql(ValueRef::String(&hash)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "From SQL error: `SqlType::String of 32 bytes cannot be cast to [u8; 16].`"
+        );
+        assert!(<[u8; 4]>::from_sql(ValueRef::UInt32(1)).is_err());
+    }
+
+    #[test]
+    fn test_u256() {
+        let v = ethnum::U256::from_words(1, 2);
+        assert_eq!(ethnum::U256::from_sql(ValueRef::from(v)).unwrap(), v);
+
+        let actual = ethereum_types::U256::from_sql(ValueRef::from(v)).unwrap();
+        assert_eq!(actual, (ethereum_types::U256::from(1) << 128) + 2);
+
+        assert!(ethnum::U256::from_sql(ValueRef::from(1_u64)).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        use either::Either;
+        use serde_json::json;
+
+        let value = ValueRef::Tuple(Arc::new(vec![
+            ValueRef::UInt8(1),
+            ValueRef::UInt64(u64::MAX),
+            ValueRef::Float64(0.5),
+            ValueRef::String(b"abc"),
+            ValueRef::Nullable(Either::Left(SqlType::Int32.into())),
+            ValueRef::Array(
+                SqlType::Int32.into(),
+                Arc::new(vec![ValueRef::Int32(-1), ValueRef::Int32(2)]),
+            ),
+            ValueRef::Map(
+                SqlType::String.into(),
+                SqlType::Bool.into(),
+                Arc::new(vec![(ValueRef::String(b"k"), ValueRef::Bool(true))]),
+            ),
+        ]));
+
+        let actual = serde_json::Value::from_sql(value).unwrap();
+        assert_eq!(
+            actual,
+            json!([1, "18446744073709551615", 0.5, "abc", null, [-1, 2], {"k": true}])
+        );
+
+        let actual = serde_json::Value::from_sql(ValueRef::Float32(f32::NAN)).unwrap();
+        assert_eq!(actual, serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_naive_date_time() {
+        let actual = chrono::NaiveDateTime::from_sql(ValueRef::from(1_600_000_000_u32)).unwrap();
+        assert_eq!(actual.to_string(), "2020-09-13 12:26:40");
+
+        let actual = chrono::NaiveDateTime::from_sql(ValueRef::from(u32::MAX)).unwrap();
+        assert_eq!(actual.to_string(), "2106-02-07 06:28:15");
+
+        let actual = chrono::NaiveDateTime::from_sql(ValueRef::from(1_u64 << 32)).unwrap();
+        assert_eq!(actual.to_string(), "2106-02-07 06:28:16");
+
+        assert!(chrono::NaiveDateTime::from_sql(ValueRef::from(i64::MAX)).is_err());
+        assert!(chrono::NaiveDateTime::from_sql(ValueRef::from(u64::MAX)).is_err());
+        assert!(chrono::NaiveDateTime::from_sql(ValueRef::from(1_u16)).is_err());
+    }
+
+    #[test]
+    fn test_lossy() {
+        let bytes: &[u8] = b"ab\xffc";
+        assert!(String::from_sql(ValueRef::String(bytes)).is_err());
+
+        let actual = Lossy::<String>::from_sql(ValueRef::String(bytes)).unwrap();
+        assert_eq!(actual.into_inner(), "ab\u{fffd}c");
+
+        let actual = Lossy::<std::borrow::Cow<str>>::from_sql(ValueRef::String(b"abc")).unwrap();
+        assert!(matches!(actual.0, std::borrow::Cow::Borrowed("abc")));
+
+        let v = ValueRef::Nullable(either::Either::Right(Box::new(ValueRef::String(bytes))));
+        let actual = Option::<Lossy<String>>::from_sql(v).unwrap();
+        assert_eq!(actual, Some(Lossy("ab\u{fffd}c".to_string())));
+
+        assert!(Lossy::<String>::from_sql(ValueRef::UInt8(1)).is_err());
+    }
+
+    #[test]
+    fn test_trimmed() {
+        let actual = Trimmed::<String>::from_sql(ValueRef::String(b"abc\0\0")).unwrap();
+        assert_eq!(actual.into_inner(), "abc");
+
+        let v = ValueRef::Nullable(either::Either::Right(Box::new(ValueRef::String(b"a\0"))));
+        let actual = Option::<Trimmed<&str>>::from_sql(v).unwrap();
+        assert_eq!(actual, Some(Trimmed("a")));
+    }
+
     #[test]
     fn test_bad_convert() {
         let v = ValueRef::from(42_u16);
@@ -1,7 +1,10 @@
 use std::{borrow::Cow, collections::HashMap, fmt, pin::Pin, sync::Mutex};
 
+use crate::{column::factory, error::Result};
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum SqlType {
+    Bool,
     UInt8,
     UInt16,
     UInt32,
@@ -11,12 +14,18 @@ pub enum SqlType {
     Int32,
     Int64,
     Int256,
+    UInt256,
     String,
     FixedString(usize),
     Float32,
     Float64,
+    /// Type of `NULL` and of the items of `[]`, it has no values.
+    Nothing,
     Nullable(&'static SqlType),
     Array(&'static SqlType),
+    Map(&'static SqlType, &'static SqlType),
+    Tuple(Vec<SqlType>),
+    LowCardinality(&'static SqlType),
 }
 
 lazy_static::lazy_static! {
@@ -26,6 +35,7 @@ lazy_static::lazy_static! {
 impl From<SqlType> for &'static SqlType {
     fn from(value: SqlType) -> Self {
         match value {
+            SqlType::Bool => &SqlType::Bool,
             SqlType::UInt8 => &SqlType::UInt8,
             SqlType::UInt16 => &SqlType::UInt16,
             SqlType::UInt32 => &SqlType::UInt32,
@@ -35,9 +45,11 @@ impl From<SqlType> for &'static SqlType {
             SqlType::Int32 => &SqlType::Int32,
             SqlType::Int64 => &SqlType::Int64,
             SqlType::Int256 => &SqlType::Int256,
+            SqlType::UInt256 => &SqlType::UInt256,
             SqlType::String => &SqlType::String,
             SqlType::Float32 => &SqlType::Float32,
             SqlType::Float64 => &SqlType::Float64,
+            SqlType::Nothing => &SqlType::Nothing,
             _ => {
                 let mut guard = TYPES_CACHE.lock().unwrap();
                 loop {
@@ -52,8 +64,16 @@ impl From<SqlType> for &'static SqlType {
 }
 
 impl SqlType {
+    /// Parses a type name like `Nullable(Array(UInt32))`, the inverse of `to_string`.
+    ///
+    /// Aliases such as `BigInt` or `Text` are accepted as the server accepts them.
+    pub fn from_type_name(type_name: &str) -> Result<SqlType> {
+        factory::parse_sql_type(type_name)
+    }
+
     pub fn to_string(&self) -> Cow<'static, str> {
         match self.clone() {
+            SqlType::Bool => "Bool".into(),
             SqlType::UInt8 => "UInt8".into(),
             SqlType::UInt16 => "UInt16".into(),
             SqlType::UInt32 => "UInt32".into(),
@@ -63,12 +83,63 @@ impl SqlType {
             SqlType::Int32 => "Int32".into(),
             SqlType::Int64 => "Int64".into(),
             SqlType::Int256 => "Int256".into(),
+            SqlType::UInt256 => "UInt256".into(),
             SqlType::String => "String".into(),
             SqlType::FixedString(str_len) => format!("FixedString({})", str_len).into(),
             SqlType::Float32 => "Float32".into(),
             SqlType::Float64 => "Float64".into(),
+            SqlType::Nothing => "Nothing".into(),
             SqlType::Nullable(nested) => format!("Nullable({})", &nested).into(),
             SqlType::Array(nested) => format!("Array({})", &nested).into(),
+            SqlType::Map(key, value) => format!("Map({}, {})", &key, &value).into(),
+            SqlType::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| t.to_string().into()).collect();
+                format!("Tuple({})", types.join(", ")).into()
+            }
+            SqlType::LowCardinality(nested) => format!("LowCardinality({})", &nested).into(),
+        }
+    }
+
+    /// Returns `true` for `Nullable(T)`.
+    pub fn is_nullable(&self) -> bool {
+        matches!(self, SqlType::Nullable(_))
+    }
+
+    /// Returns `T` for `Nullable(T)` and the type itself otherwise.
+    pub fn unwrap_nullable(&self) -> &SqlType {
+        match self {
+            SqlType::Nullable(inner) => inner,
+            sql_type => sql_type,
+        }
+    }
+
+    /// Returns `T` for `Nullable(T)` and `Array(T)`, one level at a time, and `None`
+    /// for other types. `Nullable(Array(T))` gives `Array(T)`.
+    pub fn inner_type(&self) -> Option<&SqlType> {
+        match self {
+            SqlType::Nullable(inner) | SqlType::Array(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    /// Size in bytes of one value of a fixed-width type as sent over the wire, `None`
+    /// for `String`, `Array`, `Map`, `Nullable`, `LowCardinality` and `Tuple`.
+    ///
+    /// `Nothing` takes one placeholder byte per row.
+    pub fn fixed_width_bytes(&self) -> Option<usize> {
+        match self {
+            SqlType::Bool | SqlType::UInt8 | SqlType::Int8 | SqlType::Nothing => Some(1),
+            SqlType::UInt16 | SqlType::Int16 => Some(2),
+            SqlType::UInt32 | SqlType::Int32 | SqlType::Float32 => Some(4),
+            SqlType::UInt64 | SqlType::Int64 | SqlType::Float64 => Some(8),
+            SqlType::Int256 | SqlType::UInt256 => Some(32),
+            SqlType::FixedString(len) => Some(*len),
+            SqlType::String
+            | SqlType::Array(_)
+            | SqlType::Map(_, _)
+            | SqlType::Nullable(_)
+            | SqlType::LowCardinality(_)
+            | SqlType::Tuple(_) => None,
         }
     }
 
@@ -76,6 +147,7 @@ impl SqlType {
         match self {
             SqlType::Nullable(inner) => 1 + inner.level(),
             SqlType::Array(inner) => 1 + inner.level(),
+            SqlType::LowCardinality(inner) => inner.level(),
             _ => 0,
         }
     }
@@ -86,3 +158,46 @@ impl fmt::Display for SqlType {
         write!(f, "{}", Self::to_string(self))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nullable_helpers() {
+        let array = SqlType::Array(SqlType::UInt32.into());
+        let nullable_array = SqlType::Nullable(array.clone().into());
+
+        assert!(nullable_array.is_nullable());
+        assert!(!array.is_nullable());
+
+        assert_eq!(nullable_array.unwrap_nullable(), &array);
+        assert_eq!(array.unwrap_nullable(), &array);
+
+        assert_eq!(nullable_array.inner_type(), Some(&array));
+        assert_eq!(array.inner_type(), Some(&SqlType::UInt32));
+        assert_eq!(SqlType::UInt32.inner_type(), None);
+        assert_eq!(
+            SqlType::LowCardinality(SqlType::String.into()).inner_type(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fixed_width_bytes() {
+        assert_eq!(SqlType::UInt8.fixed_width_bytes(), Some(1));
+        assert_eq!(SqlType::Int64.fixed_width_bytes(), Some(8));
+        assert_eq!(SqlType::UInt256.fixed_width_bytes(), Some(32));
+        assert_eq!(SqlType::FixedString(16).fixed_width_bytes(), Some(16));
+
+        assert_eq!(SqlType::String.fixed_width_bytes(), None);
+        assert_eq!(
+            SqlType::Array(SqlType::UInt8.into()).fixed_width_bytes(),
+            None
+        );
+        assert_eq!(
+            SqlType::Nullable(SqlType::UInt8.into()).fixed_width_bytes(),
+            None
+        );
+    }
+}
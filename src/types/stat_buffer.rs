@@ -1,5 +1,5 @@
 use crate::types::sql_type::SqlType;
-use ethnum::I256;
+use ethnum::{I256, U256};
 
 pub trait StatBuffer {
     type Buffer: AsMut<[u8]> + AsRef<[u8]> + Copy + Sync;
@@ -115,6 +115,18 @@ impl StatBuffer for I256 {
     }
 }
 
+impl StatBuffer for U256 {
+    type Buffer = [u8; 32];
+
+    fn buffer() -> Self::Buffer {
+        [0; 32]
+    }
+
+    fn sql_type() -> SqlType {
+        SqlType::UInt256
+    }
+}
+
 impl StatBuffer for f32 {
     type Buffer = [u8; 4];
 
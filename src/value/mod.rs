@@ -1,6 +1,53 @@
+use std::fmt;
+
 pub use value::Value;
 pub use value_ref::ValueRef;
 
 #[allow(clippy::module_inception)]
 pub mod value;
 pub mod value_ref;
+
+/// Float values as `Value` and `ValueRef` compare and hash them: `-0.0` is `0.0`
+/// and every NaN is the same NaN, equal to itself and greater than any number.
+/// This keeps `==`, `cmp` and `hash` in agreement.
+pub(crate) fn canonical_f32(v: f32) -> f32 {
+    if v == 0.0 {
+        0.0
+    } else if v.is_nan() {
+        f32::NAN
+    } else {
+        v
+    }
+}
+
+/// See `canonical_f32`.
+pub(crate) fn canonical_f64(v: f64) -> f64 {
+    if v == 0.0 {
+        0.0
+    } else if v.is_nan() {
+        f64::NAN
+    } else {
+        v
+    }
+}
+
+/// Bytes of binary strings shown by `Display`, the rest is elided.
+const BINARY_PREFIX_LEN: usize = 8;
+
+/// Writes a string which is not valid UTF-8 as a hex prefix and its length,
+/// e.g. `0x1f8b0800…(1024 bytes)`, so blobs keep tables readable.
+pub(crate) fn fmt_binary(bytes: &[u8], f: &mut fmt::Formatter) -> fmt::Result {
+    let prefix = &bytes[..bytes.len().min(BINARY_PREFIX_LEN)];
+
+    let mut text = String::with_capacity(2 * prefix.len() + 16);
+    text.push_str("0x");
+    for byte in prefix {
+        text.push_str(&format!("{:02x}", byte));
+    }
+    if prefix.len() < bytes.len() {
+        text.push('\u{2026}');
+    }
+    text.push_str(&format!("({} bytes)", bytes.len()));
+
+    f.pad(&text)
+}
@@ -1,14 +1,20 @@
+use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 use std::{convert, fmt, mem, str, sync::Arc};
 
 use either::Either;
-use ethnum::I256;
+use ethnum::{I256, U256};
 
-use crate::types::{HasSqlType, SqlType};
+use crate::{
+    error::{Error, FromSqlError, Result},
+    types::{FromSql, HasSqlType, SqlType},
+    value::{canonical_f32, canonical_f64, ValueRef},
+};
 
 /// Client side representation of a value of Clickhouse column.
 #[derive(Clone, Debug)]
 pub enum Value {
+    Bool(bool),
     UInt8(u8),
     UInt16(u16),
     UInt32(u32),
@@ -18,28 +24,19 @@ pub enum Value {
     Int32(i32),
     Int64(i64),
     Int256(I256),
+    UInt256(U256),
     String(Arc<Vec<u8>>),
     Float32(f32),
     Float64(f64),
     Nullable(Either<&'static SqlType, Box<Value>>),
     Array(&'static SqlType, Arc<Vec<Value>>),
+    Map(&'static SqlType, &'static SqlType, Arc<Vec<(Value, Value)>>),
+    Tuple(Arc<Vec<Value>>),
 }
 
 impl Hash for Value {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        match self {
-            Self::String(s) => s.hash(state),
-            Self::Int8(i) => i.hash(state),
-            Self::Int16(i) => i.hash(state),
-            Self::Int32(i) => i.hash(state),
-            Self::Int64(i) => i.hash(state),
-            Self::Int256(i) => i.hash(state),
-            Self::UInt8(i) => i.hash(state),
-            Self::UInt16(i) => i.hash(state),
-            Self::UInt32(i) => i.hash(state),
-            Self::UInt64(i) => i.hash(state),
-            _ => unimplemented!(),
-        }
+        ValueRef::from(self).hash(state)
     }
 }
 
@@ -48,6 +45,7 @@ impl Eq for Value {}
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => *a == *b,
             (Value::UInt8(a), Value::UInt8(b)) => *a == *b,
             (Value::UInt16(a), Value::UInt16(b)) => *a == *b,
             (Value::UInt32(a), Value::UInt32(b)) => *a == *b,
@@ -56,19 +54,76 @@ impl PartialEq for Value {
             (Value::Int16(a), Value::Int16(b)) => *a == *b,
             (Value::Int32(a), Value::Int32(b)) => *a == *b,
             (Value::Int64(a), Value::Int64(b)) => *a == *b,
+            (Value::Int256(a), Value::Int256(b)) => *a == *b,
+            (Value::UInt256(a), Value::UInt256(b)) => *a == *b,
             (Value::String(a), Value::String(b)) => *a == *b,
-            (Value::Float32(a), Value::Float32(b)) => *a == *b,
-            (Value::Float64(a), Value::Float64(b)) => *a == *b,
+            (Value::Float32(a), Value::Float32(b)) => {
+                canonical_f32(*a).to_bits() == canonical_f32(*b).to_bits()
+            }
+            (Value::Float64(a), Value::Float64(b)) => {
+                canonical_f64(*a).to_bits() == canonical_f64(*b).to_bits()
+            }
             (Value::Nullable(a), Value::Nullable(b)) => *a == *b,
             (Value::Array(ta, a), Value::Array(tb, b)) => *ta == *tb && *a == *b,
+            (Value::Map(ka, va, a), Value::Map(kb, vb, b)) => *ka == *kb && *va == *vb && *a == *b,
+            (Value::Tuple(a), Value::Tuple(b)) => *a == *b,
             _ => false,
         }
     }
 }
 
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Same order as of `ValueRef`.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        ValueRef::from(self).cmp(&ValueRef::from(other))
+    }
+}
+
 impl Value {
+    /// Builds an `Array(elem_type)` value, failing on the first item of another type.
+    ///
+    /// `LowCardinality(T)` items are plain `T` values and `FixedString(N)` items are
+    /// strings of at most `N` bytes.
+    pub fn array(elem_type: SqlType, items: Vec<Value>) -> Result<Value> {
+        let item_type = match &elem_type {
+            SqlType::LowCardinality(inner) => (*inner).clone(),
+            sql_type => sql_type.clone(),
+        };
+
+        for item in &items {
+            let src = SqlType::from(item.clone());
+            let matches = match (&item_type, item) {
+                (SqlType::FixedString(len), Value::String(bytes)) => bytes.len() <= *len,
+                _ => src == item_type,
+            };
+            if !matches {
+                return Err(Error::FromSql(FromSqlError::InvalidType {
+                    src: src.to_string(),
+                    dst: item_type.to_string(),
+                }));
+            }
+        }
+
+        Ok(Value::Array(elem_type.into(), Arc::new(items)))
+    }
+
+    /// Converts the value to `T`, failing with `FromSqlError::InvalidType` on a type
+    /// mismatch. Prefer it over the `From<Value>` impls, which panic.
+    ///
+    /// `TryFrom` can't be used for this: the `From` impls already provide an infallible one.
+    pub fn get<'a, T: FromSql<'a>>(&'a self) -> Result<T> {
+        T::from_sql(ValueRef::from(self))
+    }
+
     pub(crate) fn default(sql_type: SqlType) -> Value {
         match sql_type {
+            SqlType::Bool => Value::Bool(false),
             SqlType::UInt8 => Value::UInt8(0),
             SqlType::UInt16 => Value::UInt16(0),
             SqlType::UInt32 => Value::UInt32(0),
@@ -78,12 +133,19 @@ impl Value {
             SqlType::Int32 => Value::Int32(0),
             SqlType::Int64 => Value::Int64(0),
             SqlType::Int256 => Value::Int256(Default::default()),
+            SqlType::UInt256 => Value::UInt256(Default::default()),
             SqlType::String => Value::String(Arc::new(Vec::default())),
             SqlType::FixedString(str_len) => Value::String(Arc::new(vec![0_u8; str_len])),
             SqlType::Float32 => Value::Float32(0.0),
             SqlType::Float64 => Value::Float64(0.0),
+            SqlType::Nothing => Value::Nullable(Either::Left(SqlType::Nothing.into())),
             SqlType::Nullable(inner) => Value::Nullable(Either::Left(inner)),
             SqlType::Array(inner) => Value::Array(inner, Arc::new(Vec::default())),
+            SqlType::Map(key, value) => Value::Map(key, value, Arc::new(Vec::default())),
+            SqlType::Tuple(types) => {
+                Value::Tuple(Arc::new(types.into_iter().map(Value::default).collect()))
+            }
+            SqlType::LowCardinality(inner) => Value::default(inner.clone()),
         }
     }
 }
@@ -91,6 +153,7 @@ impl Value {
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Value::Bool(ref v) => fmt::Display::fmt(v, f),
             Value::UInt8(ref v) => fmt::Display::fmt(v, f),
             Value::UInt16(ref v) => fmt::Display::fmt(v, f),
             Value::UInt32(ref v) => fmt::Display::fmt(v, f),
@@ -100,9 +163,10 @@ impl fmt::Display for Value {
             Value::Int32(ref v) => fmt::Display::fmt(v, f),
             Value::Int64(ref v) => fmt::Display::fmt(v, f),
             Value::Int256(ref v) => fmt::Display::fmt(v, f),
+            Value::UInt256(ref v) => fmt::Display::fmt(v, f),
             Value::String(ref v) => match str::from_utf8(v) {
                 Ok(s) => fmt::Display::fmt(s, f),
-                Err(_) => write!(f, "{:?}", v),
+                Err(_) => super::fmt_binary(v, f),
             },
             Value::Float32(ref v) => fmt::Display::fmt(v, f),
             Value::Float64(ref v) => fmt::Display::fmt(v, f),
@@ -114,6 +178,17 @@ impl fmt::Display for Value {
                 let cells: Vec<String> = vs.iter().map(|v| format!("{}", v)).collect();
                 write!(f, "[{}]", cells.join(", "))
             }
+            Value::Map(_, _, entries) => {
+                let cells: Vec<String> = entries
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .collect();
+                write!(f, "{{{}}}", cells.join(", "))
+            }
+            Value::Tuple(vs) => {
+                let cells: Vec<String> = vs.iter().map(|v| format!("{}", v)).collect();
+                write!(f, "({})", cells.join(", "))
+            }
         }
     }
 }
@@ -121,6 +196,7 @@ impl fmt::Display for Value {
 impl From<Value> for SqlType {
     fn from(source: Value) -> Self {
         match source {
+            Value::Bool(_) => SqlType::Bool,
             Value::UInt8(_) => SqlType::UInt8,
             Value::UInt16(_) => SqlType::UInt16,
             Value::UInt32(_) => SqlType::UInt32,
@@ -130,6 +206,7 @@ impl From<Value> for SqlType {
             Value::Int32(_) => SqlType::Int32,
             Value::Int64(_) => SqlType::Int64,
             Value::Int256(_) => SqlType::Int256,
+            Value::UInt256(_) => SqlType::UInt256,
             Value::String(_) => SqlType::String,
             Value::Float32(_) => SqlType::Float32,
             Value::Float64(_) => SqlType::Float64,
@@ -141,6 +218,8 @@ impl From<Value> for SqlType {
                 }
             },
             Value::Array(t, _) => SqlType::Array(t),
+            Value::Map(k, v, _) => SqlType::Map(k, v),
+            Value::Tuple(vs) => SqlType::Tuple(vs.iter().cloned().map(SqlType::from).collect()),
         }
     }
 }
@@ -215,6 +294,15 @@ impl From<Vec<String>> for Value {
     }
 }
 
+impl From<Vec<bool>> for Value {
+    fn from(v: Vec<bool>) -> Self {
+        Value::Array(
+            SqlType::UInt8.into(),
+            Arc::new(v.into_iter().map(|b| b.into()).collect()),
+        )
+    }
+}
+
 impl From<bool> for Value {
     fn from(v: bool) -> Value {
         Value::UInt8(u8::from(v))
@@ -232,6 +320,7 @@ value_from! {
     i32: Int32,
     i64: Int64,
     I256: Int256,
+    U256: UInt256,
 
     f32: Float32,
     f64: Float64
@@ -247,6 +336,7 @@ value_array_from! {
     i32: Int32,
     i64: Int64,
     I256: Int256,
+    U256: UInt256,
 
     f32: Float32,
     f64: Float64
@@ -288,6 +378,7 @@ impl From<Value> for Vec<u8> {
 macro_rules! from_value {
     ( $( $t:ty : $k:ident ),* ) => {
         $(
+            /// Panics on a type mismatch, see `Value::get`.
             impl convert::From<Value> for $t {
                 fn from(v: Value) -> $t {
                     if let Value::$k(x) = v {
@@ -311,6 +402,72 @@ from_value! {
     i32: Int32,
     i64: Int64,
     I256: Int256,
+    U256: UInt256,
     f32: Float32,
     f64: Float64
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let value = Value::from(42_u32);
+        assert_eq!(value.get::<u32>().unwrap(), 42);
+
+        let err = value.get::<u64>().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "From SQL error: `SqlType::UInt32 cannot be cast to u64.`"
+        );
+
+        let value = Value::from("foo");
+        assert_eq!(value.get::<&str>().unwrap(), "foo");
+        assert!(value.get::<f64>().is_err());
+
+        let value = Value::from(None::<u8>);
+        assert_eq!(value.get::<Option<u8>>().unwrap(), None);
+    }
+
+    #[test]
+    fn test_array() {
+        let value = Value::array(SqlType::UInt32, vec![1_u32.into(), 2_u32.into()]).unwrap();
+        assert_eq!(value, Value::from(vec![1_u32, 2]));
+        assert_eq!(SqlType::from(value.clone()).to_string(), "Array(UInt32)");
+
+        let nested = Value::array(
+            SqlType::Array(SqlType::UInt32.into()),
+            vec![value, Value::from(Vec::<u32>::new())],
+        )
+        .unwrap();
+        assert_eq!(nested.to_string(), "[[1, 2], []]");
+
+        let nullable = Value::array(
+            SqlType::Nullable(SqlType::String.into()),
+            vec![Some("a").into(), None::<&str>.into()],
+        )
+        .unwrap();
+        assert_eq!(nullable.to_string(), "[a, NULL]");
+
+        let bools = Value::array(SqlType::UInt8, vec![true.into(), false.into()]).unwrap();
+        assert_eq!(bools, Value::from(vec![true, false]));
+
+        assert!(Value::array(
+            SqlType::LowCardinality(SqlType::String.into()),
+            vec!["a".into()]
+        )
+        .is_ok());
+        assert!(Value::array(SqlType::FixedString(2), vec!["ab".into()]).is_ok());
+        assert!(Value::array(SqlType::FixedString(2), vec!["abc".into()]).is_err());
+    }
+
+    #[test]
+    fn test_array_heterogeneous() {
+        let err = Value::array(SqlType::UInt32, vec![1_u32.into(), 1_u64.into()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "From SQL error: `SqlType::UInt64 cannot be cast to UInt32.`"
+        );
+    }
+}
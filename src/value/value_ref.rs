@@ -1,14 +1,20 @@
+use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
-use std::{convert, fmt, str, sync::Arc};
+use std::{borrow::Cow, convert, fmt, mem, str, sync::Arc};
 
 use either::Either;
-use ethnum::I256;
+use ethnum::{I256, U256};
 
 use crate::error::{Error, FromSqlError};
-use crate::{error::Result, types::SqlType, value::Value};
+use crate::{
+    error::Result,
+    types::{FromSql, SqlType},
+    value::{canonical_f32, canonical_f64, Value},
+};
 
 #[derive(Clone, Debug)]
 pub enum ValueRef<'a> {
+    Bool(bool),
     UInt8(u8),
     UInt16(u16),
     UInt32(u32),
@@ -18,27 +24,46 @@ pub enum ValueRef<'a> {
     Int32(i32),
     Int64(i64),
     Int256(I256),
+    UInt256(U256),
     String(&'a [u8]),
     Float32(f32),
     Float64(f64),
     Nullable(Either<&'static SqlType, Box<ValueRef<'a>>>),
     Array(&'static SqlType, Arc<Vec<ValueRef<'a>>>),
+    Map(
+        &'static SqlType,
+        &'static SqlType,
+        Arc<Vec<(ValueRef<'a>, ValueRef<'a>)>>,
+    ),
+    Tuple(Arc<Vec<ValueRef<'a>>>),
 }
 
 impl<'a> Hash for ValueRef<'a> {
     fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
         match self {
+            Self::Bool(b) => b.hash(state),
             Self::String(s) => s.hash(state),
             Self::Int8(i) => i.hash(state),
             Self::Int16(i) => i.hash(state),
             Self::Int32(i) => i.hash(state),
             Self::Int64(i) => i.hash(state),
             Self::Int256(i) => i.hash(state),
+            Self::UInt256(i) => i.hash(state),
             Self::UInt8(i) => i.hash(state),
             Self::UInt16(i) => i.hash(state),
             Self::UInt32(i) => i.hash(state),
             Self::UInt64(i) => i.hash(state),
-            _ => unimplemented!(),
+            Self::Float32(f) => canonical_f32(*f).to_bits().hash(state),
+            Self::Float64(f) => canonical_f64(*f).to_bits().hash(state),
+            Self::Nullable(Either::Left(_)) => 0_u8.hash(state),
+            Self::Nullable(Either::Right(v)) => {
+                1_u8.hash(state);
+                v.hash(state);
+            }
+            Self::Array(_, vs) => vs.hash(state),
+            Self::Map(_, _, entries) => entries.hash(state),
+            Self::Tuple(vs) => vs.hash(state),
         }
     }
 }
@@ -48,6 +73,7 @@ impl<'a> Eq for ValueRef<'a> {}
 impl<'a> PartialEq for ValueRef<'a> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+            (ValueRef::Bool(a), ValueRef::Bool(b)) => *a == *b,
             (ValueRef::UInt8(a), ValueRef::UInt8(b)) => *a == *b,
             (ValueRef::UInt16(a), ValueRef::UInt16(b)) => *a == *b,
             (ValueRef::UInt32(a), ValueRef::UInt32(b)) => *a == *b,
@@ -56,19 +82,91 @@ impl<'a> PartialEq for ValueRef<'a> {
             (ValueRef::Int16(a), ValueRef::Int16(b)) => *a == *b,
             (ValueRef::Int32(a), ValueRef::Int32(b)) => *a == *b,
             (ValueRef::Int64(a), ValueRef::Int64(b)) => *a == *b,
+            (ValueRef::Int256(a), ValueRef::Int256(b)) => *a == *b,
+            (ValueRef::UInt256(a), ValueRef::UInt256(b)) => *a == *b,
             (ValueRef::String(a), ValueRef::String(b)) => *a == *b,
-            (ValueRef::Float32(a), ValueRef::Float32(b)) => *a == *b,
-            (ValueRef::Float64(a), ValueRef::Float64(b)) => *a == *b,
+            (ValueRef::Float32(a), ValueRef::Float32(b)) => {
+                canonical_f32(*a).to_bits() == canonical_f32(*b).to_bits()
+            }
+            (ValueRef::Float64(a), ValueRef::Float64(b)) => {
+                canonical_f64(*a).to_bits() == canonical_f64(*b).to_bits()
+            }
             (ValueRef::Nullable(a), ValueRef::Nullable(b)) => *a == *b,
             (ValueRef::Array(ta, a), ValueRef::Array(tb, b)) => *ta == *tb && *a == *b,
+            (ValueRef::Map(ka, va, a), ValueRef::Map(kb, vb, b)) => {
+                *ka == *kb && *va == *vb && *a == *b
+            }
+            (ValueRef::Tuple(a), ValueRef::Tuple(b)) => *a == *b,
             _ => false,
         }
     }
 }
 
+impl<'a> PartialOrd for ValueRef<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Total order of values:
+/// - values of the same type are compared naturally, floats with `total_cmp` after
+///   `-0.0` is turned into `0.0` and NaNs into one NaN, greater than any number;
+/// - NULL is greater than any non-NULL value of a nullable column;
+/// - arrays, maps and tuples are compared lexicographically;
+/// - values of different types are ordered by type, in the order of `ValueRef` variants.
+impl<'a> Ord for ValueRef<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (ValueRef::Bool(a), ValueRef::Bool(b)) => a.cmp(b),
+            (ValueRef::UInt8(a), ValueRef::UInt8(b)) => a.cmp(b),
+            (ValueRef::UInt16(a), ValueRef::UInt16(b)) => a.cmp(b),
+            (ValueRef::UInt32(a), ValueRef::UInt32(b)) => a.cmp(b),
+            (ValueRef::UInt64(a), ValueRef::UInt64(b)) => a.cmp(b),
+            (ValueRef::Int8(a), ValueRef::Int8(b)) => a.cmp(b),
+            (ValueRef::Int16(a), ValueRef::Int16(b)) => a.cmp(b),
+            (ValueRef::Int32(a), ValueRef::Int32(b)) => a.cmp(b),
+            (ValueRef::Int64(a), ValueRef::Int64(b)) => a.cmp(b),
+            (ValueRef::Int256(a), ValueRef::Int256(b)) => a.cmp(b),
+            (ValueRef::UInt256(a), ValueRef::UInt256(b)) => a.cmp(b),
+            (ValueRef::String(a), ValueRef::String(b)) => a.cmp(b),
+            (ValueRef::Float32(a), ValueRef::Float32(b)) => {
+                canonical_f32(*a).total_cmp(&canonical_f32(*b))
+            }
+            (ValueRef::Float64(a), ValueRef::Float64(b)) => {
+                canonical_f64(*a).total_cmp(&canonical_f64(*b))
+            }
+            (ValueRef::Nullable(a), ValueRef::Nullable(b)) => match (a, b) {
+                (Either::Left(ta), Either::Left(tb)) => cmp_types(ta, tb),
+                (Either::Left(_), Either::Right(_)) => Ordering::Greater,
+                (Either::Right(_), Either::Left(_)) => Ordering::Less,
+                (Either::Right(a), Either::Right(b)) => a.cmp(b),
+            },
+            (ValueRef::Array(ta, a), ValueRef::Array(tb, b)) => {
+                a.cmp(b).then_with(|| cmp_types(ta, tb))
+            }
+            (ValueRef::Map(ka, va, a), ValueRef::Map(kb, vb, b)) => a
+                .cmp(b)
+                .then_with(|| cmp_types(ka, kb))
+                .then_with(|| cmp_types(va, vb)),
+            (ValueRef::Tuple(a), ValueRef::Tuple(b)) => a.cmp(b),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+
+/// Keeps the order consistent with `PartialEq`, which distinguishes equal values of different types.
+fn cmp_types(a: &SqlType, b: &SqlType) -> Ordering {
+    if a == b {
+        Ordering::Equal
+    } else {
+        a.to_string().cmp(&b.to_string())
+    }
+}
+
 impl<'a> fmt::Display for ValueRef<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            ValueRef::Bool(v) => fmt::Display::fmt(v, f),
             ValueRef::UInt8(v) => fmt::Display::fmt(v, f),
             ValueRef::UInt16(v) => fmt::Display::fmt(v, f),
             ValueRef::UInt32(v) => fmt::Display::fmt(v, f),
@@ -78,9 +176,10 @@ impl<'a> fmt::Display for ValueRef<'a> {
             ValueRef::Int32(v) => fmt::Display::fmt(v, f),
             ValueRef::Int64(v) => fmt::Display::fmt(v, f),
             ValueRef::Int256(v) => fmt::Display::fmt(v, f),
+            ValueRef::UInt256(v) => fmt::Display::fmt(v, f),
             ValueRef::String(v) => match str::from_utf8(v) {
                 Ok(s) => fmt::Display::fmt(s, f),
-                Err(_) => write!(f, "{:?}", *v),
+                Err(_) => super::fmt_binary(v, f),
             },
             ValueRef::Float32(v) => fmt::Display::fmt(v, f),
             ValueRef::Float64(v) => fmt::Display::fmt(v, f),
@@ -92,6 +191,17 @@ impl<'a> fmt::Display for ValueRef<'a> {
                 let cells: Vec<String> = vs.iter().map(|v| format!("{}", v)).collect();
                 write!(f, "[{}]", cells.join(", "))
             }
+            ValueRef::Map(_, _, entries) => {
+                let cells: Vec<String> = entries
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .collect();
+                write!(f, "{{{}}}", cells.join(", "))
+            }
+            ValueRef::Tuple(vs) => {
+                let cells: Vec<String> = vs.iter().map(|v| format!("{}", v)).collect();
+                write!(f, "({})", cells.join(", "))
+            }
         }
     }
 }
@@ -99,6 +209,7 @@ impl<'a> fmt::Display for ValueRef<'a> {
 impl<'a> convert::From<ValueRef<'a>> for SqlType {
     fn from(source: ValueRef<'a>) -> Self {
         match source {
+            ValueRef::Bool(_) => SqlType::Bool,
             ValueRef::UInt8(_) => SqlType::UInt8,
             ValueRef::UInt16(_) => SqlType::UInt16,
             ValueRef::UInt32(_) => SqlType::UInt32,
@@ -108,6 +219,7 @@ impl<'a> convert::From<ValueRef<'a>> for SqlType {
             ValueRef::Int32(_) => SqlType::Int32,
             ValueRef::Int64(_) => SqlType::Int64,
             ValueRef::Int256(_) => SqlType::Int256,
+            ValueRef::UInt256(_) => SqlType::UInt256,
             ValueRef::String(_) => SqlType::String,
             ValueRef::Float32(_) => SqlType::Float32,
             ValueRef::Float64(_) => SqlType::Float64,
@@ -116,11 +228,42 @@ impl<'a> convert::From<ValueRef<'a>> for SqlType {
                 Either::Right(value_ref) => SqlType::Nullable(SqlType::from(*value_ref).into()),
             },
             ValueRef::Array(t, _) => SqlType::Array(t),
+            ValueRef::Map(k, v, _) => SqlType::Map(k, v),
+            ValueRef::Tuple(vs) => SqlType::Tuple(vs.iter().cloned().map(SqlType::from).collect()),
         }
     }
 }
 
 impl<'a> ValueRef<'a> {
+    fn type_rank(&self) -> u8 {
+        match self {
+            ValueRef::Bool(_) => 0,
+            ValueRef::UInt8(_) => 1,
+            ValueRef::UInt16(_) => 2,
+            ValueRef::UInt32(_) => 3,
+            ValueRef::UInt64(_) => 4,
+            ValueRef::Int8(_) => 5,
+            ValueRef::Int16(_) => 6,
+            ValueRef::Int32(_) => 7,
+            ValueRef::Int64(_) => 8,
+            ValueRef::Int256(_) => 9,
+            ValueRef::UInt256(_) => 10,
+            ValueRef::String(_) => 11,
+            ValueRef::Float32(_) => 12,
+            ValueRef::Float64(_) => 13,
+            ValueRef::Nullable(_) => 14,
+            ValueRef::Array(_, _) => 15,
+            ValueRef::Map(_, _, _) => 16,
+            ValueRef::Tuple(_) => 17,
+        }
+    }
+
+    /// Converts the value to `T`, failing with `FromSqlError::InvalidType` on a type
+    /// mismatch. Prefer it over the `From<ValueRef>` impls, which panic.
+    pub fn get<T: FromSql<'a>>(&self) -> Result<T> {
+        T::from_sql(self.clone())
+    }
+
     pub fn as_str(&self) -> Result<&'a str> {
         if let ValueRef::String(t) = self {
             return Ok(str::from_utf8(t)?);
@@ -132,6 +275,20 @@ impl<'a> ValueRef<'a> {
         }))
     }
 
+    /// Same as `as_str`, but invalid UTF-8 sequences are replaced with `U+FFFD`
+    /// instead of failing.
+    pub fn as_str_lossy(&self) -> Result<Cow<'a, str>> {
+        Ok(String::from_utf8_lossy(self.as_bytes()?))
+    }
+
+    /// Same as `as_str`, without the trailing `\0` bytes which pad `FixedString(N)` values.
+    /// Zero bytes in the middle of the value are kept.
+    pub fn as_str_trimmed(&self) -> Result<&'a str> {
+        let bytes = self.as_bytes()?;
+        let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+        Ok(str::from_utf8(&bytes[..len])?)
+    }
+
     pub fn as_string(&self) -> Result<String> {
         let tmp = self.as_str()?;
         Ok(tmp.to_string())
@@ -147,11 +304,44 @@ impl<'a> ValueRef<'a> {
             dst: "&[u8]".into(),
         }))
     }
+
+    /// Converts any integer or float value to `f64`.
+    ///
+    /// This is lossy: `u64`, `i64`, `Int256` and `UInt256` values beyond 2^53 are rounded.
+    /// NULL yields `FromSqlError::UnexpectedNull`.
+    pub fn as_f64(&self) -> Result<f64> {
+        match self {
+            ValueRef::UInt8(v) => Ok(f64::from(*v)),
+            ValueRef::UInt16(v) => Ok(f64::from(*v)),
+            ValueRef::UInt32(v) => Ok(f64::from(*v)),
+            ValueRef::UInt64(v) => Ok(*v as f64),
+            ValueRef::Int8(v) => Ok(f64::from(*v)),
+            ValueRef::Int16(v) => Ok(f64::from(*v)),
+            ValueRef::Int32(v) => Ok(f64::from(*v)),
+            ValueRef::Int64(v) => Ok(*v as f64),
+            ValueRef::Int256(v) => Ok(v.as_f64()),
+            ValueRef::UInt256(v) => Ok(v.as_f64()),
+            ValueRef::Float32(v) => Ok(f64::from(*v)),
+            ValueRef::Float64(v) => Ok(*v),
+            ValueRef::Nullable(Either::Left(_)) => {
+                Err(Error::FromSql(FromSqlError::UnexpectedNull))
+            }
+            ValueRef::Nullable(Either::Right(inner)) => inner.as_f64(),
+            _ => {
+                let from = SqlType::from(self.clone()).to_string();
+                Err(Error::FromSql(FromSqlError::InvalidType {
+                    src: from,
+                    dst: "f64".into(),
+                }))
+            }
+        }
+    }
 }
 
 impl<'a> From<ValueRef<'a>> for Value {
     fn from(borrowed: ValueRef<'a>) -> Self {
         match borrowed {
+            ValueRef::Bool(v) => Value::Bool(v),
             ValueRef::UInt8(v) => Value::UInt8(v),
             ValueRef::UInt16(v) => Value::UInt16(v),
             ValueRef::UInt32(v) => Value::UInt32(v),
@@ -161,6 +351,7 @@ impl<'a> From<ValueRef<'a>> for Value {
             ValueRef::Int32(v) => Value::Int32(v),
             ValueRef::Int64(v) => Value::Int64(v),
             ValueRef::Int256(v) => Value::Int256(v),
+            ValueRef::UInt256(v) => Value::UInt256(v),
             ValueRef::String(v) => Value::String(Arc::new(v.into())),
             ValueRef::Float32(v) => Value::Float32(v),
             ValueRef::Float64(v) => Value::Float64(v),
@@ -179,6 +370,17 @@ impl<'a> From<ValueRef<'a>> for Value {
                 }
                 Value::Array(t, Arc::new(value_list))
             }
+            ValueRef::Map(k, v, entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| (key.clone().into(), value.clone().into()))
+                    .collect();
+                Value::Map(k, v, Arc::new(entries))
+            }
+            ValueRef::Tuple(vs) => {
+                let value_list = vs.iter().map(|v| v.clone().into()).collect();
+                Value::Tuple(Arc::new(value_list))
+            }
         }
     }
 }
@@ -218,6 +420,7 @@ from_number! {
     i32: Int32,
     i64: Int64,
     I256: Int256,
+    U256: UInt256,
 
     f32: Float32,
     f64: Float64
@@ -226,6 +429,7 @@ from_number! {
 impl<'a> From<&'a Value> for ValueRef<'a> {
     fn from(value: &'a Value) -> ValueRef<'a> {
         match value {
+            Value::Bool(v) => ValueRef::Bool(*v),
             Value::UInt8(v) => ValueRef::UInt8(*v),
             Value::UInt16(v) => ValueRef::UInt16(*v),
             Value::UInt32(v) => ValueRef::UInt32(*v),
@@ -235,6 +439,7 @@ impl<'a> From<&'a Value> for ValueRef<'a> {
             Value::Int32(v) => ValueRef::Int32(*v),
             Value::Int64(v) => ValueRef::Int64(*v),
             Value::Int256(v) => ValueRef::Int256(*v),
+            Value::UInt256(v) => ValueRef::UInt256(*v),
             Value::String(v) => ValueRef::String(v),
             Value::Float32(v) => ValueRef::Float32(*v),
             Value::Float64(v) => ValueRef::Float64(*v),
@@ -253,6 +458,17 @@ impl<'a> From<&'a Value> for ValueRef<'a> {
                 }
                 ValueRef::Array(t, Arc::new(ref_vec))
             }
+            Value::Map(k, v, entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| (From::from(key), From::from(value)))
+                    .collect();
+                ValueRef::Map(k, v, Arc::new(entries))
+            }
+            Value::Tuple(vs) => {
+                let ref_vec = vs.iter().map(From::from).collect();
+                ValueRef::Tuple(Arc::new(ref_vec))
+            }
         }
     }
 }
@@ -260,6 +476,7 @@ impl<'a> From<&'a Value> for ValueRef<'a> {
 macro_rules! value_from {
     ( $( $t:ty: $k:ident ),* ) => {
         $(
+            /// Panics on a type mismatch, see `ValueRef::get`.
             impl<'a> From<ValueRef<'a>> for $t {
                 fn from(value: ValueRef<'a>) -> Self {
                     if let ValueRef::$k(v) = value {
@@ -285,6 +502,7 @@ value_from! {
     i32: Int32,
     i64: Int64,
     I256: Int256,
+    U256: UInt256,
 
     f32: Float32,
     f64: Float64
@@ -294,15 +512,207 @@ value_from! {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_as_str_trimmed() {
+        assert_eq!(ValueRef::String(b"ab\0\0").as_str_trimmed().unwrap(), "ab");
+        assert_eq!(
+            ValueRef::String(b"a\0b\0").as_str_trimmed().unwrap(),
+            "a\0b"
+        );
+        assert_eq!(ValueRef::String(b"\0\0").as_str_trimmed().unwrap(), "");
+        assert_eq!(ValueRef::String(b"ab\0").as_str().unwrap(), "ab\0");
+        assert!(ValueRef::UInt8(1).as_str_trimmed().is_err());
+    }
+
+    #[test]
+    fn test_hash() {
+        use std::collections::HashSet;
+
+        let values = vec![
+            ValueRef::Float32(1.5),
+            ValueRef::Float64(-0.0),
+            ValueRef::Float64(0.0),
+            ValueRef::Nullable(Either::Left(SqlType::Float64.into())),
+            ValueRef::Nullable(Either::Right(Box::new(ValueRef::Float64(0.0)))),
+            ValueRef::Array(
+                SqlType::Float64.into(),
+                Arc::new(vec![ValueRef::Float64(1.0), ValueRef::Float64(2.0)]),
+            ),
+            ValueRef::Map(
+                SqlType::String.into(),
+                SqlType::Float32.into(),
+                Arc::new(vec![(ValueRef::String(b"a"), ValueRef::Float32(1.0))]),
+            ),
+            ValueRef::Tuple(Arc::new(vec![ValueRef::UInt8(1), ValueRef::Float32(1.0)])),
+            ValueRef::UInt8(1),
+            ValueRef::UInt16(1),
+        ];
+
+        let set: HashSet<ValueRef> = values.iter().cloned().collect();
+        assert_eq!(set.len(), values.len() - 1);
+        assert!(set.contains(&ValueRef::Float64(0.0)));
+
+        let set: HashSet<Value> = values.into_iter().map(Value::from).collect();
+        assert!(set.contains(&Value::Float32(1.5)));
+    }
+
+    #[test]
+    fn test_ord() {
+        assert!(ValueRef::UInt32(1) < ValueRef::UInt32(2));
+        assert!(ValueRef::Int64(-1) < ValueRef::Int64(0));
+        assert!(ValueRef::String(b"abc") < ValueRef::String(b"abd"));
+        assert!(ValueRef::String(b"ab") < ValueRef::String(b"abc"));
+        assert!(ValueRef::Float64(-0.5) < ValueRef::Float64(f64::NAN));
+
+        let null = ValueRef::Nullable(Either::Left(SqlType::Int32.into()));
+        let some = |v| ValueRef::Nullable(Either::Right(Box::new(ValueRef::Int32(v))));
+        assert!(some(i32::MAX) < null);
+        assert!(some(1) < some(2));
+        assert_eq!(null.cmp(&null.clone()), Ordering::Equal);
+
+        let array = |vs: Vec<u8>| {
+            let vs = vs.into_iter().map(ValueRef::UInt8).collect();
+            ValueRef::Array(SqlType::UInt8.into(), Arc::new(vs))
+        };
+        assert!(array(vec![1, 2]) < array(vec![1, 3]));
+        assert!(array(vec![1, 2]) < array(vec![1, 2, 0]));
+        assert!(array(vec![]) < array(vec![0]));
+
+        assert!(ValueRef::Int32(i32::MAX) < ValueRef::String(b""));
+        assert!(ValueRef::String(b"zzz") > ValueRef::Int32(0));
+        assert_eq!(
+            ValueRef::UInt8(1).cmp(&ValueRef::UInt16(1)),
+            ValueRef::UInt8(2).cmp(&ValueRef::UInt16(0))
+        );
+    }
+
+    #[test]
+    fn test_float_eq_ord_hash_agree() {
+        use std::collections::{hash_map::DefaultHasher, BTreeSet, HashSet};
+
+        fn hash(value: &ValueRef) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let pairs = [
+            (ValueRef::Float64(-0.0), ValueRef::Float64(0.0)),
+            (ValueRef::Float32(-0.0), ValueRef::Float32(0.0)),
+            (ValueRef::Float64(f64::NAN), ValueRef::Float64(f64::NAN)),
+            (ValueRef::Float64(f64::NAN), ValueRef::Float64(-f64::NAN)),
+            (ValueRef::Float32(f32::NAN), ValueRef::Float32(-f32::NAN)),
+        ];
+        for (a, b) in &pairs {
+            assert_eq!(a, b);
+            assert_eq!(a.cmp(b), Ordering::Equal);
+            assert_eq!(hash(a), hash(b));
+            assert_eq!(Value::from(a.clone()), Value::from(b.clone()));
+            assert_eq!(
+                Value::from(a.clone()).cmp(&Value::from(b.clone())),
+                Ordering::Equal
+            );
+        }
+
+        assert!(ValueRef::Float64(f64::INFINITY) < ValueRef::Float64(f64::NAN));
+        assert!(ValueRef::Float64(-f64::NAN) > ValueRef::Float64(f64::NEG_INFINITY));
+
+        let values = [-0.0, 0.0, f64::NAN, -f64::NAN, 1.0].map(ValueRef::Float64);
+        let hashed: HashSet<_> = values.iter().cloned().collect();
+        let ordered: BTreeSet<_> = values.iter().cloned().collect();
+        assert_eq!(hashed.len(), 3);
+        assert_eq!(ordered.len(), 3);
+    }
+
+    #[test]
+    fn test_as_f64() {
+        assert_eq!(ValueRef::UInt8(42).as_f64().unwrap(), 42.0);
+        assert_eq!(ValueRef::Int64(-42).as_f64().unwrap(), -42.0);
+        assert_eq!(ValueRef::Float32(0.5).as_f64().unwrap(), 0.5);
+        assert_eq!(
+            ValueRef::UInt64(u64::MAX).as_f64().unwrap(),
+            18446744073709552000.0
+        );
+        assert_eq!(
+            ValueRef::Int256(I256::MAX).as_f64().unwrap(),
+            2_f64.powi(255)
+        );
+        assert_eq!(
+            ValueRef::Int256(I256::MIN).as_f64().unwrap(),
+            -(2_f64.powi(255))
+        );
+        assert_eq!(
+            ValueRef::UInt256(U256::MAX).as_f64().unwrap(),
+            2_f64.powi(256)
+        );
+
+        let value = ValueRef::Nullable(Either::Right(Box::new(ValueRef::Int32(7))));
+        assert_eq!(value.as_f64().unwrap(), 7.0);
+
+        let null = ValueRef::Nullable(Either::Left(SqlType::Int32.into()));
+        match null.as_f64() {
+            Err(Error::FromSql(FromSqlError::UnexpectedNull)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        assert!(ValueRef::String(b"42").as_f64().is_err());
+        assert!(ValueRef::Bool(true).as_f64().is_err());
+        let value = ValueRef::Nullable(Either::Right(Box::new(ValueRef::String(b"42"))));
+        assert!(value.as_f64().is_err());
+    }
+
+    #[test]
+    fn test_display_nested_nulls() {
+        let null = || ValueRef::Nullable(Either::Left(SqlType::String.into()));
+        let some = |s: &'static str| {
+            ValueRef::Nullable(Either::Right(Box::new(ValueRef::String(s.as_bytes()))))
+        };
+        let item_type = SqlType::Nullable(SqlType::String.into());
+
+        let array = ValueRef::Array(
+            item_type.clone().into(),
+            Arc::new(vec![some("a"), null(), some("")]),
+        );
+        assert_eq!(array.to_string(), "[a, NULL, ]");
+
+        let tuple = ValueRef::Tuple(Arc::new(vec![null(), array]));
+        assert_eq!(tuple.to_string(), "(NULL, [a, NULL, ])");
+
+        let nested = ValueRef::Array(
+            SqlType::Array(item_type.into()).into(),
+            Arc::new(vec![ValueRef::Array(
+                SqlType::String.into(),
+                Arc::new(vec![null()]),
+            )]),
+        );
+        assert_eq!(nested.to_string(), "[[NULL]]");
+    }
+
+    #[test]
+    fn test_get() {
+        let value = ValueRef::Int64(-3);
+        assert_eq!(value.get::<i64>().unwrap(), -3);
+        assert!(value.get::<u8>().is_err());
+        assert_eq!(ValueRef::String(b"foo").get::<&str>().unwrap(), "foo");
+    }
+
     #[test]
     fn test_display() {
         assert_eq!(
-            "[0, 159, 146, 150]".to_string(),
+            "0x009f9296(4 bytes)".to_string(),
             format!("{}", ValueRef::String(&[0, 159, 146, 150]))
         );
 
+        let blob: Vec<u8> = (0..1024).map(|i| (i % 256) as u8 ^ 0x80).collect();
+        assert_eq!(
+            "0x8081828384858687\u{2026}(1024 bytes)".to_string(),
+            format!("{}", ValueRef::String(&blob))
+        );
+
         assert_eq!("text".to_string(), format!("{}", ValueRef::String(b"text")));
 
+        assert_eq!("true".to_string(), format!("{}", ValueRef::Bool(true)));
+
         assert_eq!("42".to_string(), format!("{}", ValueRef::UInt8(42)));
         assert_eq!("42".to_string(), format!("{}", ValueRef::UInt16(42)));
         assert_eq!("42".to_string(), format!("{}", ValueRef::UInt32(42)));
@@ -396,6 +806,11 @@ mod test {
         assert_eq!(SqlType::from(ValueRef::Int32(42)), SqlType::Int32);
         assert_eq!(SqlType::from(ValueRef::Int64(42)), SqlType::Int64);
 
+        assert_eq!(
+            SqlType::from(ValueRef::UInt256(U256::ONE)),
+            SqlType::UInt256
+        );
+
         assert_eq!(SqlType::from(ValueRef::Float32(42.0)), SqlType::Float32);
         assert_eq!(SqlType::from(ValueRef::Float64(42.0)), SqlType::Float64);
 